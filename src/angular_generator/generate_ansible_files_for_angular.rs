@@ -7,6 +7,13 @@ use std::{
 
 use serde_json::Value;
 
+use crate::ci_generator::ansible_inventory::{write_ansible_inventory, InventorySettings};
+use crate::ci_generator::ansible_releases::{
+    apply_release_placeholders, write_rollback_playbook, ReleaseSettings,
+};
+use crate::ci_generator::ansible_role::{write_ansible_role, AnsibleRoleSettings};
+use crate::ci_generator::deployment_pipeline::{indent_lines, DeploymentPipeline};
+use crate::ci_generator::image_registry::IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT;
 use crate::constants::SLEEP_DURATION;
 
 pub fn generate_ansible_files_for_angular() -> io::Result<()> {
@@ -80,84 +87,69 @@ pub fn generate_ansible_files_for_angular() -> io::Result<()> {
     thread::sleep(SLEEP_DURATION);
     fs::create_dir_all(&ansible_dir)?;
 
-    // Step 4: Copy `hosts.yml` to the `ansible` directory
-    println!("📋 Creating `hosts.yml` file...");
-    thread::sleep(SLEEP_DURATION);
-    let hosts_content = r#"
-target_servers:
-  hosts:
-    {{ ansible_host_name_placeholder }}:
-      ansible_host: "{{ ansible_host_placeholder }}"
-      ansible_user: "{{ ansible_user_placeholder }}"
-      ansible_ssh_port: {{ ansible_ssh_port_placeholder }}
-      ansible_become_user: root
-      ansible_become_pass: "{{ ansible_become_pass_placeholder }}"
-      ansible_ssh_common_args: '-o StrictHostKeyChecking=no'
-"#;
-
-    let hosts_file_path = ansible_dir.join("hosts.yml");
-    let mut hosts_file = File::create(&hosts_file_path)?;
-    hosts_file.write_all(hosts_content.as_bytes())?;
-    println!("✅ `hosts.yml` file created at {:?}", hosts_file_path);
+    // Step 4: Write `hosts.yml` with its `group_vars` and `host_vars` to the `ansible` directory
+    write_ansible_inventory(&config, &ansible_dir)?;
+    let inventory_settings = InventorySettings::from_config(&config)?;
+    let release_settings = ReleaseSettings::from_config(&config)?;
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+
+    // With `ansible.role`, the playbook only applies a generated role
+    if let Some(role_settings) = AnsibleRoleSettings::from_config(&config)? {
+        write_ansible_role(&config, &ansible_dir, &role_settings)?;
+        println!("🎉 Ansible File Copy Process Completed Successfully!");
+        return Ok(());
+    }
 
     // Step 5: Copy `ansible-deploy.yml` to the `ansible` directory, replacing placeholders
     println!("📋 Creating `ansible-deploy.yml` file...");
     thread::sleep(SLEEP_DURATION);
-    // Step 5: Copy `ansible-deploy.yml` to the `ansible` directory, replacing placeholders
     let ansible_deploy_content = r#"---
 - name: Deploy files and start docker compose
   hosts: target_servers
-  become: yes
+[[ play_options ]]  become: yes
   vars:
     project_directory: "[[ project_directory ]]"
     project_location: "[[ project_location ]]" # This is for both local and remote server
   tasks:
-    - name: Remove the existing remote directory
-      ansible.builtin.file:
-        path: "{{ project_location }}/{{ project_directory }}"
-        state: absent
-    
-    - name: Ensure the destination directory exists on the remote server
-      ansible.builtin.file:
-        path: "{{ project_location }}/{{ project_directory }}"
-        state: directory
-        mode: '0755'
-
-    - name: Copy files to the remote server
-      ansible.builtin.copy:
-        src: "{{ project_location }}/{{ project_directory }}"
-        dest: "{{ project_location }}"
-        mode: '0755'
+[[ copy_tasks ]]
 
     - name: Run docker compose to start the services
       ansible.builtin.shell:
         cmd: |
           echo "Current Directory:"
           pwd
-          # Run docker compose up and retry once if it fails
-          if ! docker compose up --build -d --remove-orphans; then
-            echo "docker compose failed. Retrying with cache clearing..."
-            echo "Stopping and removing existing containers..."
-            docker compose down || echo "Failed to stop containers, continuing..."
-            echo "Rebuilding containers without cache..."
-            docker compose build --no-cache
-            if ! docker compose up -d --remove-orphans; then
-              echo "Retry failed. Exiting..."
-              exit 1
-            fi
-          fi
-        chdir: "{{ project_location }}/{{ project_directory }}"
+[[ compose_up ]]
+        chdir: "[[ compose_directory ]]"
       register: docker_compose_result
-      ignore_errors: false
-    
+      environment:
+        COMPOSE_FILE: "{{ compose_file | default('compose.yaml') }}"
+[[ image_registry_environment ]][[ compose_project_name ]]      ignore_errors: [[ ignore_compose_errors ]]
+[[ compose_when ]]    
     - name: Display docker compose result
       debug:
         var: docker_compose_result.stdout
-"#;
-
-    let updated_ansible_deploy_content = ansible_deploy_content
-        .replace("[[ project_location ]]", &project_location)
-        .replace("[[ project_directory ]]", &project_directory);
+[[ release_tasks ]]"#;
+
+    let updated_ansible_deploy_content =
+        apply_release_placeholders(ansible_deploy_content, release_settings.as_ref(), None)
+            .replace(
+                "[[ image_registry_environment ]]",
+                match pipeline.image_registry {
+                    Some(_) => IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT,
+                    None => "",
+                },
+            )
+            .replace("[[ compose_when ]]", pipeline.compose_up_condition())
+            .replace(
+                "[[ compose_up ]]",
+                &indent_lines(pipeline.compose_up_script(), 10),
+            )
+            .replace(
+                "[[ play_options ]]",
+                &inventory_settings.render_play_options(),
+            )
+            .replace("[[ project_location ]]", &project_location)
+            .replace("[[ project_directory ]]", &project_directory);
 
     let ansible_deploy_file_path = ansible_dir.join("ansible-deploy.yml");
     let mut ansible_deploy_file = File::create(&ansible_deploy_file_path)?;
@@ -167,6 +159,11 @@ target_servers:
         ansible_deploy_file_path
     );
 
+    // With `ansible.keep_releases`, a rollback playbook switches back to the previous release
+    if release_settings.is_some() {
+        write_rollback_playbook(&config, &ansible_dir, None)?;
+    }
+
     println!("🎉 Ansible File Copy Process Completed Successfully!");
     Ok(())
 }
@@ -4,7 +4,11 @@ use std::io::{self, Read, Write};
 use std::path::Path;
 use std::{env, thread};
 
+use crate::ci_generator::image_registry::ImageRegistrySettings;
+use crate::ci_generator::reverse_proxy::{write_reverse_proxy_files, ReverseProxySettings};
+use crate::ci_generator::swarm_stack::write_swarm_stack_files;
 use crate::constants::SLEEP_DURATION;
+use crate::dotnet_generator::generate_compose_file_for_dotnet::DOTNET_COMPOSE_NETWORKS;
 
 pub fn generate_compose_file_for_angular() -> io::Result<()> {
     println!("🚀 Starting docker-compose file generation...");
@@ -27,42 +31,21 @@ pub fn generate_compose_file_for_angular() -> io::Result<()> {
     thread::sleep(SLEEP_DURATION);
 
     let config: Value = serde_json::from_str(&config_content)?;
-
-    // Extract configuration values
-    let service_name = config["service_name"].as_str().unwrap_or("default_service");
-    let image_name = config["image_name"].as_str().unwrap_or("default_image");
-    let container_name = config["container_name"]
-        .as_str()
-        .unwrap_or("default_container");
-    let port = config["port"].as_u64().unwrap_or(5000);
-
-    println!("⚙️  Extracted config values: service_name = {}, image_name = {}, container_name = {}, port = {}",
-        service_name, image_name, container_name, port);
-    thread::sleep(SLEEP_DURATION);
-
-    // Base template without the healthcheck block
-    let template = format!(
-        r#"
-services:
-  {service_name}:
-    image: "{image_name}"
-    container_name: "{container_name}"
-    restart: unless-stopped
-    build:
-      context: .
-      dockerfile: Dockerfile
-    ports:
-      - '{port}:80'
-    volumes:
-      - ./node_modules:/app/node_modules
-    environment:
-      NODE_ENV: production
-"#,
-        service_name = service_name,
-        image_name = image_name,
-        container_name = container_name,
-        port = port,
-    );
+    let image_registry = ImageRegistrySettings::from_config(&config)?;
+    let reverse_proxy = ReverseProxySettings::from_config(&config)?;
+
+    let mut template = String::from("\nservices:\n");
+    template.push_str(&render_compose_service_for_angular(
+        &config,
+        ".",
+        image_registry.as_ref(),
+        reverse_proxy.as_ref(),
+    ));
+    if reverse_proxy.as_ref().is_some_and(|proxy| {
+        proxy.is_routed(config["service_name"].as_str().unwrap_or("default_service"))
+    }) {
+        template.push_str(DOTNET_COMPOSE_NETWORKS);
+    }
 
     // Determine the output directory based on build mode
     #[cfg(debug_assertions)]
@@ -125,6 +108,10 @@ services:
     output_file.write_all(template.as_bytes())?;
     thread::sleep(SLEEP_DURATION);
 
+    // Swarm hosts deploy the same services as a stack
+    write_swarm_stack_files(&config, &output_dir)?;
+    write_reverse_proxy_files(&config, &output_dir)?;
+
     println!(
         "🎉 Compose file generated successfully at {:?}",
         output_path
@@ -133,3 +120,91 @@ services:
 
     Ok(())
 }
+
+/// Renders the compose service definition for an Angular project, indented to sit under `services:`.
+///
+/// # Parameters
+/// - `config`: The Angular project configuration.
+/// - `build_context`: The build context of the service, relative to the compose file.
+/// - `image_registry`: The registry the image is pulled from, which leaves the `build` section to
+///   `compose.build.yaml`, or `None` to build the image on the server.
+/// - `reverse_proxy`: The proxy routing a hostname to the service, which then joins the
+///   `api-network`, or `None`.
+///
+/// # Returns
+/// - `String`: The service definition.
+pub fn render_compose_service_for_angular(
+    config: &Value,
+    build_context: &str,
+    image_registry: Option<&ImageRegistrySettings>,
+    reverse_proxy: Option<&ReverseProxySettings>,
+) -> String {
+    // Extract configuration values
+    let service_name = config["service_name"].as_str().unwrap_or("default_service");
+    let image_name = config["image_name"].as_str().unwrap_or("default_image");
+    let container_name = config["container_name"]
+        .as_str()
+        .unwrap_or("default_container");
+    let port = config["port"].as_u64().unwrap_or(5000);
+    let dockerfile = config["dockerfile"].as_str().unwrap_or("Dockerfile");
+
+    println!("⚙️  Extracted config values: service_name = {}, image_name = {}, container_name = {}, port = {}",
+        service_name, image_name, container_name, port);
+    thread::sleep(SLEEP_DURATION);
+
+    // Images pulled from the registry are built by the CI pipeline, without the sources on the
+    // server to mount the dependencies from
+    let mut template = if let Some(image_registry) = image_registry {
+        format!(
+            r#"  {service_name}:
+    image: "{image}"
+    container_name: "{container_name}"
+    restart: unless-stopped
+    ports:
+      - '{port}:80'
+    environment:
+      NODE_ENV: production
+"#,
+            service_name = service_name,
+            image = image_registry.image(image_name),
+            container_name = container_name,
+            port = port,
+        )
+    } else {
+        // Base template without the healthcheck block
+        format!(
+            r#"  {service_name}:
+    image: "{image_name}"
+    container_name: "{container_name}"
+    restart: unless-stopped
+    build:
+      context: {build_context}
+      dockerfile: {dockerfile}
+    ports:
+      - '{port}:80'
+    volumes:
+      - {build_context}/node_modules:/app/node_modules
+    environment:
+      NODE_ENV: production
+"#,
+            service_name = service_name,
+            build_context = build_context,
+            dockerfile = dockerfile,
+            image_name = image_name,
+            container_name = container_name,
+            port = port,
+        )
+    };
+
+    // The proxy reaches the routed app on the API network
+    if let Some(reverse_proxy) = reverse_proxy.filter(|proxy| proxy.is_routed(service_name)) {
+        template.push_str(&reverse_proxy.render_compose_labels(service_name));
+        template.push_str(
+            r#"    networks:
+      - api-network
+"#,
+        );
+    }
+
+    template
+}
@@ -35,6 +35,49 @@ pub fn generate_dockerfile_for_angular() -> io::Result<()> {
 
     // Parse the JSON file
     let config: Value = serde_json::from_str(&config_content)?;
+    let updated_dockerfile = render_dockerfile_for_angular(&config)?;
+
+    // Write Dockerfile to output directory
+    #[cfg(debug_assertions)]
+    let output_dir = current_dir.join("testing_directory").join("dotnet-testing");
+    #[cfg(not(debug_assertions))]
+    let output_dir = current_dir.clone();
+
+    println!("📁 Ensuring output directory exists: {:?}", output_dir);
+    fs::create_dir_all(&output_dir)?;
+    thread::sleep(SLEEP_DURATION);
+
+    // Create a backup file path
+    let output_path = output_dir.join("Dockerfile");
+    let backup_path = output_dir.join("Dockerfile.backup");
+
+    // Check if Dockerfile exists and create a backup
+    if output_path.exists() {
+        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
+        fs::rename(&output_path, &backup_path)?;
+        println!("✅ Backup created successfully.");
+    }
+
+    // Write the updated Dockerfile
+    let mut output_file = File::create(&output_path)?;
+    output_file.write_all(updated_dockerfile.as_bytes())?;
+
+    println!("✅ Dockerfile written to: {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    println!("🎉 Dockerfile generation completed successfully!");
+    thread::sleep(SLEEP_DURATION);
+    Ok(())
+}
+
+/// Renders the Dockerfile for an Angular project from its configuration.
+///
+/// # Parameters
+/// - `config`: The Angular project configuration.
+///
+/// # Returns
+/// - `io::Result<String>`: The Dockerfile content, or an error if a required value is missing.
+pub fn render_dockerfile_for_angular(config: &Value) -> io::Result<String> {
     let node_version = config["node_version"]
         .as_str()
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing 'node_version'"))?
@@ -42,6 +85,17 @@ pub fn generate_dockerfile_for_angular() -> io::Result<()> {
     println!("📂 Found Node Version: {}", node_version);
     thread::sleep(SLEEP_DURATION);
 
+    // Workspaces with several applications build and copy only the selected one
+    let build_command = config["build_command"]
+        .as_str()
+        .unwrap_or("npm run build:prod");
+    let dist_path = config["dist_path"]
+        .as_str()
+        .unwrap_or("dist/angular-app/browser");
+    println!("📂 Using build command: {}", build_command);
+    println!("📂 Using dist path: {}", dist_path);
+    thread::sleep(SLEEP_DURATION);
+
     // Prepare Dockerfile template
     let docker_template = r#"
 ### STAGE 1: Build ###
@@ -64,7 +118,7 @@ RUN npm install --legacy-peer-deps
 COPY . .
 
 # Build the app
-RUN npm run build:prod
+RUN {{ build_command }}
 
 ### STAGE 2: Run ###
 # Use an official Nginx image
@@ -74,42 +128,15 @@ FROM nginx:1.24.0-alpine
 COPY nginx.conf /etc/nginx/nginx.conf
 
 # Copying New angular build files to nginx default folder
-COPY --from=build /app/dist/angular-app/browser /usr/share/nginx/html
+COPY --from=build /app/{{ dist_path }} /usr/share/nginx/html
 "#;
 
-    let updated_dockerfile = docker_template.replace("{{ node_version }}", "20.10.0");
+    let updated_dockerfile = docker_template
+        .replace("{{ node_version }}", &node_version)
+        .replace("{{ build_command }}", build_command)
+        .replace("{{ dist_path }}", dist_path);
     println!("✅ Dockerfile template updated with project directory.");
     thread::sleep(SLEEP_DURATION);
 
-    // Write Dockerfile to output directory
-    #[cfg(debug_assertions)]
-    let output_dir = current_dir.join("testing_directory").join("dotnet-testing");
-    #[cfg(not(debug_assertions))]
-    let output_dir = current_dir.clone();
-
-    println!("📁 Ensuring output directory exists: {:?}", output_dir);
-    fs::create_dir_all(&output_dir)?;
-    thread::sleep(SLEEP_DURATION);
-
-    // Create a backup file path
-    let output_path = output_dir.join("Dockerfile");
-    let backup_path = output_dir.join("Dockerfile.backup");
-
-    // Check if Dockerfile exists and create a backup
-    if output_path.exists() {
-        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
-        fs::rename(&output_path, &backup_path)?;
-        println!("✅ Backup created successfully.");
-    }
-
-    // Write the updated Dockerfile
-    let mut output_file = File::create(&output_path)?;
-    output_file.write_all(updated_dockerfile.as_bytes())?;
-
-    println!("✅ Dockerfile written to: {:?}", output_path);
-    thread::sleep(SLEEP_DURATION);
-
-    println!("🎉 Dockerfile generation completed successfully!");
-    thread::sleep(SLEEP_DURATION);
-    Ok(())
+    Ok(updated_dockerfile)
 }
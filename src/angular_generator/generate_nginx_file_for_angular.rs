@@ -17,31 +17,7 @@ pub fn generate_nginx_file_angular() -> io::Result<()> {
     println!("📂 Current directory: {:?}", current_dir);
     thread::sleep(SLEEP_DURATION);
 
-    // Base Nginx configuration template
-    let template = r#"
-events {}
-
-http {
-    include       /etc/nginx/mime.types;
-    default_type  application/octet-stream;
-
-    server {
-        listen       80;
-
-        root   /usr/share/nginx/html;
-        index  index.html;
-
-        location / {
-            try_files $uri $uri/ /index.html;
-        }
-
-        error_page   500 502 503 504  /50x.html;
-        location = /50x.html {
-            root   /usr/share/nginx/html;
-        }
-    }
-}
-"#;
+    let template = render_nginx_file_for_angular();
 
     // Determine the output directory based on the build mode
     let output_dir = if cfg!(debug_assertions) {
@@ -83,3 +59,36 @@ http {
 
     Ok(())
 }
+
+/// Renders the nginx configuration used to serve the Angular build.
+///
+/// # Returns
+/// - `String`: The `nginx.conf` content.
+pub fn render_nginx_file_for_angular() -> String {
+    // Base Nginx configuration template
+    r#"
+events {}
+
+http {
+    include       /etc/nginx/mime.types;
+    default_type  application/octet-stream;
+
+    server {
+        listen       80;
+
+        root   /usr/share/nginx/html;
+        index  index.html;
+
+        location / {
+            try_files $uri $uri/ /index.html;
+        }
+
+        error_page   500 502 503 504  /50x.html;
+        location = /50x.html {
+            root   /usr/share/nginx/html;
+        }
+    }
+}
+"#
+    .to_string()
+}
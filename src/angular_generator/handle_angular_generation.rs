@@ -5,10 +5,15 @@ use crate::{
         generate_ansible_files_for_angular::generate_ansible_files_for_angular,
         generate_compose_file_for_angular::generate_compose_file_for_angular,
         generate_dockerfile_for_angular::generate_dockerfile_for_angular,
-        generate_gitlab_ci_file_for_angular::generate_gitlab_ci_file_for_angular,
         generate_nginx_file_for_angular::generate_nginx_file_angular,
     },
+    ci_generator::{
+        blue_green::generate_blue_green_files,
+        generate_compose_overrides::generate_compose_overrides,
+        handle_ci_generation::handle_ci_generation, image_registry::generate_image_registry_files,
+    },
     constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::generate_helm_charts,
 };
 
 pub fn handle_angular_generation() -> io::Result<()> {
@@ -33,16 +38,50 @@ pub fn handle_angular_generation() -> io::Result<()> {
     }
     println!("✅ Docker Compose file generated successfully!");
 
-    println!("\n🔧 Generating GitLab CI file...");
+    println!("\n🔧 Generating environment overrides...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_compose_overrides() {
+        eprintln!(
+            "❌ Error: Failed to generate environment overrides. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating blue/green deploy files...");
     thread::sleep(SLEEP_DURATION); // Simulate progress
-    if let Err(e) = generate_gitlab_ci_file_for_angular() {
+    if let Err(e) = generate_blue_green_files() {
         eprintln!(
-            "❌ Error: Failed to generate GitLab CI file. Details: {}",
+            "❌ Error: Failed to generate blue/green deploy files. Details: {}",
             e
         );
         std::process::exit(1);
     }
-    println!("✅ GitLab CI file generated successfully!");
+
+    println!("\n🔧 Generating image build files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_image_registry_files() {
+        eprintln!(
+            "❌ Error: Failed to generate image build files. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating Helm charts...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_helm_charts() {
+        eprintln!("❌ Error: Failed to generate Helm charts. Details: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating CI file...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = handle_ci_generation() {
+        eprintln!("❌ Error: Failed to generate CI file. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ CI file generated successfully!");
 
     println!("\n🔧 Copying Ansible files...");
     thread::sleep(SLEEP_DURATION); // Simulate progress
@@ -0,0 +1,345 @@
+use std::{io, path::Path, thread};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry, deploy_environment::DeployEnvironment,
+        deploy_target::render_vars, deployment_pipeline::DeploymentPipeline,
+    },
+    constants::SLEEP_DURATION,
+    shared::output_helper::{make_executable, write_file_with_backup},
+};
+
+/// The encrypted vars file holding the secrets of every server, relative to the repository root.
+pub const VAULT_FILE: &str = "ansible/group_vars/all/vault.yml";
+
+/// The script printing the vault password for `--vault-password-file`, relative to the
+/// repository root.
+pub const VAULT_PASSWORD_SCRIPT: &str = "ansible/vault-password.sh";
+
+/// The inventory wide settings read from the optional `inventory` section of the configuration.
+pub struct InventorySettings {
+    /// The user Ansible becomes on every server.
+    pub become_user: String,
+    /// Whether SSH checks the host keys against `ansible/known_hosts`.
+    pub host_key_checking: bool,
+    /// The `known_hosts` lines written when host key checking is enabled.
+    pub known_hosts: Vec<String>,
+    /// The `serial` value of the deploy play, rendered as YAML, for rolling deploys.
+    pub serial: Option<String>,
+    /// Extra Ansible variables of every server, written to `group_vars/target_servers.yml`.
+    pub vars: Map<String, Value>,
+    /// The CI variable holding the vault password, or `None` when the vault is disabled.
+    pub vault_password_variable: Option<String>,
+}
+
+impl InventorySettings {
+    /// Reads the settings from the optional `inventory` section of the configuration.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<InventorySettings>`: The settings, or an error if `serial` or the vault
+    ///   password variable is invalid.
+    pub fn from_config(config: &Value) -> io::Result<InventorySettings> {
+        let inventory = &config["inventory"];
+
+        let vault_password_variable = if inventory["vault"].as_bool().unwrap_or(false) {
+            let variable = inventory["vault_password_variable"]
+                .as_str()
+                .unwrap_or("ANSIBLE_VAULT_PASSWORD");
+            if variable.is_empty()
+                || variable.starts_with(|c: char| c.is_ascii_digit())
+                || !variable
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid vault password variable: {} (use upper-case letters, digits and underscores)",
+                        variable
+                    ),
+                ));
+            }
+            Some(variable.to_string())
+        } else {
+            None
+        };
+
+        // `serial` takes a host count, a percentage or a list of them for growing batches
+        let is_batch_size = |value: &Value| match value {
+            Value::Number(number) => number.as_u64().is_some_and(|number| number > 0),
+            Value::String(percentage) => percentage
+                .strip_suffix('%')
+                .and_then(|percentage| percentage.parse::<u8>().ok())
+                .is_some_and(|percentage| percentage > 0 && percentage <= 100),
+            _ => false,
+        };
+        let serial = match &inventory["serial"] {
+            Value::Null => None,
+            Value::Array(batches) if !batches.is_empty() && batches.iter().all(is_batch_size) => {
+                Some(inventory["serial"].to_string())
+            }
+            serial if is_batch_size(serial) => Some(serial.to_string()),
+            serial => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid inventory serial: {} (use a host count, a percentage like \"25%\" or a list of them)",
+                        serial
+                    ),
+                ))
+            }
+        };
+
+        Ok(InventorySettings {
+            become_user: inventory["become_user"]
+                .as_str()
+                .unwrap_or("root")
+                .to_string(),
+            host_key_checking: inventory["host_key_checking"].as_bool().unwrap_or(false),
+            known_hosts: inventory["known_hosts"]
+                .as_array()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(|line| line.as_str())
+                        .map(|line| line.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            serial,
+            vars: inventory["vars"].as_object().cloned().unwrap_or_default(),
+            vault_password_variable,
+        })
+    }
+
+    /// Renders the play options for rolling deploys, to be placed below `hosts:` in the playbook.
+    ///
+    /// A failed host stops the remaining batches, so a broken release never reaches every server.
+    pub fn render_play_options(&self) -> String {
+        match &self.serial {
+            Some(serial) => format!("  serial: {}\n  max_fail_percentage: 0\n", serial),
+            None => String::new(),
+        }
+    }
+
+    /// Renders `group_vars/target_servers.yml`, which holds the settings shared by every server.
+    ///
+    /// # Parameters
+    /// - `registry`: The registry the playbook pulls the base images from, which adds the
+    ///   `registry_*` variables.
+    /// - `uses_image_registry`: Whether the servers pull the images from the image registry,
+    ///   which adds the `image_registry_*` variables.
+    ///
+    /// # Returns
+    /// - `String`: The content of the group variables file.
+    pub fn render_group_vars(
+        &self,
+        registry: Option<&BaseImageRegistry>,
+        uses_image_registry: bool,
+    ) -> String {
+        let ssh_common_args = if self.host_key_checking {
+            "\"-o StrictHostKeyChecking=yes -o UserKnownHostsFile={{ inventory_dir }}/known_hosts\""
+        } else {
+            "'-o StrictHostKeyChecking=no'"
+        };
+
+        let mut group_vars = format!(
+            "---\nansible_become_user: {}\nansible_ssh_common_args: {}\n",
+            self.become_user, ssh_common_args
+        );
+
+        // The registry password comes from the vault when it is enabled
+        if let Some(registry) = registry {
+            group_vars
+                .push_str(&registry.render_group_vars(self.vault_password_variable.is_some()));
+        }
+        if uses_image_registry {
+            let image_registry_password = if self.vault_password_variable.is_some() {
+                "vault_image_registry_password"
+            } else {
+                "lookup('env', 'IMAGE_REGISTRY_PASSWORD')"
+            };
+            group_vars.push_str(&format!(
+                r#"image_registry_username: "{{{{ lookup('env', 'IMAGE_REGISTRY_USERNAME') }}}}"
+image_registry_password: "{{{{ {} }}}}"
+"#,
+                image_registry_password
+            ));
+        }
+
+        group_vars.push_str(&render_vars(&self.vars));
+
+        group_vars
+    }
+}
+
+/// Renders the script that prints the vault password from its CI variable, so the password
+/// never touches the disk.
+fn render_vault_password_script(variable: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+# Prints the Ansible Vault password for --vault-password-file
+if [ -z "${{{variable}:-}}" ]; then
+  echo "{variable} is not set" >&2
+  exit 1
+fi
+printf '%s\n' "${variable}"
+"#,
+        variable = variable
+    )
+}
+
+/// Renders the Ansible inventory with one group per environment under `target_servers`.
+///
+/// The connection settings live in `host_vars`, so the inventory only lists the hosts.
+///
+/// # Parameters
+/// - `environments`: The environments of the project.
+///
+/// # Returns
+/// - `String`: The `hosts.yml` content.
+pub fn render_inventory(environments: &[DeployEnvironment]) -> String {
+    let mut inventory = String::from("target_servers:\n");
+
+    match environments {
+        [environment] if !environment.configured => {
+            inventory.push_str("  hosts:\n");
+            for host in &environment.hosts {
+                inventory.push_str(&format!("    {}:\n", host.name));
+            }
+        }
+        _ => {
+            inventory.push_str("  children:\n");
+            for environment in environments {
+                inventory.push_str(&format!("    {}:\n      hosts:\n", environment.host_group));
+                for host in &environment.hosts {
+                    inventory.push_str(&format!("        {}:\n", host.name));
+                }
+            }
+        }
+    }
+
+    inventory
+}
+
+/// Renders the `group_vars` file of a configured environment.
+///
+/// Each environment sets `compose_file`, which the playbook passes to docker compose as
+/// `COMPOSE_FILE`.
+fn render_environment_group_vars(environment: &DeployEnvironment) -> String {
+    format!(
+        "---\ncompose_file: \"{}\"\n{}",
+        environment.compose_file(),
+        render_vars(&environment.vars)
+    )
+}
+
+/// Writes `hosts.yml` with its `group_vars` and `host_vars` to the Ansible directory, plus
+/// `known_hosts` when host key checking is enabled and the vault password script when the vault
+/// is enabled.
+///
+/// An existing `known_hosts` is kept unless the configuration lists the host keys itself. The
+/// vault itself is never written, since only the user can encrypt it.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+/// - `ansible_dir`: The directory the playbook is written to.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn write_ansible_inventory(config: &Value, ansible_dir: &Path) -> io::Result<()> {
+    let pipeline = DeploymentPipeline::from_config(config)?;
+    let environments = &pipeline.environments;
+    let settings = InventorySettings::from_config(config)?;
+
+    let group_vars_dir = ansible_dir.join("group_vars");
+    let host_vars_dir = ansible_dir.join("host_vars");
+
+    println!("📋 Creating `hosts.yml` file...");
+    thread::sleep(SLEEP_DURATION);
+    let hosts_file_path = ansible_dir.join("hosts.yml");
+    write_file_with_backup(&hosts_file_path, &render_inventory(environments))?;
+    println!("✅ `hosts.yml` file created at {:?}", hosts_file_path);
+
+    println!("📋 Creating `group_vars` and `host_vars` files...");
+    thread::sleep(SLEEP_DURATION);
+    write_file_with_backup(
+        &group_vars_dir.join("target_servers.yml"),
+        &settings.render_group_vars(
+            pipeline.server_base_image_registry(),
+            pipeline.image_registry.is_some(),
+        ),
+    )?;
+    for environment in environments {
+        if environment.configured {
+            write_file_with_backup(
+                &group_vars_dir.join(format!("{}.yml", environment.host_group)),
+                &render_environment_group_vars(environment),
+            )?;
+        }
+        for host in &environment.hosts {
+            write_file_with_backup(
+                &host_vars_dir.join(format!("{}.yml", host.name)),
+                &host.render_host_vars(),
+            )?;
+        }
+    }
+    println!("✅ Inventory variables created in {:?}", ansible_dir);
+
+    if settings.host_key_checking {
+        let known_hosts_path = ansible_dir.join("known_hosts");
+        if !settings.known_hosts.is_empty() {
+            write_file_with_backup(
+                &known_hosts_path,
+                &format!("{}\n", settings.known_hosts.join("\n")),
+            )?;
+            println!("🔑 `known_hosts` file created at {:?}", known_hosts_path);
+        } else if !known_hosts_path.exists() {
+            write_file_with_backup(
+                &known_hosts_path,
+                "# Host keys of the deploy servers, checked by SSH before Ansible connects.\n# Add a server with: ssh-keyscan -p <port> <host> >> ansible/known_hosts\n",
+            )?;
+            println!(
+                "⚠️  Add the host keys of your servers to {:?} before deploying.",
+                known_hosts_path
+            );
+        }
+    }
+
+    if let Some(variable) = &settings.vault_password_variable {
+        let script_path = ansible_dir.join("vault-password.sh");
+        write_file_with_backup(&script_path, &render_vault_password_script(variable))?;
+        make_executable(&script_path)?;
+        println!("🔐 Vault password script created at {:?}", script_path);
+
+        let vault_path = ansible_dir.join("group_vars").join("all").join("vault.yml");
+        if !vault_path.exists() {
+            let mut vault_variables: Vec<String> = environments
+                .iter()
+                .flat_map(|environment| &environment.hosts)
+                .map(|host| host.become_pass_vault_variable())
+                .collect();
+            if pipeline
+                .server_base_image_registry()
+                .is_some_and(|registry| registry.login)
+            {
+                vault_variables.push("vault_registry_password".to_string());
+            }
+            if pipeline.image_registry.is_some() {
+                vault_variables.push("vault_image_registry_password".to_string());
+            }
+            println!(
+                "⚠️  Create the vault with `ansible-vault create {}` and define: {}",
+                VAULT_FILE,
+                vault_variables.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,317 @@
+use std::{fs, io, path::Path};
+
+use serde_json::Value;
+
+use crate::ci_generator::ansible_inventory::InventorySettings;
+
+/// The release directories the playbook deploys into, read from the optional `ansible` section
+/// of the configuration.
+///
+/// Each deploy copies the files to `<project_directory>-releases/<timestamp>` next to the project
+/// path and points the `current` symlink in it at the release, so the previous releases stay on
+/// the server for a rollback. They live outside the project path, which the CI deploy jobs empty
+/// and the playbook copies from.
+pub struct ReleaseSettings {
+    /// The number of releases kept on the server, including the current one.
+    pub keep: u64,
+}
+
+impl ReleaseSettings {
+    /// Reads the release settings, if `ansible.keep_releases` is set.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<ReleaseSettings>>`: The settings, `None` to deploy in place, or an
+    ///   error if fewer than two releases are kept or the playbook applies a role.
+    pub fn from_config(config: &Value) -> io::Result<Option<ReleaseSettings>> {
+        let ansible = &config["ansible"];
+        match &ansible["keep_releases"] {
+            Value::Null => Ok(None),
+            _ if ansible["role"].as_bool().unwrap_or(false) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "ansible.keep_releases cannot be combined with ansible.role",
+            )),
+            keep => match keep.as_u64() {
+                Some(keep) if keep >= 2 => Ok(Some(ReleaseSettings { keep })),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid ansible.keep_releases: {} (keep at least 2 releases to roll back to)",
+                        keep
+                    ),
+                )),
+            },
+        }
+    }
+}
+
+const IN_PLACE_COPY_TASKS: &str = r#"    - name: Remove the existing remote directory
+      ansible.builtin.file:
+        path: "{{ project_location }}/{{ project_directory }}"
+        state: absent
+
+    - name: Ensure the destination directory exists on the remote server
+      ansible.builtin.file:
+        path: "{{ project_location }}/{{ project_directory }}"
+        state: directory
+        mode: '0755'
+
+    - name: Copy files to the remote server
+      ansible.builtin.copy:
+        src: "{{ project_location }}/{{ project_directory }}"
+        dest: "{{ project_location }}"
+        mode: '0755'
+"#;
+
+const RELEASE_COPY_TASKS: &str = r#"    - name: Select the release directory
+      ansible.builtin.set_fact:
+        release_path: "{{ project_location }}/{{ project_directory }}-releases/{{ now(utc=true, fmt='%Y%m%d%H%M%S') }}"
+        current_path: "{{ project_location }}/{{ project_directory }}-releases/current"
+
+    - name: Read the current release
+      ansible.builtin.stat:
+        path: "{{ current_path }}"
+      register: previous_release
+
+    - name: Ensure the release directory exists on the remote server
+      ansible.builtin.file:
+        path: "{{ release_path }}"
+        state: directory
+        mode: '0755'
+
+    - name: Copy files to the release directory
+      ansible.builtin.copy:
+        src: "{{ project_location }}/{{ project_directory }}/"
+        dest: "{{ release_path }}/"
+        mode: '0755'
+
+    - name: Point current to the new release
+      ansible.builtin.file:
+        src: "{{ release_path }}"
+        dest: "{{ current_path }}"
+        state: link
+        force: true
+"#;
+
+/// Runs after the compose task, which ignores its errors so a failed release can be replaced by
+/// the previous one before the host is marked as failed.
+const RELEASE_TASKS: &str = r#"
+    - name: Point current back to the previous release
+      ansible.builtin.file:
+        src: "{{ previous_release.stat.lnk_source }}"
+        dest: "{{ current_path }}"
+        state: link
+        force: true
+      when: docker_compose_result is failed and previous_release.stat.islnk | default(false)
+
+    - name: Restart the previous release
+      ansible.builtin.shell:
+        cmd: docker compose up --build -d --remove-orphans
+        chdir: "{{ current_path }}"
+      environment:
+[[ compose_environment ]]      when: docker_compose_result is failed and previous_release.stat.islnk | default(false)
+
+    - name: Remove the failed release
+      ansible.builtin.file:
+        path: "{{ release_path }}"
+        state: absent
+      when: docker_compose_result is failed and previous_release.stat.islnk | default(false)
+
+    - name: Fail the deployment
+      ansible.builtin.fail:
+        msg: "The new release failed to start{{ ', the previous release is running again' if previous_release.stat.islnk | default(false) else '' }}"
+      when: docker_compose_result is failed
+
+    - name: Find the releases
+      ansible.builtin.find:
+        paths: "{{ project_location }}/{{ project_directory }}-releases"
+        file_type: directory
+      register: releases
+
+    - name: Remove the releases beyond the last [[ keep ]]
+      ansible.builtin.file:
+        path: "{{ item }}"
+        state: absent
+      loop: "{{ (releases.files | map(attribute='path') | sort(reverse=true))[[[ keep ]]:] }}"
+"#;
+
+const ROLLBACK_PLAYBOOK: &str = r#"---
+- name: Roll back to the previous release
+  hosts: target_servers
+[[ play_options ]]  become: yes
+  vars:
+    project_directory: "[[ project_directory ]]"
+    project_location: "[[ project_location ]]"
+    current_path: "{{ project_location }}/{{ project_directory }}-releases/current"
+  tasks:
+    - name: Read the current release
+      ansible.builtin.stat:
+        path: "{{ current_path }}"
+      register: current_release
+
+    - name: Find the releases
+      ansible.builtin.find:
+        paths: "{{ project_location }}/{{ project_directory }}-releases"
+        file_type: directory
+      register: releases
+
+    # Release directories are named after their UTC timestamp, so they sort by age
+    - name: Select the previous release
+      ansible.builtin.set_fact:
+        rollback_release: "{{ releases.files | map(attribute='path') | select('lt', current_release.stat.lnk_source | default('')) | sort | last | default('') }}"
+
+    - name: Stop when there is no previous release
+      ansible.builtin.fail:
+        msg: "There is no release older than {{ current_release.stat.lnk_source | default('the current one') }} to roll back to"
+      when: rollback_release == ''
+
+    - name: Point current to the previous release
+      ansible.builtin.file:
+        src: "{{ rollback_release }}"
+        dest: "{{ current_path }}"
+        state: link
+        force: true
+
+    - name: Restart docker compose
+      ansible.builtin.shell:
+        cmd: docker compose up --build -d --remove-orphans
+        chdir: "{{ current_path }}"
+      environment:
+[[ compose_environment ]]      register: docker_compose_result
+
+    - name: Display docker compose result
+      debug:
+        var: docker_compose_result.stdout
+"#;
+
+/// Renders the environment entry passing the registry URL to the `args` of the .NET builds, or
+/// an empty string when the Dockerfiles already name their registry.
+///
+/// # Parameters
+/// - `registry_url_variable`: The variable the compose files read the URL from, e.g.
+///   `SONATYPE_NEXUS_URL`.
+///
+/// # Returns
+/// - `String`: The environment line of the docker compose task.
+pub fn render_registry_environment(registry_url_variable: Option<&str>) -> String {
+    registry_url_variable
+        .map(|variable| {
+            format!(
+                "        {}: \"{{{{ registry_url | default('') }}}}\"\n",
+                variable
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Renders the environment of the docker compose tasks running a release.
+///
+/// The project name is pinned to the project directory, which docker compose would otherwise
+/// derive from the directory it runs in, `current`.
+fn render_compose_environment(registry_url_variable: Option<&str>) -> String {
+    let mut environment = render_registry_environment(registry_url_variable);
+    environment.push_str(
+        r#"        COMPOSE_FILE: "{{ compose_file | default('compose.yaml') }}"
+        COMPOSE_PROJECT_NAME: "{{ project_directory | lower | regex_replace('[^a-z0-9_-]', '') }}"
+"#,
+    );
+    environment
+}
+
+/// Fills the release placeholders of a flat deploy playbook.
+///
+/// The playbook marks where the files are copied with `[[ copy_tasks ]]`, the directory docker
+/// compose runs in with `[[ compose_directory ]]`, the end of the compose task environment with
+/// `[[ compose_project_name ]]`, its `ignore_errors` value with `[[ ignore_compose_errors ]]` and
+/// the end of the tasks with `[[ release_tasks ]]`.
+///
+/// # Parameters
+/// - `playbook`: The playbook template.
+/// - `releases`: The release settings, or `None` to deploy in place.
+/// - `registry_url_variable`: The variable docker compose passes the registry URL of the
+///   Dockerfiles in, if it needs one.
+///
+/// # Returns
+/// - `String`: The playbook with the placeholders replaced.
+pub fn apply_release_placeholders(
+    playbook: &str,
+    releases: Option<&ReleaseSettings>,
+    registry_url_variable: Option<&str>,
+) -> String {
+    match releases {
+        Some(releases) => playbook
+            .replace("[[ copy_tasks ]]", RELEASE_COPY_TASKS)
+            .replace("[[ compose_directory ]]", "{{ current_path }}")
+            .replace(
+                "[[ compose_project_name ]]",
+                "        COMPOSE_PROJECT_NAME: \"{{ project_directory | lower | regex_replace('[^a-z0-9_-]', '') }}\"\n",
+            )
+            .replace("[[ ignore_compose_errors ]]", "true")
+            .replace(
+                "[[ release_tasks ]]",
+                &RELEASE_TASKS
+                    .replace(
+                        "[[ compose_environment ]]",
+                        &render_compose_environment(registry_url_variable),
+                    )
+                    .replace("[[ keep ]]", &releases.keep.to_string()),
+            ),
+        None => playbook
+            .replace("[[ copy_tasks ]]", IN_PLACE_COPY_TASKS)
+            .replace(
+                "[[ compose_directory ]]",
+                "{{ project_location }}/{{ project_directory }}",
+            )
+            .replace("[[ compose_project_name ]]", "")
+            .replace("[[ ignore_compose_errors ]]", "false")
+            .replace("[[ release_tasks ]]", ""),
+    }
+}
+
+/// Writes `ansible-rollback.yml`, which points `current` back to the release before it and
+/// restarts docker compose there.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+/// - `ansible_dir`: The directory the playbook is written to.
+/// - `registry_url_variable`: The variable docker compose passes the registry URL of the
+///   Dockerfiles in, if it needs one.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or the file cannot be written.
+pub fn write_rollback_playbook(
+    config: &Value,
+    ansible_dir: &Path,
+    registry_url_variable: Option<&str>,
+) -> io::Result<()> {
+    let inventory_settings = InventorySettings::from_config(config)?;
+
+    let rollback_playbook = ROLLBACK_PLAYBOOK
+        .replace(
+            "[[ play_options ]]",
+            &inventory_settings.render_play_options(),
+        )
+        .replace(
+            "[[ compose_environment ]]",
+            &render_compose_environment(registry_url_variable),
+        )
+        .replace(
+            "[[ project_location ]]",
+            config["project_location"].as_str().unwrap_or_default(),
+        )
+        .replace(
+            "[[ project_directory ]]",
+            config["project_directory"].as_str().unwrap_or_default(),
+        );
+
+    let rollback_playbook_path = ansible_dir.join("ansible-rollback.yml");
+    fs::write(&rollback_playbook_path, rollback_playbook)?;
+    println!(
+        "✅ `ansible-rollback.yml` file created at {:?}",
+        rollback_playbook_path
+    );
+
+    Ok(())
+}
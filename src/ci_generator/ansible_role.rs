@@ -0,0 +1,306 @@
+use std::{fs, io, path::Path, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::InventorySettings, deploy_environment::get_compose_services,
+        deployment_pipeline::DeploymentPipeline,
+    },
+    constants::SLEEP_DURATION,
+};
+
+/// The role generated instead of the flat playbook, read from the optional `ansible` section of
+/// the configuration.
+pub struct AnsibleRoleSettings {
+    /// The role name, which also prefixes the role variables, e.g. `shop_api`.
+    pub name: String,
+}
+
+impl AnsibleRoleSettings {
+    /// Reads the role settings, if `ansible.role` is enabled.
+    ///
+    /// The role is named after the service, or after the project directory for monorepos, unless
+    /// `ansible.role_name` is set.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<AnsibleRoleSettings>>`: The settings, `None` for the flat playbook, or
+    ///   an error if the role name is invalid.
+    pub fn from_config(config: &Value) -> io::Result<Option<AnsibleRoleSettings>> {
+        let ansible = &config["ansible"];
+        if !ansible["role"].as_bool().unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let default_name = match config["project_type"].as_str() {
+            Some("monorepo") => config["project_directory"].as_str(),
+            _ => config["service_name"]
+                .as_str()
+                .or(config["project_directory"].as_str()),
+        }
+        .unwrap_or("app");
+        let name = ansible["role_name"]
+            .as_str()
+            .unwrap_or(default_name)
+            .replace('-', "_")
+            .to_lowercase();
+
+        if name.is_empty()
+            || name.starts_with(|c: char| c.is_ascii_digit())
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid Ansible role name: {} (use letters, digits and underscores)",
+                    name
+                ),
+            ));
+        }
+
+        Ok(Some(AnsibleRoleSettings { name }))
+    }
+}
+
+/// The collections the role needs, installed by the CI jobs with `ansible-galaxy`.
+const REQUIREMENTS: &str = r#"---
+collections:
+  - name: community.docker
+    version: ">=3.6.0"
+"#;
+
+const PLAYBOOK: &str = r#"---
+- name: Deploy [[ role ]]
+  hosts: target_servers
+[[ play_options ]]  become: yes
+  roles:
+    - role: [[ role ]]
+"#;
+
+const DEFAULTS: &str = r#"---
+# The project on the server
+[[ role ]]_project_location: "[[ project_location ]]"
+[[ role ]]_project_directory: "[[ project_directory ]]"
+[[ role ]]_project_path: "{{ [[ role ]]_project_location }}/{{ [[ role ]]_project_directory }}"
+
+# The files copied to the server, read on the machine running Ansible
+[[ role ]]_source_path: "{{ [[ role ]]_project_path }}"
+
+# The compose files, including the environment override selected by the inventory
+[[ role ]]_compose_files: "{{ (compose_file | default('compose.yaml')).split(':') + ['compose.ansible.yaml'] }}"
+
+# Variables docker compose interpolates into the compose files
+[[ role ]]_compose_environment:[[ compose_environment ]]
+
+# Environment variables added to every service by templates/compose.ansible.yaml.j2
+[[ role ]]_services:[[ services ]]
+[[ role ]]_environment: {}
+
+[[ registry_defaults ]][[ role ]]_base_images:[[ base_images ]]
+"#;
+
+const BASE_IMAGE_REGISTRY_DEFAULTS: &str = r#"# The registry the base images are pulled from
+[[ role ]]_registry_login: [[ registry_login ]]
+[[ role ]]_registry_url: "{{ registry_url | default('') }}"
+[[ role ]]_registry_username: "{{ registry_username | default('') }}"
+[[ role ]]_registry_password: "{{ registry_password | default('') }}"
+"#;
+
+const IMAGE_REGISTRY_DEFAULTS: &str = r#"# The registry the images of the release are pulled from
+[[ role ]]_registry_login: true
+[[ role ]]_registry_url: "[[ image_registry_host ]]"
+[[ role ]]_registry_username: "{{ image_registry_username | default('') }}"
+[[ role ]]_registry_password: "{{ image_registry_password | default('') }}"
+"#;
+
+const TASKS: &str = r#"---
+- name: Ensure the project directory exists
+  ansible.builtin.file:
+    path: "{{ [[ role ]]_project_path }}"
+    state: directory
+    mode: '0755'
+
+- name: Copy the project files
+  ansible.builtin.copy:
+    src: "{{ [[ role ]]_source_path }}/"
+    dest: "{{ [[ role ]]_project_path }}/"
+    mode: preserve
+  register: [[ role ]]_files
+
+- name: Render the Ansible compose override
+  ansible.builtin.template:
+    src: compose.ansible.yaml.j2
+    dest: "{{ [[ role ]]_project_path }}/compose.ansible.yaml"
+    mode: '0644'
+
+- name: Log in to the registry
+  community.docker.docker_login:
+    registry_url: "{{ [[ role ]]_registry_url.split('/') | first }}"
+    username: "{{ [[ role ]]_registry_username }}"
+    password: "{{ [[ role ]]_registry_password }}"
+  no_log: true
+  when: [[ role ]]_registry_login
+
+- name: Pull the base images
+  community.docker.docker_image:
+    name: "{{ [[ role ]]_registry_url }}/{{ item }}"
+    source: pull
+  loop: "{{ [[ role ]]_base_images }}"
+
+# Images are only rebuilt when the project files changed, so an unchanged deploy reports no change
+- name: Start the compose services
+  community.docker.docker_compose_v2:
+    project_src: "{{ [[ role ]]_project_path }}"
+    files: "{{ [[ role ]]_compose_files }}"
+    build: "{{ 'always' if [[ role ]]_files is changed else 'policy' }}"
+    remove_orphans: true
+    state: present
+  environment: "{{ [[ role ]]_compose_environment }}"
+  notify: Prune dangling images
+"#;
+
+const HANDLERS: &str = r#"---
+- name: Prune dangling images
+  community.docker.docker_prune:
+    images: true
+    images_filters:
+      dangling: true
+"#;
+
+const COMPOSE_OVERRIDE_TEMPLATE: &str = r#"# {{ ansible_managed }}
+{% if [[ role ]]_environment %}
+services:
+{% for service in [[ role ]]_services %}
+  {{ service }}:
+    environment: {{ [[ role ]]_environment | to_json }}
+{% endfor %}
+{% else %}
+services: {}
+{% endif %}
+"#;
+
+/// Renders a YAML list on the lines following its key, or `[]` when empty.
+fn render_list(items: &[String]) -> String {
+    if items.is_empty() {
+        return " []".to_string();
+    }
+
+    items
+        .iter()
+        .map(|item| format!("\n  - {}", Value::String(item.clone())))
+        .collect()
+}
+
+/// Writes the Ansible role of the project to `roles/<name>`, with `ansible-deploy.yml` applying
+/// it to the inventory and `requirements.yml` listing the collections it needs.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+/// - `ansible_dir`: The directory the playbook is written to.
+/// - `settings`: The role settings.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn write_ansible_role(
+    config: &Value,
+    ansible_dir: &Path,
+    settings: &AnsibleRoleSettings,
+) -> io::Result<()> {
+    let pipeline = DeploymentPipeline::from_config(config)?;
+    let inventory_settings = InventorySettings::from_config(config)?;
+    let services = get_compose_services(config)?;
+    let registry_login = pipeline
+        .server_base_image_registry()
+        .is_some_and(|registry| registry.login);
+    let registry_defaults = match &pipeline.image_registry {
+        Some(image_registry) => {
+            IMAGE_REGISTRY_DEFAULTS.replace("[[ image_registry_host ]]", image_registry.host())
+        }
+        None => BASE_IMAGE_REGISTRY_DEFAULTS.to_string(),
+    };
+
+    let project_location = config["project_location"].as_str().unwrap_or_default();
+    let project_directory = config["project_directory"].as_str().unwrap_or_default();
+    let compose_environment = match pipeline.server_registry_url_variable() {
+        Some(variable) => format!(
+            "\n  {}: \"{{{{ {}_registry_url }}}}\"",
+            variable, settings.name
+        ),
+        None => " {}".to_string(),
+    };
+
+    let role_dir = ansible_dir.join("roles").join(&settings.name);
+    println!(
+        "📋 Creating the `{}` role at {:?}...",
+        settings.name, role_dir
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    let files = [
+        ("defaults/main.yml", DEFAULTS),
+        ("tasks/main.yml", TASKS),
+        ("handlers/main.yml", HANDLERS),
+        (
+            "templates/compose.ansible.yaml.j2",
+            COMPOSE_OVERRIDE_TEMPLATE,
+        ),
+    ];
+    for (file, content) in files {
+        let path = role_dir.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = content
+            .replace("[[ project_location ]]", project_location)
+            .replace("[[ project_directory ]]", project_directory)
+            .replace("[[ compose_environment ]]", &compose_environment)
+            .replace(
+                "[[ services ]]",
+                &render_list(
+                    &services
+                        .iter()
+                        .map(|service| service.name.clone())
+                        .collect::<Vec<String>>(),
+                ),
+            )
+            .replace("[[ registry_defaults ]]", &registry_defaults)
+            .replace("[[ registry_login ]]", &registry_login.to_string())
+            .replace(
+                "[[ base_images ]]",
+                &render_list(pipeline.server_base_images()),
+            )
+            .replace("[[ role ]]", &settings.name);
+        fs::write(&path, content)?;
+    }
+    println!("✅ Role created at {:?}", role_dir);
+
+    let requirements_path = ansible_dir.join("requirements.yml");
+    fs::write(&requirements_path, REQUIREMENTS)?;
+    println!(
+        "✅ `requirements.yml` file created at {:?}",
+        requirements_path
+    );
+
+    let playbook_path = ansible_dir.join("ansible-deploy.yml");
+    fs::write(
+        &playbook_path,
+        PLAYBOOK
+            .replace(
+                "[[ play_options ]]",
+                &inventory_settings.render_play_options(),
+            )
+            .replace("[[ role ]]", &settings.name),
+    )?;
+    println!(
+        "✅ `ansible-deploy.yml` file created at {:?}",
+        playbook_path
+    );
+
+    Ok(())
+}
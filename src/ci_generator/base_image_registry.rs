@@ -0,0 +1,351 @@
+use std::io;
+
+use serde_json::Value;
+
+use crate::ci_generator::deploy_target::CiVariable;
+
+/// The registry the .NET base images are pulled from when none is configured.
+const MICROSOFT_REGISTRY: &str = "mcr.microsoft.com";
+
+/// The CI variable holding the Nexus URL when `registry.url` is not set.
+const NEXUS_URL_VARIABLE: &str = "SONATYPE_NEXUS_URL";
+
+/// The kind of registry the .NET base images are pulled from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BaseImageRegistryKind {
+    /// The Microsoft Container Registry, without a login or pre-pulls.
+    None,
+    /// A Sonatype Nexus proxy of the Microsoft Container Registry.
+    Nexus,
+    /// A Harbor proxy cache project.
+    Harbor,
+    /// A GitLab container registry the base images are mirrored to.
+    GitLab,
+    /// Any other registry mirror.
+    Mirror,
+}
+
+/// The registry the .NET base images are pulled from, read from the optional `registry` section
+/// of the configuration.
+///
+/// The registry sets the prefix of the `FROM` lines of the Dockerfiles, and the login and pulls
+/// run before the images are built. Without the section, the base images come from a Sonatype
+/// Nexus whose URL is read from the `SONATYPE_NEXUS_URL` CI variable.
+pub struct BaseImageRegistry {
+    pub kind: BaseImageRegistryKind,
+    /// The registry and path the `dotnet/*` images are pulled from, e.g.
+    /// `harbor.example.com/mcr`, or `None` to read the Nexus URL from `SONATYPE_NEXUS_URL`.
+    pub url: Option<String>,
+    /// Whether the CI jobs and servers log in before pulling the base images.
+    pub login: bool,
+}
+
+impl BaseImageRegistry {
+    /// Reads the registry from the `registry` section of the configuration.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<BaseImageRegistry>`: The registry, or an error if the type is unknown or a
+    ///   required URL is missing or invalid.
+    pub fn from_config(config: &Value) -> io::Result<BaseImageRegistry> {
+        let registry = &config["registry"];
+
+        let kind = match registry["type"].as_str().unwrap_or("nexus") {
+            "none" => BaseImageRegistryKind::None,
+            "nexus" => BaseImageRegistryKind::Nexus,
+            "harbor" => BaseImageRegistryKind::Harbor,
+            "gitlab" => BaseImageRegistryKind::GitLab,
+            "mirror" => BaseImageRegistryKind::Mirror,
+            kind => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Unknown registry type: {} (use none, nexus, harbor, gitlab or mirror)",
+                        kind
+                    ),
+                ))
+            }
+        };
+
+        let url = registry["url"]
+            .as_str()
+            .map(|url| url.trim_end_matches('/').to_string());
+        if let Some(url) = &url {
+            if url.is_empty()
+                || url.contains("://")
+                || url.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Invalid registry.url: {:?} (use the registry host and path without a scheme, e.g. harbor.example.com/mcr)",
+                        url
+                    ),
+                ));
+            }
+        }
+        let url_required = !matches!(
+            kind,
+            BaseImageRegistryKind::None | BaseImageRegistryKind::Nexus
+        );
+        if url_required && url.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing registry.url, which the harbor, gitlab and mirror registries need",
+            ));
+        }
+
+        // Mirrors are usually open to the network they serve
+        let login = match kind {
+            BaseImageRegistryKind::None => false,
+            BaseImageRegistryKind::Mirror => registry["login"].as_bool().unwrap_or(false),
+            _ => registry["login"].as_bool().unwrap_or(true),
+        };
+
+        Ok(BaseImageRegistry { kind, url, login })
+    }
+
+    /// Returns the name of the registry in log messages and task names, e.g. `Sonatype Nexus`.
+    pub fn display_name(&self) -> &'static str {
+        match self.kind {
+            BaseImageRegistryKind::None => "Microsoft Container Registry",
+            BaseImageRegistryKind::Nexus => "Sonatype Nexus",
+            BaseImageRegistryKind::Harbor => "Harbor",
+            BaseImageRegistryKind::GitLab => "GitLab Container Registry",
+            BaseImageRegistryKind::Mirror => "Registry Mirror",
+        }
+    }
+
+    /// Returns whether the base images are pulled before the build, which is the case for every
+    /// registry but the Microsoft one.
+    pub fn pre_pulls(&self) -> bool {
+        self.kind != BaseImageRegistryKind::None
+    }
+
+    /// Returns the CI variable holding the registry URL, if it is not set in the configuration.
+    pub fn url_variable(&self) -> Option<&'static str> {
+        match (self.kind, &self.url) {
+            (BaseImageRegistryKind::Nexus, None) => Some(NEXUS_URL_VARIABLE),
+            _ => None,
+        }
+    }
+
+    /// Returns the CI variables holding the user name and password of the login.
+    pub fn credential_variables(&self) -> (&'static str, &'static str) {
+        match self.kind {
+            BaseImageRegistryKind::Nexus => ("SONATYPE_NEXUS_USERNAME", "SONATYPE_NEXUS_PASSWORD"),
+            // GitLab CI defines these in every job
+            BaseImageRegistryKind::GitLab => ("CI_REGISTRY_USER", "CI_REGISTRY_PASSWORD"),
+            _ => ("REGISTRY_USERNAME", "REGISTRY_PASSWORD"),
+        }
+    }
+
+    /// Returns the CI variables the registry login reads.
+    pub fn variables(&self) -> Vec<CiVariable> {
+        let mut variables = Vec::new();
+        if let Some(url_variable) = self.url_variable() {
+            variables.push((url_variable, "URL of the Docker registry".to_string()));
+        }
+        if self.login {
+            let (username, password) = self.credential_variables();
+            let predefined = if self.kind == BaseImageRegistryKind::GitLab {
+                ", predefined in GitLab CI"
+            } else {
+                ""
+            };
+            variables.push((username, format!("Docker registry user name{}", predefined)));
+            variables.push((password, format!("Docker registry password{}", predefined)));
+        }
+
+        variables
+            .into_iter()
+            .map(|(name, description)| CiVariable {
+                name: name.to_string(),
+                description,
+            })
+            .collect()
+    }
+
+    /// Returns the registry URL as the CI shell scripts reference it, e.g. `$SONATYPE_NEXUS_URL`.
+    pub fn url_reference(&self) -> String {
+        match (self.url_variable(), &self.url) {
+            (Some(variable), _) => format!("${}", variable),
+            (None, Some(url)) => url.clone(),
+            (None, None) => MICROSOFT_REGISTRY.to_string(),
+        }
+    }
+
+    /// Returns the host `docker login` authenticates against, as the CI shell scripts reference it.
+    pub fn login_host_reference(&self) -> String {
+        match &self.url {
+            Some(url) => url.split('/').next().unwrap_or(url).to_string(),
+            None => self.url_reference(),
+        }
+    }
+
+    /// Returns the shell command logging in to the registry, or `None` without a login.
+    pub fn login_command(&self) -> Option<String> {
+        if !self.login {
+            return None;
+        }
+
+        let (username, password) = self.credential_variables();
+        Some(format!(
+            r#"echo "${password}" | docker login "{host}" -u "${username}" --password-stdin"#,
+            password = password,
+            host = self.login_host_reference(),
+            username = username
+        ))
+    }
+
+    /// Returns the shell command pulling a base image, failing the job when it is missing.
+    pub fn pull_command(&self, image: &str) -> String {
+        format!(
+            r#"docker pull "{url}/{image}" || {{ echo "Failed to pull {image} image"; exit 1; }}"#,
+            url = self.url_reference(),
+            image = image
+        )
+    }
+
+    /// Returns the Dockerfile `ARG` holding the registry prefix of the `FROM` lines.
+    pub fn dockerfile_arg(&self) -> &'static str {
+        match self.kind {
+            BaseImageRegistryKind::Nexus => NEXUS_URL_VARIABLE,
+            _ => "BASE_IMAGE_REGISTRY",
+        }
+    }
+
+    /// Renders the Dockerfile lines declaring the `ARG` with its default registry.
+    pub fn render_dockerfile_arg(&self) -> String {
+        let comment = match self.kind {
+            BaseImageRegistryKind::Nexus => {
+                "Use an ARG for the Nexus URL and set a default fallback value"
+            }
+            _ => "Use an ARG for the registry the .NET base images are pulled from",
+        };
+        let default = match (self.url_variable(), &self.url) {
+            (None, Some(url)) => url.as_str(),
+            _ => MICROSOFT_REGISTRY,
+        };
+        format!(
+            "# {comment}\nARG {arg}={default}",
+            comment = comment,
+            arg = self.dockerfile_arg(),
+            default = default
+        )
+    }
+
+    /// Renders the `args` of the compose `build` sections of the .NET services, which only
+    /// forward the Nexus URL when it comes from a CI variable.
+    pub fn render_compose_build_args(&self) -> String {
+        match self.url_variable() {
+            Some(variable) => format!(
+                "      args:\n        {variable}: \"${{{variable}:-{default}}}\"\n",
+                variable = variable,
+                default = MICROSOFT_REGISTRY
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Renders the `group_vars` entries the Ansible registry login and pulls read.
+    ///
+    /// # Parameters
+    /// - `vault`: Whether the password comes from the Ansible Vault.
+    ///
+    /// # Returns
+    /// - `String`: The `registry_url`, `registry_username` and `registry_password` lines.
+    pub fn render_group_vars(&self, vault: bool) -> String {
+        let mut group_vars = match self.url_variable() {
+            Some(variable) => format!(
+                "registry_url: \"{{{{ lookup('env', '{}') }}}}\"\n",
+                variable
+            ),
+            None => format!("registry_url: \"{}\"\n", self.url_reference()),
+        };
+        if self.login {
+            let (username, password) = self.credential_variables();
+            let password = if vault {
+                "vault_registry_password".to_string()
+            } else {
+                format!("lookup('env', '{}')", password)
+            };
+            group_vars.push_str(&format!(
+                "registry_username: \"{{{{ lookup('env', '{}') }}}}\"\nregistry_password: \"{{{{ {} }}}}\"\n",
+                username, password
+            ));
+        }
+        group_vars
+    }
+
+    /// Renders the playbook task logging the server in to the registry and pulling the base
+    /// images, starting with a blank line.
+    ///
+    /// # Parameters
+    /// - `base_images`: The images to pull, relative to the registry URL.
+    ///
+    /// # Returns
+    /// - `String`: The task and the task displaying its output.
+    pub fn render_playbook_task(&self, base_images: &[String]) -> String {
+        let docker_pulls = base_images
+            .iter()
+            .map(|image| {
+                format!(
+                    r#"          docker pull "$REGISTRY_URL"/{image} || {{ echo "Failed to pull {image} image"; exit 1; }}"#,
+                    image = image
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let (name, login, login_environment) = if self.login {
+            (
+                format!("Run {} Docker Login", self.display_name()),
+                format!(
+                    r#"          echo "Logging into Microsoft Docker Registry ({name})..."
+          echo "Logging to url: $REGISTRY_HOST with username: $REGISTRY_USERNAME"
+          if echo "$REGISTRY_PASSWORD" | docker login "$REGISTRY_HOST" -u "$REGISTRY_USERNAME" --password-stdin; then
+            echo "Successfully Logged into Microsoft Docker Registry";
+          else
+            echo "Failed to Login to Microsoft Docker Registry";
+            exit 1;
+          fi
+
+"#,
+                    name = self.display_name()
+                ),
+                r#"        REGISTRY_HOST: "{{ registry_url.split('/') | first }}"
+        REGISTRY_USERNAME: "{{ registry_username }}"
+        REGISTRY_PASSWORD: "{{ registry_password }}"
+"#,
+            )
+        } else {
+            ("Pull the base images".to_string(), String::new(), "")
+        };
+
+        format!(
+            r#"
+    - name: {name}
+      ansible.builtin.shell:
+        cmd: |
+{login}          set -e  # Exit immediately if a command exits with a non-zero status
+          echo "Ensuring Required Docker Images Exist..."
+{docker_pulls}
+          echo "Docker Images Pulled Successfully"
+      environment:
+        REGISTRY_URL: "{{{{ registry_url }}}}"
+{login_environment}      register: docker_login_result
+
+    - name: Display docker-login result
+      debug:
+        var: docker_login_result.stdout
+"#,
+            name = name,
+            login = login,
+            docker_pulls = docker_pulls,
+            login_environment = login_environment
+        )
+    }
+}
@@ -0,0 +1,497 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::deploy_environment::{
+        get_compose_services, get_deploy_environments, ComposeService,
+    },
+    constants::SLEEP_DURATION,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, make_executable, write_file_with_backup},
+    },
+};
+
+/// The script the start jobs and the Ansible playbook run instead of `docker compose up`.
+pub const BLUE_GREEN_SCRIPT: &str = "deploy/blue-green.sh";
+
+/// Starts the compose services with a blue/green deploy.
+pub const BLUE_GREEN_COMMAND: &str = "sh deploy/blue-green.sh\n";
+
+/// The colors the services alternate between.
+const COLORS: [&str; 2] = ["blue", "green"];
+
+/// The reverse proxy publishing the ports of the services and sending the traffic to one color.
+pub enum BlueGreenProxy {
+    Nginx,
+    Traefik,
+}
+
+/// The blue/green deploys, read from the optional `blue_green` section of the configuration.
+///
+/// Every deploy starts the services as a second compose project next to the running one, waits
+/// for them to become healthy, then switches the proxy over and stops the old project.
+pub struct BlueGreenSettings {
+    pub proxy: BlueGreenProxy,
+    /// A path every service listening on a port must answer before it receives traffic, e.g.
+    /// `/health`. Without it, only the compose healthchecks are waited for.
+    pub health_path: Option<String>,
+    /// The seconds to wait for the new services to become healthy.
+    pub timeout: u64,
+}
+
+impl BlueGreenSettings {
+    /// Reads the blue/green settings, if the `blue_green` section is present.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<BlueGreenSettings>>`: The settings, `None` to deploy in place, or an
+    ///   error if a setting is invalid or the deploy is combined with a role or releases.
+    pub fn from_config(config: &Value) -> io::Result<Option<BlueGreenSettings>> {
+        let blue_green = &config["blue_green"];
+        if blue_green.is_null() || blue_green.as_bool() == Some(false) {
+            return Ok(None);
+        }
+
+        let proxy = match blue_green["proxy"].as_str().unwrap_or("nginx") {
+            "nginx" => BlueGreenProxy::Nginx,
+            "traefik" => BlueGreenProxy::Traefik,
+            proxy => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown blue_green.proxy: {} (use nginx or traefik)", proxy),
+                ))
+            }
+        };
+
+        let health_path = blue_green["health_path"].as_str().map(|path| {
+            if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("/{}", path)
+            }
+        });
+        if let Some(path) = &health_path {
+            if path.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid blue_green.health_path: {}", path),
+                ));
+            }
+        }
+
+        let ansible = &config["ansible"];
+        if ansible["role"].as_bool().unwrap_or(false) || !ansible["keep_releases"].is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "blue_green cannot be combined with ansible.role or ansible.keep_releases",
+            ));
+        }
+
+        Ok(Some(BlueGreenSettings {
+            proxy,
+            health_path,
+            timeout: blue_green["timeout"].as_u64().unwrap_or(120),
+        }))
+    }
+}
+
+const NGINX_PROXY_SERVICE: &str = r#"services:
+  proxy:
+    image: nginx:1.27-alpine
+    restart: unless-stopped
+    ports:
+[[ ports ]]    volumes:
+      - proxy-config:/etc/nginx/conf.d
+    networks:
+      - bluegreen
+"#;
+
+const TRAEFIK_PROXY_SERVICE: &str = r#"services:
+  proxy:
+    image: traefik:v3.1
+    restart: unless-stopped
+    command:
+      - --providers.file.directory=/etc/traefik/dynamic
+      - --providers.file.watch=true
+[[ entrypoints ]]    ports:
+[[ ports ]]    volumes:
+      - proxy-config:/etc/traefik/dynamic
+    networks:
+      - bluegreen
+"#;
+
+const PROXY_RESOURCES: &str = r#"
+volumes:
+  proxy-config:
+
+networks:
+  bluegreen:
+    name: [[ network ]]
+    external: true
+"#;
+
+const NGINX_SERVER: &str = r#"
+server {
+    listen [[ port ]];
+
+    location / {
+        set $upstream http://[[ host ]]:[[ container_port ]];
+        proxy_pass $upstream;
+        proxy_http_version 1.1;
+        proxy_set_header Host $host;
+        proxy_set_header X-Real-IP $remote_addr;
+        proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for;
+        proxy_set_header X-Forwarded-Proto $scheme;
+    }
+}
+"#;
+
+/// Starts the services of the next color, checks them and moves the proxy over.
+const DEPLOY_SCRIPT: &str = r#"#!/bin/sh
+# Blue/green deploy of the compose services, generated by fileforge.
+#
+# Starts the services as the [[ project ]]-blue or [[ project ]]-green compose project, whichever
+# is not running, waits for them to become healthy, points the proxy at them and stops the
+# other color. Run it from the project directory with COMPOSE_FILE set like for
+# `docker compose up`.
+set -eu
+
+project="[[ project ]]"
+network="[[ network ]]"
+proxy_image="[[ proxy_image ]]"
+health_path="[[ health_path ]]"
+compose_file="${COMPOSE_FILE:-compose.yaml}"
+
+# The proxy of an environment publishes the ports of its compose override
+proxy_compose_file="compose.proxy.yaml"
+for file in $(echo "$compose_file" | tr ':' ' '); do
+  case "$file" in
+    compose.*.yaml)
+      if [ -f "compose.proxy.${file#compose.}" ]; then
+        proxy_compose_file="$proxy_compose_file:compose.proxy.${file#compose.}"
+      fi
+      ;;
+  esac
+done
+
+running() {
+  [ -n "$(docker ps -q --filter "label=com.docker.compose.project=$1")" ]
+}
+
+color_compose() {
+  color="$1"
+  shift
+  COMPOSE_FILE="$compose_file:compose.$color.yaml" docker compose -p "$project-$color" "$@"
+}
+
+proxy_compose() {
+  COMPOSE_FILE="$proxy_compose_file" docker compose -p "$project-proxy" "$@"
+}
+
+# Answers once the service of the next color responds on the health path
+probe() {
+  attempt=1
+  until docker run --rm --network "$network" --entrypoint wget "$proxy_image" -q -O /dev/null "http://$1-$next:$2$health_path"; do
+    if [ "$attempt" -ge 10 ]; then
+      echo "$1 did not answer on $health_path"
+      return 1
+    fi
+    attempt=$((attempt + 1))
+    sleep 3
+  done
+}
+
+if running "$project-blue"; then
+  active=blue
+  next=green
+elif running "$project-green"; then
+  active=green
+  next=blue
+else
+  active=""
+  next=blue
+fi
+
+docker network inspect "$network" > /dev/null 2>&1 || docker network create "$network" > /dev/null
+
+echo "Starting the $next services..."
+if ! color_compose "$next" up --build -d --remove-orphans --wait --wait-timeout [[ timeout ]]; then
+  echo "The $next services did not become healthy, the ${active:-current} services keep serving"
+  color_compose "$next" down --remove-orphans
+  exit 1
+fi
+[[ probes ]]
+# The services of the first blue/green deploy replace the ones started by `docker compose up`
+if [ -z "$active" ] && running "$project"; then
+  echo "Stopping the services started without blue/green..."
+  docker compose -p "$project" down --remove-orphans
+fi
+
+echo "Switching the proxy to the $next services..."
+proxy_compose up -d --remove-orphans
+proxy_compose exec -T proxy sh -c 'cat > [[ proxy_config_path ]]' < "proxy/$next.[[ proxy_config_extension ]]"
+[[ proxy_reload ]]
+if [ -n "$active" ]; then
+  echo "Stopping the $active services..."
+  color_compose "$active" down --remove-orphans
+fi
+
+echo "The $next services are live"
+"#;
+
+/// Returns the compose project name docker compose derives from the project directory.
+pub fn compose_project_name(config: &Value) -> String {
+    config["project_directory"]
+        .as_str()
+        .unwrap_or_default()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect()
+}
+
+/// Renders the compose override of a color, which keeps its containers off the published ports
+/// and names them `<service>-<color>` on the network shared with the proxy.
+fn render_color_override(services: &[ComposeService], color: &str, network: &str) -> String {
+    let mut color_override = String::from("services:\n");
+    for service in services {
+        color_override.push_str(&format!(
+            r#"  {name}:
+    container_name: !reset null
+    ports: !reset []
+    networks:
+      default: {{}}
+      bluegreen:
+        aliases:
+          - {name}-{color}
+"#,
+            name = service.name,
+            color = color
+        ));
+    }
+    color_override.push_str(&format!(
+        "\nnetworks:\n  bluegreen:\n    name: {}\n    external: true\n",
+        network
+    ));
+
+    color_override
+}
+
+/// Renders the proxy configuration sending the traffic of every published port to a color.
+fn render_proxy_config(
+    settings: &BlueGreenSettings,
+    services: &[&ComposeService],
+    color: &str,
+) -> String {
+    match settings.proxy {
+        BlueGreenProxy::Nginx => {
+            let mut proxy_config = String::from(
+                "# Docker DNS, so the services are looked up again when they are replaced\nresolver 127.0.0.11 valid=10s;\n",
+            );
+            for service in services {
+                proxy_config.push_str(
+                    &NGINX_SERVER
+                        .replace("[[ port ]]", &service.port.to_string())
+                        .replace("[[ host ]]", &format!("{}-{}", service.name, color))
+                        .replace(
+                            "[[ container_port ]]",
+                            &service.container_port.unwrap_or_default().to_string(),
+                        ),
+                );
+            }
+            proxy_config
+        }
+        BlueGreenProxy::Traefik => {
+            let mut routers = String::new();
+            let mut upstreams = String::new();
+            for service in services {
+                routers.push_str(&format!(
+                    "    {name}:\n      entryPoints:\n        - {name}\n      rule: PathPrefix(`/`)\n      service: {name}\n",
+                    name = service.name
+                ));
+                upstreams.push_str(&format!(
+                    "    {name}:\n      loadBalancer:\n        servers:\n          - url: http://{name}-{color}:{container_port}\n",
+                    name = service.name,
+                    color = color,
+                    container_port = service.container_port.unwrap_or_default()
+                ));
+            }
+            format!("http:\n  routers:\n{}  services:\n{}", routers, upstreams)
+        }
+    }
+}
+
+/// Renders the published ports of the proxy, from the host port to the port it listens on.
+fn render_proxy_ports(ports: &[(u64, u64)]) -> String {
+    ports
+        .iter()
+        .map(|(host_port, port)| format!("      - '{}:{}'\n", host_port, port))
+        .collect()
+}
+
+/// Generates the blue/green deploy files when the `blue_green` section is configured: the color
+/// overrides `compose.blue.yaml` and `compose.green.yaml`, the proxy in `compose.proxy.yaml` with
+/// a `compose.proxy.<environment>.yaml` per environment publishing other ports, the proxy
+/// configuration of each color in `proxy/` and the `deploy/blue-green.sh` script.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn generate_blue_green_files() -> io::Result<()> {
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+
+    let config = get_current_config(current_dir.clone());
+    let settings = match BlueGreenSettings::from_config(&config)? {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+    let services = get_compose_services(&config)?;
+    let environments = get_deploy_environments(&config)?;
+
+    // Only the services listening on a port are published through the proxy
+    let routed_services = services
+        .iter()
+        .filter(|service| service.container_port.is_some())
+        .collect::<Vec<&ComposeService>>();
+    if routed_services.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Blue/green deploys need a service listening on a port",
+        ));
+    }
+
+    let project = compose_project_name(&config);
+    let network = format!("{}-bluegreen", project);
+    let output_dir = get_output_directory(&current_dir);
+
+    println!("🔵🟢 Writing the blue/green deploy files...");
+    thread::sleep(SLEEP_DURATION);
+
+    for color in COLORS {
+        write_file_with_backup(
+            &output_dir.join(format!("compose.{}.yaml", color)),
+            &render_color_override(&services, color, &network),
+        )?;
+    }
+
+    // The proxy listens on the ports of `compose.yaml`, environments only publish them elsewhere
+    let (proxy_service, proxy_image, proxy_config_path, proxy_config_extension, proxy_reload) =
+        match settings.proxy {
+            BlueGreenProxy::Nginx => (
+                NGINX_PROXY_SERVICE.to_string(),
+                "nginx:1.27-alpine",
+                "/etc/nginx/conf.d/default.conf",
+                "conf",
+                "proxy_compose exec -T proxy sh -c 'nginx -t && nginx -s reload'\n",
+            ),
+            BlueGreenProxy::Traefik => (
+                TRAEFIK_PROXY_SERVICE.replace(
+                    "[[ entrypoints ]]",
+                    &routed_services
+                        .iter()
+                        .map(|service| {
+                            format!(
+                                "      - --entrypoints.{}.address=:{}\n",
+                                service.name, service.port
+                            )
+                        })
+                        .collect::<String>(),
+                ),
+                "traefik:v3.1",
+                "/etc/traefik/dynamic/routes.yml",
+                "yml",
+                "# Traefik watches its configuration and applies it on its own\nsleep 2\n",
+            ),
+        };
+    let proxy_ports = routed_services
+        .iter()
+        .map(|service| (service.port, service.port))
+        .collect::<Vec<(u64, u64)>>();
+    write_file_with_backup(
+        &output_dir.join("compose.proxy.yaml"),
+        &format!(
+            "{}{}",
+            proxy_service.replace("[[ ports ]]", &render_proxy_ports(&proxy_ports)),
+            PROXY_RESOURCES.replace("[[ network ]]", &network)
+        ),
+    )?;
+
+    for environment in &environments {
+        let ports = routed_services
+            .iter()
+            .map(|service| {
+                (
+                    environment
+                        .port_override(service, &services)
+                        .unwrap_or(service.port),
+                    service.port,
+                )
+            })
+            .collect::<Vec<(u64, u64)>>();
+        if ports == proxy_ports {
+            continue;
+        }
+
+        if let Some(compose_override_file) = environment.compose_override_file() {
+            write_file_with_backup(
+                &output_dir.join(compose_override_file.replace("compose.", "compose.proxy.")),
+                &format!(
+                    "services:\n  proxy:\n    ports: !override\n{}",
+                    render_proxy_ports(&ports)
+                ),
+            )?;
+        }
+    }
+
+    for color in COLORS {
+        let extension = match settings.proxy {
+            BlueGreenProxy::Nginx => "conf",
+            BlueGreenProxy::Traefik => "yml",
+        };
+        write_file_with_backup(
+            &output_dir
+                .join("proxy")
+                .join(format!("{}.{}", color, extension)),
+            &render_proxy_config(&settings, &routed_services, color),
+        )?;
+    }
+
+    let probes = match &settings.health_path {
+        Some(_) => routed_services
+            .iter()
+            .map(|service| {
+                format!(
+                    "if ! probe {name} {container_port}; then\n  color_compose \"$next\" down --remove-orphans\n  exit 1\nfi\n",
+                    name = service.name,
+                    container_port = service.container_port.unwrap_or_default()
+                )
+            })
+            .collect::<String>(),
+        None => String::new(),
+    };
+
+    let script_path = output_dir.join(BLUE_GREEN_SCRIPT);
+    write_file_with_backup(
+        &script_path,
+        &DEPLOY_SCRIPT
+            .replace("[[ probes ]]", &probes)
+            .replace("[[ proxy_reload ]]", proxy_reload)
+            .replace("[[ proxy_config_path ]]", proxy_config_path)
+            .replace("[[ proxy_config_extension ]]", proxy_config_extension)
+            .replace("[[ proxy_image ]]", proxy_image)
+            .replace(
+                "[[ health_path ]]",
+                settings.health_path.as_deref().unwrap_or_default(),
+            )
+            .replace("[[ timeout ]]", &settings.timeout.to_string())
+            .replace("[[ network ]]", &network)
+            .replace("[[ project ]]", &project),
+    )?;
+    make_executable(&script_path)?;
+
+    Ok(())
+}
@@ -0,0 +1,535 @@
+use std::io;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::InventorySettings,
+        base_image_registry::BaseImageRegistry,
+        deploy_target::{CiVariable, DeployTarget},
+    },
+    dotnet_generator::generate_compose_file_for_dotnet::get_dotnet_container_port,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+};
+
+/// Compose settings an environment overrides for one or every service.
+#[derive(Default)]
+pub struct ComposeOverrides {
+    pub port: Option<u64>,
+    pub replicas: Option<u64>,
+    pub env: Vec<(String, String)>,
+}
+
+impl ComposeOverrides {
+    /// Reads the `port`, `replicas` and `env` settings of an environment or service entry.
+    pub fn from_value(value: &Value) -> ComposeOverrides {
+        let env = value["env"]
+            .as_object()
+            .map(|env| {
+                env.iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            Value::String(value) => value.clone(),
+                            value => value.to_string(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ComposeOverrides {
+            port: value["port"].as_u64(),
+            replicas: value["replicas"].as_u64(),
+            env,
+        }
+    }
+}
+
+/// A service of the compose file, as far as environment overrides are concerned.
+pub struct ComposeService {
+    pub name: String,
+    /// The port the service listens on inside its container, if any.
+    pub container_port: Option<u16>,
+    /// The host port `compose.yaml` publishes the container port on.
+    pub port: u64,
+    /// The build context of the image, relative to the compose file.
+    pub build_context: String,
+    /// The Dockerfile of the image, relative to the build context.
+    pub dockerfile: String,
+    /// The `args` lines of the `build` section, empty when the Dockerfile needs none.
+    pub build_args: String,
+}
+
+/// An environment the project is deployed to, e.g. `staging` or `production`.
+pub struct DeployEnvironment {
+    pub name: String,
+    /// Whether the environment is listed in `environments`. Without any, the project has a single
+    /// implicit environment that keeps the plain job names and has no compose override file.
+    pub configured: bool,
+    /// The branch that deploys the environment, or `None` for the CI provider's default branches.
+    pub branch: Option<String>,
+    /// The inventory group holding the servers of the environment.
+    pub host_group: String,
+    /// The servers the environment is deployed to.
+    pub hosts: Vec<DeployTarget>,
+    /// Extra Ansible variables of the environment, written to its `group_vars` file.
+    pub vars: Map<String, Value>,
+    /// Whether the deployment waits for a manual approval.
+    pub manual: bool,
+    /// Overrides applied to every service.
+    pub overrides: ComposeOverrides,
+    /// Overrides applied to one service, by service name.
+    pub service_overrides: Vec<(String, ComposeOverrides)>,
+}
+
+impl DeployEnvironment {
+    /// Returns the CI variables holding the connection settings of every host.
+    pub fn variables(&self) -> Vec<CiVariable> {
+        self.hosts
+            .iter()
+            .flat_map(|host| host.variables())
+            .collect()
+    }
+
+    /// Returns the CI variable holding the kubeconfig of the environment's cluster, e.g.
+    /// `STAGING_KUBECONFIG`, which the helm job reads.
+    pub fn kubeconfig_variable(&self) -> CiVariable {
+        CiVariable {
+            name: format!("{}_KUBECONFIG", self.name.to_uppercase().replace('-', "_")),
+            description: format!("Kubeconfig of the {} cluster", self.name),
+        }
+    }
+
+    /// Returns the suffix of the CI job names, e.g. `-staging`, empty for the implicit environment.
+    pub fn job_suffix(&self) -> String {
+        if self.configured {
+            format!("-{}", self.name)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Returns the compose override file of the environment, e.g. `compose.staging.yaml`.
+    pub fn compose_override_file(&self) -> Option<String> {
+        if self.configured {
+            Some(format!("compose.{}.yaml", self.name))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `COMPOSE_FILE` value selecting the compose file and the environment override.
+    pub fn compose_file(&self) -> String {
+        match self.compose_override_file() {
+            Some(compose_override_file) => format!("compose.yaml:{}", compose_override_file),
+            None => "compose.yaml".to_string(),
+        }
+    }
+
+    /// Returns the host port the environment publishes a service on instead of the one in
+    /// `compose.yaml`, if it overrides it.
+    ///
+    /// # Parameters
+    /// - `service`: The service.
+    /// - `services`: The services of the compose file. The environment wide port only applies to
+    ///   projects with a single service.
+    ///
+    /// # Returns
+    /// - `Option<u64>`: The overridden host port.
+    pub fn port_override(
+        &self,
+        service: &ComposeService,
+        services: &[ComposeService],
+    ) -> Option<u64> {
+        self.service_overrides
+            .iter()
+            .find(|(name, _)| *name == service.name)
+            .and_then(|(_, overrides)| overrides.port)
+            .or(self.overrides.port.filter(|_| services.len() == 1))
+    }
+
+    /// Returns the replica count the environment sets for a service, if it overrides it.
+    ///
+    /// # Parameters
+    /// - `service_name`: The name of the service.
+    /// - `single_service`: Whether the project has a single service. The environment wide replica
+    ///   count only applies to those.
+    ///
+    /// # Returns
+    /// - `Option<u64>`: The overridden replica count.
+    pub fn replicas_override(&self, service_name: &str, single_service: bool) -> Option<u64> {
+        self.service_overrides
+            .iter()
+            .find(|(name, _)| name == service_name)
+            .and_then(|(_, overrides)| overrides.replicas)
+            .or(self.overrides.replicas.filter(|_| single_service))
+    }
+
+    /// Returns the environment variables the environment sets for a service, the service
+    /// specific ones last so they win.
+    pub fn env_overrides(&self, service_name: &str) -> Vec<(String, String)> {
+        let mut env = self.overrides.env.clone();
+        if let Some((_, overrides)) = self
+            .service_overrides
+            .iter()
+            .find(|(name, _)| name == service_name)
+        {
+            env.extend(overrides.env.iter().cloned());
+        }
+        env
+    }
+
+    /// Renders the compose override file of the environment.
+    ///
+    /// Ports are replaced rather than merged with the base file. Services with several replicas
+    /// drop their fixed container name and publish a range of host ports, one per replica.
+    ///
+    /// # Parameters
+    /// - `services`: The services of the compose file.
+    ///
+    /// # Returns
+    /// - `String`: The content of the override file.
+    pub fn render_compose_override(&self, services: &[ComposeService]) -> String {
+        let mut compose_override = String::from("services:\n");
+
+        for service in services {
+            // Service specific values win over the environment wide ones; the environment wide
+            // port and replica count only apply to projects with a single service
+            let port = self.port_override(service, services);
+            let replicas = self.replicas_override(&service.name, services.len() == 1);
+            let env = self.env_overrides(&service.name);
+
+            let mut service_override = String::new();
+
+            // Every replica needs its own host port, even when the port itself is not overridden
+            let replicated = replicas.is_some_and(|replicas| replicas > 1);
+            if let Some(container_port) = service.container_port {
+                if port.is_some() || replicated {
+                    let port = port.unwrap_or(service.port);
+                    let host_ports = match replicas {
+                        Some(replicas) if replicas > 1 => {
+                            format!("{}-{}", port, port + replicas - 1)
+                        }
+                        _ => port.to_string(),
+                    };
+                    service_override.push_str(&format!(
+                        "    ports: !override\n      - '{}:{}'\n",
+                        host_ports, container_port
+                    ));
+                }
+            }
+
+            if let Some(replicas) = replicas {
+                if replicated {
+                    service_override.push_str("    container_name: !reset null\n");
+                }
+                service_override.push_str(&format!("    deploy:\n      replicas: {}\n", replicas));
+            }
+
+            if !env.is_empty() {
+                service_override.push_str("    environment:\n");
+                for (key, value) in env {
+                    service_override.push_str(&format!(
+                        "      {}: {}\n",
+                        key,
+                        Value::String(value)
+                    ));
+                }
+            }
+
+            // Services without overrides are left out, an empty entry would be null
+            if !service_override.is_empty() {
+                compose_override.push_str(&format!("  {}:\n{}", service.name, service_override));
+            }
+        }
+
+        if compose_override == "services:\n" {
+            return "services: {}\n".to_string();
+        }
+
+        compose_override
+    }
+}
+
+/// Reads the environments from the optional `environments` list of the configuration.
+///
+/// Without the list, the project has a single implicit environment deploying to the
+/// `deploy_target` servers.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+///
+/// # Returns
+/// - `io::Result<Vec<DeployEnvironment>>`: The environments in configuration order, or an error
+///   if one is invalid.
+pub fn get_deploy_environments(config: &Value) -> io::Result<Vec<DeployEnvironment>> {
+    let mut deploy_environments = read_deploy_environments(config)?;
+
+    // With the vault enabled, the become passwords no longer come from CI variables
+    if InventorySettings::from_config(config)?
+        .vault_password_variable
+        .is_some()
+    {
+        for host in deploy_environments
+            .iter_mut()
+            .flat_map(|deploy_environment| deploy_environment.hosts.iter_mut())
+        {
+            host.vault_become_pass = true;
+        }
+    }
+
+    Ok(deploy_environments)
+}
+
+fn read_deploy_environments(config: &Value) -> io::Result<Vec<DeployEnvironment>> {
+    let environments = match config["environments"].as_array() {
+        Some(environments) if !environments.is_empty() => environments,
+        _ => {
+            let deploy_target = &config["deploy_target"];
+            let hosts = match deploy_target["hosts"].as_array() {
+                Some(hosts) if !hosts.is_empty() => get_hosts(hosts)?,
+                _ => vec![DeployTarget::from_config(config)?],
+            };
+            return Ok(vec![DeployEnvironment {
+                name: deploy_target["name"]
+                    .as_str()
+                    .unwrap_or("production")
+                    .to_string(),
+                configured: false,
+                branch: None,
+                host_group: "target_servers".to_string(),
+                hosts,
+                vars: Map::new(),
+                manual: false,
+                overrides: ComposeOverrides::default(),
+                service_overrides: Vec::new(),
+            }]);
+        }
+    };
+
+    let mut deploy_environments: Vec<DeployEnvironment> = Vec::new();
+    for environment in environments {
+        let name = environment["name"].as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing name in one of the environments",
+            )
+        })?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid environment name: {}", name),
+            ));
+        }
+
+        let host_group = environment["host_group"]
+            .as_str()
+            .unwrap_or(name)
+            .replace('-', "_");
+        if let Some(duplicate) = deploy_environments.iter().find(|deploy_environment| {
+            deploy_environment.name == name || deploy_environment.host_group == host_group
+        }) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Environments {} and {} must have different names and host groups",
+                    duplicate.name, name
+                ),
+            ));
+        }
+
+        // Without a `hosts` list, the environment has a single server named after it
+        let hosts = match environment["hosts"].as_array() {
+            Some(hosts) if !hosts.is_empty() => get_hosts(hosts)?,
+            _ => {
+                let default_variable_prefix = name.to_uppercase().replace('-', "_");
+                vec![DeployTarget::new(
+                    &format!("{}-server", name),
+                    Some(
+                        environment["variable_prefix"]
+                            .as_str()
+                            .unwrap_or(&default_variable_prefix),
+                    ),
+                )?]
+            }
+        };
+        for host in &hosts {
+            if deploy_environments
+                .iter()
+                .flat_map(|deploy_environment| &deploy_environment.hosts)
+                .any(|other_host| {
+                    other_host.name == host.name
+                        || other_host.variable_prefix == host.variable_prefix
+                })
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Host {} shares its name or variable prefix with a host of another environment",
+                        host.name
+                    ),
+                ));
+            }
+        }
+
+        let service_overrides = environment["services"]
+            .as_object()
+            .map(|services| {
+                services
+                    .iter()
+                    .map(|(name, overrides)| {
+                        (name.clone(), ComposeOverrides::from_value(overrides))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        deploy_environments.push(DeployEnvironment {
+            name: name.to_string(),
+            configured: true,
+            branch: environment["branch"]
+                .as_str()
+                .map(|branch| branch.to_string()),
+            host_group,
+            hosts,
+            vars: environment["vars"].as_object().cloned().unwrap_or_default(),
+            manual: environment["manual"].as_bool().unwrap_or(false),
+            overrides: ComposeOverrides::from_value(environment),
+            service_overrides,
+        });
+    }
+
+    Ok(deploy_environments)
+}
+
+/// Reads the servers of a `hosts` list, rejecting duplicate host names and variable prefixes.
+fn get_hosts(hosts: &[Value]) -> io::Result<Vec<DeployTarget>> {
+    let mut targets: Vec<DeployTarget> = Vec::new();
+    for host in hosts {
+        let target = DeployTarget::from_host_config(host)?;
+        if let Some(duplicate) = targets.iter().find(|other| {
+            other.name == target.name || other.variable_prefix == target.variable_prefix
+        }) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Hosts {} and {} must have different names and variable prefixes",
+                    duplicate.name, target.name
+                ),
+            ));
+        }
+        targets.push(target);
+    }
+
+    Ok(targets)
+}
+
+/// Returns the services of the compose file generated for a `dotnet`, `angular` or `monorepo`
+/// configuration.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+///
+/// # Returns
+/// - `io::Result<Vec<ComposeService>>`: The services, or an error if the monorepo is invalid.
+pub fn get_compose_services(config: &Value) -> io::Result<Vec<ComposeService>> {
+    let dotnet_build_args = BaseImageRegistry::from_config(config)?.render_compose_build_args();
+    let to_compose_service = |service: &Value, build_context: String| {
+        let (container_port, build_args) =
+            match service["project_type"].as_str().unwrap_or("dotnet") {
+                "angular" => (Some(80), String::new()),
+                _ => (
+                    get_dotnet_container_port(service["app_type"].as_str().unwrap_or("web")),
+                    dotnet_build_args.clone(),
+                ),
+            };
+        ComposeService {
+            name: service["service_name"]
+                .as_str()
+                .unwrap_or("default_service")
+                .to_string(),
+            container_port,
+            port: service["port"].as_u64().unwrap_or(5000),
+            build_context,
+            dockerfile: service["dockerfile"]
+                .as_str()
+                .unwrap_or("Dockerfile")
+                .to_string(),
+            build_args,
+        }
+    };
+
+    match config["project_type"].as_str() {
+        Some("monorepo") => Ok(get_monorepo_services(config)?
+            .iter()
+            .map(|service| {
+                let build_context = format!("./{}", service["path"].as_str().unwrap_or("."));
+                to_compose_service(service, build_context)
+            })
+            .collect()),
+        _ => Ok(vec![to_compose_service(config, ".".to_string())]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_yaml::Value as Yaml;
+
+    use super::*;
+
+    fn render_override(environment: Value) -> Yaml {
+        let config = json!({
+            "project_type": "dotnet",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "container_name": "shop-api",
+            "port": 8080,
+            "environments": [environment]
+        });
+        let services = get_compose_services(&config).unwrap();
+        let environment = &get_deploy_environments(&config).unwrap()[0];
+
+        // The `!override` and `!reset` tags of compose parse as tagged values
+        let compose_override = environment
+            .render_compose_override(&services)
+            .replace("!override ", "")
+            .replace("!reset ", "");
+        serde_yaml::from_str(&compose_override).unwrap()
+    }
+
+    #[test]
+    fn replicas_publish_a_range_of_host_ports_from_the_base_port() {
+        let compose_override = render_override(json!({ "name": "staging", "replicas": 2 }));
+        let service = &compose_override["services"]["shop-api"];
+
+        assert_eq!(service["ports"][0], "8080-8081:5000");
+        assert_eq!(service["deploy"]["replicas"], 2);
+        assert!(service["container_name"].is_null());
+    }
+
+    #[test]
+    fn replicas_publish_a_range_of_host_ports_from_the_overridden_port() {
+        let compose_override =
+            render_override(json!({ "name": "staging", "port": 9000, "replicas": 3 }));
+
+        assert_eq!(
+            compose_override["services"]["shop-api"]["ports"][0],
+            "9000-9002:5000"
+        );
+    }
+
+    #[test]
+    fn a_single_replica_keeps_the_base_port() {
+        let compose_override = render_override(json!({ "name": "staging", "replicas": 1 }));
+        let service = &compose_override["services"]["shop-api"];
+
+        assert!(service.get("ports").is_none());
+        assert_eq!(service["deploy"]["replicas"], 1);
+    }
+}
@@ -0,0 +1,187 @@
+use std::io;
+
+use serde_json::{Map, Value};
+
+/// A variable the CI provider must define for the generated pipeline.
+pub struct CiVariable {
+    pub name: String,
+    pub description: String,
+}
+
+/// A server the Ansible playbook deploys to.
+///
+/// The host variables read the connection settings from environment variables, so the CI jobs
+/// only need to expose the `<variable_prefix>_ANSIBLE_*` variables to the playbook.
+pub struct DeployTarget {
+    /// The inventory host name, e.g. `production`.
+    pub name: String,
+    /// The prefix of the CI variables holding the connection settings, e.g. `PRODUCTION`.
+    pub variable_prefix: String,
+    /// Extra Ansible variables of the host, written to its `host_vars` file.
+    pub vars: Map<String, Value>,
+    /// Whether the become password is read from the Ansible Vault instead of a CI variable.
+    pub vault_become_pass: bool,
+}
+
+impl DeployTarget {
+    /// Reads the target from the optional `deploy_target` section of the configuration.
+    ///
+    /// The name defaults to `production` and the variable prefix to the upper-cased name.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<DeployTarget>`: The target, or an error if the name or prefix is invalid.
+    pub fn from_config(config: &Value) -> io::Result<DeployTarget> {
+        let deploy_target = &config["deploy_target"];
+        DeployTarget::new(
+            deploy_target["name"].as_str().unwrap_or("production"),
+            deploy_target["variable_prefix"].as_str(),
+        )
+    }
+
+    /// Creates a target, deriving the variable prefix from the name when none is given.
+    ///
+    /// # Parameters
+    /// - `name`: The inventory host name.
+    /// - `variable_prefix`: The prefix of the CI variables, or `None` for the upper-cased name.
+    ///
+    /// # Returns
+    /// - `io::Result<DeployTarget>`: The target, or an error if the name or prefix is invalid.
+    pub fn new(name: &str, variable_prefix: Option<&str>) -> io::Result<DeployTarget> {
+        let variable_prefix = match variable_prefix {
+            Some(variable_prefix) => variable_prefix.to_string(),
+            None => name.to_uppercase().replace('-', "_"),
+        };
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid deploy target name: {}", name),
+            ));
+        }
+        if variable_prefix.is_empty()
+            || variable_prefix.starts_with(|c: char| c.is_ascii_digit())
+            || !variable_prefix
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid CI variable prefix: {} (use upper-case letters, digits and underscores)",
+                    variable_prefix
+                ),
+            ));
+        }
+
+        Ok(DeployTarget {
+            name: name.to_string(),
+            variable_prefix,
+            vars: Map::new(),
+            vault_become_pass: false,
+        })
+    }
+
+    /// Reads a target from an entry of a `hosts` list, either a host name or an object with
+    /// `name`, `variable_prefix` and `vars`.
+    ///
+    /// # Parameters
+    /// - `host`: The entry of the `hosts` list.
+    ///
+    /// # Returns
+    /// - `io::Result<DeployTarget>`: The target, or an error if the entry is invalid.
+    pub fn from_host_config(host: &Value) -> io::Result<DeployTarget> {
+        let name = host
+            .as_str()
+            .or_else(|| host["name"].as_str())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Missing name in one of the hosts",
+                )
+            })?;
+
+        let mut target = DeployTarget::new(name, host["variable_prefix"].as_str())?;
+        if let Some(vars) = host["vars"].as_object() {
+            target.vars = vars.clone();
+        }
+
+        Ok(target)
+    }
+
+    /// Returns the name of the CI variable holding a connection setting, e.g. `PRODUCTION_ANSIBLE_HOST`.
+    pub fn variable(&self, setting: &str) -> String {
+        format!("{}_ANSIBLE_{}", self.variable_prefix, setting)
+    }
+
+    /// Returns the name of the vault variable holding the become password, e.g.
+    /// `vault_web_1_become_pass`.
+    pub fn become_pass_vault_variable(&self) -> String {
+        format!("vault_{}_become_pass", self.name.replace('-', "_"))
+    }
+
+    /// Returns the CI variables read by the host variables.
+    pub fn variables(&self) -> Vec<CiVariable> {
+        [
+            ("HOST", "SSH address of `{}`"),
+            ("PORT", "SSH port of `{}`, 22 when empty"),
+            ("USER", "SSH user Ansible connects to `{}` as"),
+            ("BECOME_PASS", "sudo password of the SSH user on `{}`"),
+        ]
+        .iter()
+        .filter(|(setting, _)| !(self.vault_become_pass && *setting == "BECOME_PASS"))
+        .map(|(setting, description)| CiVariable {
+            name: self.variable(setting),
+            description: description.replace("{}", &self.name),
+        })
+        .collect()
+    }
+
+    /// Renders the `host_vars` file of the host, which looks the connection settings up from the
+    /// environment and the become password up from the environment or the vault.
+    ///
+    /// # Returns
+    /// - `String`: The content of `host_vars/<name>.yml`.
+    pub fn render_host_vars(&self) -> String {
+        let become_pass = if self.vault_become_pass {
+            self.become_pass_vault_variable()
+        } else {
+            format!("lookup('env', '{}')", self.variable("BECOME_PASS"))
+        };
+
+        let mut host_vars = format!(
+            r#"---
+ansible_host: "{{{{ lookup('env', '{host}') }}}}"
+ansible_user: "{{{{ lookup('env', '{user}') }}}}"
+ansible_port: "{{{{ lookup('env', '{port}') | default('22', true) }}}}"
+ansible_become_pass: "{{{{ {become_pass} }}}}"
+"#,
+            host = self.variable("HOST"),
+            user = self.variable("USER"),
+            port = self.variable("PORT"),
+            become_pass = become_pass,
+        );
+        host_vars.push_str(&render_vars(&self.vars));
+
+        host_vars
+    }
+}
+
+/// Renders Ansible variables as YAML lines. Values are written as JSON, which YAML reads as is.
+///
+/// # Parameters
+/// - `vars`: The variables to render.
+///
+/// # Returns
+/// - `String`: One `name: value` line per variable.
+pub fn render_vars(vars: &Map<String, Value>) -> String {
+    vars.iter()
+        .map(|(name, value)| format!("{}: {}\n", name, value))
+        .collect()
+}
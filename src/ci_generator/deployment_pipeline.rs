@@ -0,0 +1,355 @@
+use std::io;
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::{InventorySettings, VAULT_FILE, VAULT_PASSWORD_SCRIPT},
+        ansible_releases::ReleaseSettings,
+        ansible_role::AnsibleRoleSettings,
+        base_image_registry::BaseImageRegistry,
+        blue_green::{BlueGreenSettings, BLUE_GREEN_COMMAND},
+        deploy_environment::{get_deploy_environments, DeployEnvironment},
+        deploy_target::CiVariable,
+        image_registry::{image_registry_variables, ImageRegistrySettings},
+        swarm_stack::{SwarmSettings, STACK_DEPLOY_COMMAND, STACK_DEPLOY_CONDITION},
+    },
+    dotnet_generator::generate_dockerfile_for_dotnet::get_dotnet_base_images,
+    kubernetes_generator::helm_chart::HelmSettings,
+    monorepo_generator::get_monorepo_services::{
+        get_monorepo_dotnet_base_images, get_monorepo_services,
+    },
+};
+
+/// The provider independent description of the deploy, start and ansible stages generated for a
+/// project.
+pub struct DeploymentPipeline {
+    /// The directory on the server the repository is copied to, `project_location/project_directory`.
+    pub project_path: String,
+    /// Directories inside the project path that survive a redeploy.
+    pub preserved_directories: Vec<String>,
+    /// The `dotnet/*` base images pulled from the registry before building, e.g. `dotnet/sdk:9.0`.
+    /// Nothing is pulled when this is empty.
+    pub base_images: Vec<String>,
+    /// The registry the base images are pulled from.
+    pub base_image_registry: BaseImageRegistry,
+    /// The environments the project is deployed to, in deployment order.
+    pub environments: Vec<DeployEnvironment>,
+    /// The CI variable holding the Ansible Vault password, or `None` when the vault is disabled.
+    pub vault_password_variable: Option<String>,
+    /// Whether the playbook applies a generated role, whose collections the ansible jobs install.
+    pub ansible_role: bool,
+    /// Whether the playbook keeps the previous releases, which adds a manual rollback job running
+    /// `ansible-rollback.yml`.
+    pub rollback: bool,
+    /// Whether the services are started with `deploy/blue-green.sh` instead of `docker compose up`.
+    pub blue_green: bool,
+    /// Whether the services are deployed as a stack with `deploy/stack-deploy.sh` instead of
+    /// `docker compose up`.
+    pub swarm: bool,
+    /// The registry a build job pushes the images to, or `None` when the images are built on the
+    /// servers.
+    pub image_registry: Option<ImageRegistrySettings>,
+    /// The charts the helm jobs install on the cluster of each environment, replacing the
+    /// deploy, start and ansible stages, or `None` to deploy the compose files.
+    pub helm: Option<HelmSettings>,
+}
+
+impl DeploymentPipeline {
+    /// Builds the pipeline description from a `dotnet`, `angular` or `monorepo` configuration.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<DeploymentPipeline>`: The pipeline, or an error if a required value is missing.
+    pub fn from_config(config: &Value) -> io::Result<DeploymentPipeline> {
+        let project_location = config["project_location"].as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing project_location in config",
+            )
+        })?;
+        let project_directory = config["project_directory"].as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing project_directory in config",
+            )
+        })?;
+
+        let (preserved_directories, base_images) =
+            match config["project_type"].as_str().unwrap_or("dotnet") {
+                "angular" => (vec!["node_modules".to_string()], Vec::new()),
+                // Go projects only run with the systemd target, without images
+                "go" => (vec!["Logs".to_string()], Vec::new()),
+                "monorepo" => {
+                    let services = get_monorepo_services(config)?;
+                    (
+                        vec!["Logs".to_string(), "node_modules".to_string()],
+                        get_monorepo_dotnet_base_images(services),
+                    )
+                }
+                _ => {
+                    let dotnet_version = config["dotnet_version"].as_str().ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Missing dotnet_version in config",
+                        )
+                    })?;
+                    let app_type = config["app_type"].as_str().unwrap_or("web");
+                    let base_images = get_dotnet_base_images(app_type)
+                        .iter()
+                        .map(|image| format!("dotnet/{}:{}", image, dotnet_version))
+                        .collect();
+                    (vec!["Logs".to_string()], base_images)
+                }
+            };
+
+        // The Microsoft registry needs no pre-pulls, docker pulls the base images while building
+        let base_image_registry = BaseImageRegistry::from_config(config)?;
+        let base_images = if base_image_registry.pre_pulls() {
+            base_images
+        } else {
+            Vec::new()
+        };
+
+        Ok(DeploymentPipeline {
+            project_path: format!("{}/{}", project_location, project_directory),
+            preserved_directories,
+            base_images,
+            base_image_registry,
+            environments: get_deploy_environments(config)?,
+            vault_password_variable: InventorySettings::from_config(config)?
+                .vault_password_variable,
+            ansible_role: AnsibleRoleSettings::from_config(config)?.is_some(),
+            rollback: ReleaseSettings::from_config(config)?.is_some(),
+            blue_green: BlueGreenSettings::from_config(config)?.is_some(),
+            swarm: SwarmSettings::from_config(config)?.is_some(),
+            image_registry: ImageRegistrySettings::from_config(config)?,
+            helm: HelmSettings::from_config(config)?,
+        })
+    }
+
+    /// Returns the `when` condition of the playbook task starting the services, or an empty
+    /// string when every host starts its own.
+    pub fn compose_up_condition(&self) -> &'static str {
+        if self.swarm {
+            STACK_DEPLOY_CONDITION
+        } else {
+            ""
+        }
+    }
+
+    /// Returns the script starting the compose services in the project path.
+    pub fn compose_up_script(&self) -> &'static str {
+        if self.swarm {
+            return STACK_DEPLOY_COMMAND;
+        }
+        match (self.image_registry.is_some(), self.blue_green) {
+            (false, false) => COMPOSE_UP_WITH_RETRY,
+            (false, true) => BLUE_GREEN_COMMAND,
+            (true, false) => PULL_AND_COMPOSE_UP,
+            (true, true) => PULL_AND_BLUE_GREEN,
+        }
+    }
+
+    /// Returns the base images the servers pull before building, none when a build job pushes
+    /// the images to a registry.
+    pub fn server_base_images(&self) -> &[String] {
+        if self.image_registry.is_some() {
+            &[]
+        } else {
+            &self.base_images
+        }
+    }
+
+    /// Returns the registry the servers log in to and pull the base images from, if they build
+    /// .NET images.
+    pub fn server_base_image_registry(&self) -> Option<&BaseImageRegistry> {
+        if self.server_base_images().is_empty() {
+            None
+        } else {
+            Some(&self.base_image_registry)
+        }
+    }
+
+    /// Returns the CI variable the servers read the registry URL of the Dockerfiles from, e.g.
+    /// `SONATYPE_NEXUS_URL`, if it is not set in the configuration.
+    pub fn server_registry_url_variable(&self) -> Option<&'static str> {
+        self.server_base_image_registry()
+            .and_then(|registry| registry.url_variable())
+    }
+
+    /// Returns the registry credentials of the jobs building the images.
+    pub fn registry_variables(&self) -> Vec<CiVariable> {
+        let mut variables = if self.base_images.is_empty() {
+            Vec::new()
+        } else {
+            self.base_image_registry.variables()
+        };
+        if self.image_registry.is_some() {
+            variables.extend(image_registry_variables());
+        }
+        variables
+    }
+
+    /// Returns the registry credentials of the jobs starting the services, here or through Ansible.
+    pub fn server_registry_variables(&self) -> Vec<CiVariable> {
+        if self.image_registry.is_some() {
+            image_registry_variables()
+        } else {
+            self.registry_variables()
+        }
+    }
+
+    /// Returns the files the deploy job copies to the project path: the whole checkout, or only
+    /// what starting the pushed images needs.
+    pub fn deploy_sources(&self) -> &'static str {
+        if self.swarm {
+            return "compose*.yaml stack*.yaml deploy";
+        }
+        match (self.image_registry.is_some(), self.blue_green) {
+            (false, _) => "*",
+            (true, false) => "compose*.yaml deploy",
+            (true, true) => "compose*.yaml deploy proxy",
+        }
+    }
+
+    /// Returns the command writing the commit SHA the images are tagged with to the `.env` file
+    /// of the project path, which docker compose reads on the server and Ansible copies along.
+    pub fn image_tag_command(&self) -> Option<&'static str> {
+        self.image_registry.as_ref().map(|_| {
+            r#"echo "IMAGE_TAG=$(git rev-parse HEAD)" | sudo tee "$PROJECT_PATH/.env" > /dev/null"#
+        })
+    }
+
+    /// Returns every variable the CI provider must define, registry login first.
+    pub fn ci_variables(&self) -> Vec<CiVariable> {
+        let mut ci_variables = self.registry_variables();
+        for environment in &self.environments {
+            ci_variables.extend(self.environment_variables(environment));
+        }
+        if self.helm.is_none() {
+            ci_variables.extend(self.vault_variable());
+        }
+        ci_variables
+    }
+
+    /// Returns the variables giving the jobs of an environment access to it: the kubeconfig of
+    /// its cluster with Helm, the connection settings of its hosts otherwise.
+    pub fn environment_variables(&self, environment: &DeployEnvironment) -> Vec<CiVariable> {
+        if self.helm.is_some() {
+            vec![environment.kubeconfig_variable()]
+        } else {
+            environment.variables()
+        }
+    }
+
+    /// Returns the variables the ansible job of an environment passes to the playbook.
+    pub fn inventory_variables(&self, environment: &DeployEnvironment) -> Vec<CiVariable> {
+        let mut inventory_variables = environment.variables();
+        inventory_variables.extend(self.vault_variable());
+        inventory_variables
+    }
+
+    /// Returns the CI variable holding the vault password, if the vault is enabled.
+    pub fn vault_variable(&self) -> Option<CiVariable> {
+        self.vault_password_variable
+            .as_ref()
+            .map(|variable| CiVariable {
+                name: variable.clone(),
+                description: "Password of the Ansible Vault".to_string(),
+            })
+    }
+
+    /// Returns the options `ansible-inventory` and `ansible-playbook` need to read the vault, or
+    /// an empty string when the vault is disabled.
+    pub fn ansible_vault_options(&self) -> String {
+        match self.vault_password_variable {
+            Some(_) => format!(" --vault-password-file {}", VAULT_PASSWORD_SCRIPT),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the command installing the collections of the generated role, if there is one.
+    pub fn ansible_requirements_command(&self) -> Option<&'static str> {
+        if self.ansible_role {
+            Some("ansible-galaxy collection install -r ansible/requirements.yml")
+        } else {
+            None
+        }
+    }
+
+    /// Returns the shell commands run before Ansible when the vault is enabled.
+    ///
+    /// They refuse to deploy with a vault that is missing or was committed unencrypted.
+    pub fn vault_check_script(&self) -> Option<String> {
+        self.vault_password_variable.as_ref().map(|_| {
+            format!(
+                r#"chmod +x {script}
+if ! head -n 1 {vault} | grep -q '^\$ANSIBLE_VAULT'; then
+  echo "{vault} is missing or not encrypted"
+  exit 1
+fi
+"#,
+                script = VAULT_PASSWORD_SCRIPT,
+                vault = VAULT_FILE
+            )
+        })
+    }
+
+    /// Returns the `find` expression matching the preserved directories, e.g. `-name "Logs"`.
+    pub fn preserved_directories_expression(&self) -> String {
+        self.preserved_directories
+            .iter()
+            .map(|directory| format!(r#"-name "{}""#, directory))
+            .collect::<Vec<String>>()
+            .join(" -o ")
+    }
+}
+
+/// Starts the compose services, retrying once without the build cache if the first attempt fails.
+pub const COMPOSE_UP_WITH_RETRY: &str = r#"# Run docker compose up and retry once if it fails
+if ! docker compose up --build -d --remove-orphans; then
+  echo "docker compose failed. Retrying with cache clearing..."
+  echo "Stopping and removing existing containers..."
+  docker compose down || echo "Failed to stop containers, continuing..."
+  echo "Rebuilding containers without cache..."
+  docker compose build --no-cache
+  if ! docker compose up -d --remove-orphans; then
+    echo "Retry failed. Exiting..."
+    exit 1
+  fi
+fi
+"#;
+
+/// Pulls the images of the release from the registry and starts them.
+pub const PULL_AND_COMPOSE_UP: &str = r#"# Pull the images of the release and start them
+sh deploy/pull-images.sh
+docker compose up -d --remove-orphans
+"#;
+
+/// Pulls the images of the release from the registry and starts them with a blue/green deploy.
+pub const PULL_AND_BLUE_GREEN: &str = "sh deploy/pull-images.sh\nsh deploy/blue-green.sh\n";
+
+/// Indents every non-empty line of a block of text, for embedding scripts in YAML block scalars.
+///
+/// # Parameters
+/// - `text`: The text to indent.
+/// - `spaces`: The number of spaces to prefix each line with.
+///
+/// # Returns
+/// - `String`: The indented text.
+pub fn indent_lines(text: &str, spaces: usize) -> String {
+    let indentation = " ".repeat(spaces);
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indentation, line)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
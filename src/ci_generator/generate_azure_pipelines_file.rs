@@ -0,0 +1,583 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        deploy_environment::DeployEnvironment,
+        deploy_target::CiVariable,
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::{image_registry_variables, IMAGE_BUILD_SCRIPT},
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::{helm_upgrade_command, KUBECONFIG_CONTENT_VARIABLE},
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// The Azure DevOps variable group and agent pool used by the generated `azure-pipelines.yml`.
+pub struct AzurePipelinesSettings {
+    pub pool: String,
+    /// The variable group holding the registry credentials and the deploy target connection settings.
+    pub variable_group: String,
+}
+
+impl AzurePipelinesSettings {
+    /// Reads the settings from the optional `azure_pipelines` section of the configuration.
+    pub fn from_config(config: &Value) -> AzurePipelinesSettings {
+        let azure_pipelines = &config["azure_pipelines"];
+        AzurePipelinesSettings {
+            pool: azure_pipelines["pool"]
+                .as_str()
+                .unwrap_or("docker")
+                .to_string(),
+            variable_group: azure_pipelines["variable_group"]
+                .as_str()
+                .unwrap_or("fileforge-deploy")
+                .to_string(),
+        }
+    }
+}
+
+pub fn generate_azure_pipelines_file() -> io::Result<()> {
+    println!("🚀 Starting Azure Pipelines file generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    let settings = AzurePipelinesSettings::from_config(&config);
+    println!("🌟 Project path: {}", pipeline.project_path);
+    println!("🔐 Variable group: {}", settings.variable_group);
+    thread::sleep(SLEEP_DURATION);
+
+    let azure_pipelines = render_azure_pipelines(&pipeline, &settings);
+
+    let output_path = get_output_directory(&current_dir).join("azure-pipelines.yml");
+    write_file_with_backup(&output_path, &azure_pipelines)?;
+
+    println!("🎉 Generated azure-pipelines.yml in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    if let Some(rollback_pipeline) = render_azure_rollback_pipeline(&pipeline, &settings) {
+        let rollback_path = get_output_directory(&current_dir).join("azure-pipelines.rollback.yml");
+        write_file_with_backup(&rollback_path, &rollback_pipeline)?;
+        println!(
+            "🎉 Generated azure-pipelines.rollback.yml in {:?}",
+            rollback_path
+        );
+    }
+
+    Ok(())
+}
+
+/// The steps of the ansible and rollback jobs, which run `[[ playbook ]]` against the servers of
+/// the environment.
+const ANSIBLE_STEPS: &str = r#"          - script: |
+              if ! command -v ansible-playbook &> /dev/null; then
+                echo "Installing Ansible..."
+                pipx install --include-deps ansible
+                pipx ensurepath
+              fi
+[[ requirements ]]            displayName: Install Ansible
+
+[[ vault_check ]]          - script: ansible-inventory -i ansible/hosts.yml --list[[ vault_options ]] > /dev/null
+            displayName: Validate the inventory
+[[ vault_env ]]
+          - script: ansible-playbook -i ansible/hosts.yml ansible/[[ playbook ]][[ vault_options ]][[ limit ]]
+            displayName: Run Ansible playbook
+[[ ansible_env ]]"#;
+
+/// Renders `azure-pipelines.yml` with the deploy, start and ansible stages of every environment,
+/// or a single helm stage per environment when the charts are installed with Helm.
+///
+/// Secret variables of the variable group are not exposed to scripts automatically, so every
+/// step that needs one maps it into its environment explicitly.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The variable group and agent pool.
+///
+/// # Returns
+/// - `String`: The content of `azure-pipelines.yml`.
+pub fn render_azure_pipelines(
+    pipeline: &DeploymentPipeline,
+    settings: &AzurePipelinesSettings,
+) -> String {
+    let azure_pipelines = r#"trigger:
+  branches:
+    include:
+[[ branches ]]
+pr: none
+
+pool: [[ pool ]]
+
+variables:
+  - group: [[ variable_group ]]
+  - name: PROJECT_PATH
+    value: "[[ project_path ]]"
+
+stages:
+[[ stages ]]"#;
+
+    let stages_template = r#"  - stage: deploy[[ suffix ]]
+[[ condition ]]    jobs:
+[[ approval ]]      - job: deploy
+[[ deploy_depends_on ]]        steps:
+          - script: |
+              sudo mkdir -p "$PROJECT_PATH"
+              sudo find "$PROJECT_PATH" -mindepth 1 -maxdepth 1 ! \( [[ preserved_directories ]] \) -exec rm -rf {} +
+              sudo cp -r [[ sources ]] "$PROJECT_PATH"
+[[ image_tag ]]            displayName: Copy files to the project path
+
+  - stage: start[[ suffix ]]
+    dependsOn: deploy[[ suffix ]]
+    jobs:
+      - job: start
+        steps:
+[[ registry_steps ]]          - script: |
+[[ compose_up ]]
+            workingDirectory: $(PROJECT_PATH)
+            displayName: Start the services
+[[ compose_env ]]
+  - stage: ansible[[ suffix ]]
+    dependsOn: start[[ suffix ]]
+    jobs:
+      - job: ansible
+        steps:
+[[ ansible_steps ]]"#;
+
+    let mut branches: Vec<&str> = Vec::new();
+    let mut stages = Vec::new();
+    for environment in &pipeline.environments {
+        let branch = environment.branch.as_deref().unwrap_or("master");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+
+        // The stages of each environment only depend on each other, so a skipped or waiting
+        // environment doesn't hold back the others
+        let (suffix, condition, mut compose_env) = if environment.configured {
+            let condition = format!(
+                "eq(variables['Build.SourceBranch'], 'refs/heads/{}')",
+                branch
+            );
+            (
+                stage_suffix(environment),
+                if pipeline.image_registry.is_some() {
+                    format!(
+                        "    dependsOn: build\n    condition: and(succeeded(), {})\n",
+                        condition
+                    )
+                } else {
+                    format!("    dependsOn: []\n    condition: {}\n", condition)
+                },
+                format!(
+                    "              COMPOSE_FILE: \"{}\"\n",
+                    environment.compose_file()
+                ),
+            )
+        } else {
+            (String::new(), String::new(), String::new())
+        };
+        if pipeline.image_registry.is_some() {
+            compose_env.push_str(&render_registry_env(&pipeline.server_registry_variables()));
+        }
+        let compose_env = if compose_env.is_empty() {
+            compose_env
+        } else {
+            format!("            env:\n{}", compose_env)
+        };
+
+        // A manual environment waits for an agentless validation job before deploying
+        let (approval, deploy_depends_on) = if environment.configured && environment.manual {
+            (
+                format!(
+                    r#"      - job: approve
+        pool: server
+        steps:
+          - task: ManualValidation@0
+            inputs:
+              notifyUsers: $(Build.RequestedForEmail)
+              instructions: Approve the deployment to {name}
+"#,
+                    name = environment.name
+                ),
+                "        dependsOn: approve\n",
+            )
+        } else {
+            (String::new(), "")
+        };
+
+        if pipeline.helm.is_some() {
+            stages.push(format!(
+                r#"  - stage: helm{suffix}
+{condition}    jobs:
+{approval}      - job: helm
+{depends_on}        steps:
+          - script: {command}
+            displayName: Install the charts
+            env:
+              {content_variable}: $({kubeconfig_variable})
+"#,
+                suffix = suffix,
+                condition = condition,
+                approval = approval,
+                depends_on = deploy_depends_on,
+                command = helm_upgrade_command(environment),
+                content_variable = KUBECONFIG_CONTENT_VARIABLE,
+                kubeconfig_variable = environment.kubeconfig_variable().name,
+            ));
+            continue;
+        }
+
+        stages.push(
+            stages_template
+                .replace("[[ condition ]]", &condition)
+                .replace("[[ approval ]]", &approval)
+                .replace("[[ deploy_depends_on ]]", deploy_depends_on)
+                .replace("[[ compose_env ]]", &compose_env)
+                .replace(
+                    "[[ ansible_steps ]]",
+                    &render_ansible_steps(pipeline, environment, "ansible-deploy.yml"),
+                )
+                .replace("[[ suffix ]]", &suffix),
+        );
+    }
+
+    // The images are built once, before the stages of the environments
+    if pipeline.image_registry.is_some() {
+        stages.insert(0, render_build_stage(pipeline));
+    }
+
+    azure_pipelines
+        .replace(
+            "[[ branches ]]",
+            &branches
+                .iter()
+                .map(|branch| format!("      - {}\n", branch))
+                .collect::<String>(),
+        )
+        .replace("[[ stages ]]", &stages.join("\n"))
+        .replace(
+            "[[ registry_steps ]]",
+            &render_base_image_steps(&pipeline.base_image_registry, pipeline.server_base_images()),
+        )
+        .replace("[[ sources ]]", pipeline.deploy_sources())
+        .replace(
+            "[[ image_tag ]]",
+            &pipeline
+                .image_tag_command()
+                .map(|command| format!("              {}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace(
+            "[[ compose_up ]]",
+            &indent_lines(pipeline.compose_up_script(), 14),
+        )
+        .replace(
+            "[[ preserved_directories ]]",
+            &pipeline.preserved_directories_expression(),
+        )
+        .replace("[[ pool ]]", &settings.pool)
+        .replace("[[ variable_group ]]", &settings.variable_group)
+        .replace("[[ project_path ]]", &pipeline.project_path)
+}
+
+/// Renders the manually run pipeline executing `ansible-rollback.yml` against the environment
+/// picked in its `environment` parameter, or `None` when the playbook does not keep previous
+/// releases.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The variable group and agent pool.
+///
+/// # Returns
+/// - `Option<String>`: The content of `azure-pipelines.rollback.yml`.
+pub fn render_azure_rollback_pipeline(
+    pipeline: &DeploymentPipeline,
+    settings: &AzurePipelinesSettings,
+) -> Option<String> {
+    if !pipeline.rollback {
+        return None;
+    }
+
+    let rollback_pipeline = r#"trigger: none
+
+pr: none
+
+parameters:
+  - name: environment
+    displayName: The environment to roll back to its previous release
+    type: string
+    values:
+[[ values ]]
+pool: [[ pool ]]
+
+variables:
+  - group: [[ variable_group ]]
+
+stages:
+[[ stages ]]"#;
+
+    let stages = pipeline
+        .environments
+        .iter()
+        .map(|environment| {
+            format!(
+                r#"  - stage: rollback{suffix}
+    dependsOn: []
+    condition: eq('${{{{ parameters.environment }}}}', '{name}')
+    jobs:
+      - job: rollback
+        steps:
+{steps}"#,
+                suffix = stage_suffix(environment),
+                name = environment.name,
+                steps = render_ansible_steps(pipeline, environment, "ansible-rollback.yml"),
+            )
+        })
+        .collect::<Vec<String>>();
+
+    Some(
+        rollback_pipeline
+            .replace(
+                "[[ values ]]",
+                &pipeline
+                    .environments
+                    .iter()
+                    .map(|environment| format!("      - {}\n", environment.name))
+                    .collect::<String>(),
+            )
+            .replace("[[ stages ]]", &stages.join("\n"))
+            .replace("[[ pool ]]", &settings.pool)
+            .replace("[[ variable_group ]]", &settings.variable_group),
+    )
+}
+
+/// Returns the suffix of the stage names of a configured environment, e.g. `_staging`.
+fn stage_suffix(environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!("_{}", environment.name.replace('-', "_"))
+    } else {
+        String::new()
+    }
+}
+
+/// Renders the stage building the images and pushing them to the image registry.
+fn render_build_stage(pipeline: &DeploymentPipeline) -> String {
+    format!(
+        r#"  - stage: build
+    jobs:
+      - job: build
+        steps:
+{base_image_steps}          - script: sh {build_script}
+            displayName: Build and push the images
+            env:
+{registry_env}"#,
+        base_image_steps =
+            render_base_image_steps(&pipeline.base_image_registry, &pipeline.base_images),
+        build_script = IMAGE_BUILD_SCRIPT,
+        registry_env = render_registry_env(&image_registry_variables()),
+    )
+}
+
+/// Renders the step logging in to the registry and pulling the base images of the .NET builds.
+fn render_base_image_steps(registry: &BaseImageRegistry, base_images: &[String]) -> String {
+    if base_images.is_empty() {
+        return String::new();
+    }
+
+    let commands = registry
+        .login_command()
+        .into_iter()
+        .chain(base_images.iter().map(|image| registry.pull_command(image)))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let registry_env = render_registry_env(&registry.variables());
+    format!(
+        r#"          - script: |
+{commands}
+            displayName: {display_name}
+{env}
+"#,
+        commands = indent_lines(&commands, 14),
+        display_name = if registry.login {
+            "Log in to the registry and pull the base images"
+        } else {
+            "Pull the base images"
+        },
+        env = if registry_env.is_empty() {
+            String::new()
+        } else {
+            format!("            env:\n{}", registry_env)
+        },
+    )
+}
+
+/// Maps the given registry credentials into the environment of a step.
+fn render_registry_env(variables: &[CiVariable]) -> String {
+    variables
+        .iter()
+        .map(|variable| format!("              {name}: $({name})\n", name = variable.name))
+        .collect()
+}
+
+/// Renders the steps running a playbook against the servers of an environment.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `environment`: The environment whose servers the playbook runs against.
+/// - `playbook`: The playbook in the `ansible` directory, e.g. `ansible-deploy.yml`.
+///
+/// # Returns
+/// - `String`: The steps of the job.
+fn render_ansible_steps(
+    pipeline: &DeploymentPipeline,
+    environment: &DeployEnvironment,
+    playbook: &str,
+) -> String {
+    let limit = if environment.configured {
+        format!(" --limit {}", environment.host_group)
+    } else {
+        String::new()
+    };
+
+    // The host variables read the connection settings of the servers from these variables
+    let inventory_env = pipeline
+        .inventory_variables(environment)
+        .iter()
+        .map(|variable| format!("              {name}: $({name})\n", name = variable.name))
+        .collect::<String>();
+    let ansible_env = format!(
+        "            env:\n{}{}",
+        render_registry_env(&pipeline.server_registry_variables()),
+        inventory_env
+    );
+
+    // Secret variables only reach the scripts through `env`, so the vault password is mapped to
+    // every step that reads the vault
+    let (vault_check, vault_env) = match (
+        pipeline.vault_check_script(),
+        &pipeline.vault_password_variable,
+    ) {
+        (Some(script), Some(variable)) => (
+            format!(
+                "          - script: |\n{}\n            displayName: Check the Ansible Vault\n\n",
+                indent_lines(&script, 14)
+            ),
+            format!(
+                "            env:\n              {name}: $({name})\n",
+                name = variable
+            ),
+        ),
+        _ => (String::new(), String::new()),
+    };
+
+    ANSIBLE_STEPS
+        .replace(
+            "[[ requirements ]]",
+            &pipeline
+                .ansible_requirements_command()
+                .map(|command| format!("              {}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace("[[ vault_check ]]", &vault_check)
+        .replace("[[ vault_env ]]", &vault_env)
+        .replace("[[ vault_options ]]", &pipeline.ansible_vault_options())
+        .replace("[[ limit ]]", &limit)
+        .replace("[[ ansible_env ]]", &ansible_env)
+        .replace("[[ playbook ]]", playbook)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_yaml::Value as Yaml;
+
+    use super::*;
+
+    fn render() -> Yaml {
+        let config = json!({
+            "project_type": "dotnet",
+            "project_location": "/etc/www",
+            "project_directory": "shop",
+            "dotnet_version": "9.0",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "image_name": "shop-api",
+            "container_name": "shop-api",
+            "port": 8080,
+            "environments": [
+                { "name": "staging", "branch": "develop" },
+                { "name": "production", "branch": "main", "manual": true }
+            ],
+            "azure_pipelines": { "variable_group": "shop-deploy" }
+        });
+        let pipeline = DeploymentPipeline::from_config(&config).unwrap();
+        let settings = AzurePipelinesSettings::from_config(&config);
+        serde_yaml::from_str(&render_azure_pipelines(&pipeline, &settings)).unwrap()
+    }
+
+    #[test]
+    fn pipeline_reads_the_variable_group() {
+        let pipeline = render();
+
+        assert_eq!(pipeline["variables"][0]["group"], "shop-deploy");
+        assert_eq!(pipeline["variables"][1]["value"], "/etc/www/shop");
+        assert_eq!(
+            pipeline["trigger"]["branches"]["include"],
+            serde_yaml::from_str::<Yaml>("[develop, main]").unwrap()
+        );
+    }
+
+    #[test]
+    fn pipeline_chains_the_stages_of_every_environment() {
+        let pipeline = render();
+        let stages = pipeline["stages"].as_sequence().unwrap();
+
+        let names = stages
+            .iter()
+            .map(|stage| stage["stage"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            [
+                "deploy_staging",
+                "start_staging",
+                "ansible_staging",
+                "deploy_production",
+                "start_production",
+                "ansible_production"
+            ]
+        );
+        assert_eq!(
+            stages[3]["condition"],
+            "eq(variables['Build.SourceBranch'], 'refs/heads/main')"
+        );
+        assert_eq!(stages[4]["dependsOn"], "deploy_production");
+        assert_eq!(stages[5]["dependsOn"], "start_production");
+    }
+
+    #[test]
+    fn manual_environments_wait_for_an_approval() {
+        let pipeline = render();
+        let staging_jobs = &pipeline["stages"][0]["jobs"];
+        let production_jobs = &pipeline["stages"][3]["jobs"];
+
+        assert_eq!(staging_jobs.as_sequence().unwrap().len(), 1);
+        assert_eq!(production_jobs[0]["job"], "approve");
+        assert_eq!(production_jobs[0]["pool"], "server");
+        assert_eq!(production_jobs[0]["steps"][0]["task"], "ManualValidation@0");
+        assert_eq!(production_jobs[1]["job"], "deploy");
+        assert_eq!(production_jobs[1]["dependsOn"], "approve");
+    }
+}
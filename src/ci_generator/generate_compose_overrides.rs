@@ -0,0 +1,54 @@
+use std::{env, io, thread};
+
+use crate::{
+    ci_generator::deploy_environment::{get_compose_services, get_deploy_environments},
+    constants::SLEEP_DURATION,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// Generates a `compose.<environment>.yaml` override file for every configured environment.
+///
+/// Projects without `environments` have nothing to override, so no file is written.
+pub fn generate_compose_overrides() -> io::Result<()> {
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+
+    let config = get_current_config(current_dir.clone());
+    let environments = get_deploy_environments(&config)?;
+    let services = get_compose_services(&config)?;
+
+    for environment in &environments {
+        if let Some((name, _)) = environment
+            .service_overrides
+            .iter()
+            .find(|(name, _)| !services.iter().any(|service| service.name == *name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Environment {} overrides the unknown service {}",
+                    environment.name, name
+                ),
+            ));
+        }
+
+        if let Some(compose_override_file) = environment.compose_override_file() {
+            println!(
+                "🌍 Writing the {} overrides to {}...",
+                environment.name, compose_override_file
+            );
+            thread::sleep(SLEEP_DURATION);
+
+            let output_path = get_output_directory(&current_dir).join(&compose_override_file);
+            write_file_with_backup(
+                &output_path,
+                &environment.render_compose_override(&services),
+            )?;
+        }
+    }
+
+    Ok(())
+}
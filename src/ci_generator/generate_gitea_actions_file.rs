@@ -0,0 +1,107 @@
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{
+    ci_generator::{
+        deployment_pipeline::DeploymentPipeline,
+        generate_github_actions_file::{
+            render_github_actions_rollback_workflow, render_github_actions_workflow,
+        },
+    },
+    constants::SLEEP_DURATION,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// Returns the path of a workflow in the `.gitea/workflows` directory, which Gitea and Forgejo
+/// both read.
+///
+/// # Parameters
+/// - `output_dir`: The directory the generated files are written to.
+/// - `file_name`: The file name of the workflow, e.g. `deploy.yml`.
+///
+/// # Returns
+/// - `PathBuf`: The path of the workflow.
+pub fn gitea_workflow_path(output_dir: &Path, file_name: &str) -> PathBuf {
+    output_dir.join(".gitea").join("workflows").join(file_name)
+}
+
+/// Generates the Gitea/Forgejo Actions workflow.
+///
+/// Gitea and Forgejo Actions understand the GitHub Actions syntax, including `secrets.*` and
+/// `actions/checkout`, so the workflow is the same as the GitHub one. Forgejo also reads
+/// workflows from `.gitea/workflows/`.
+pub fn generate_gitea_actions_file() -> io::Result<()> {
+    println!("🚀 Starting Gitea Actions workflow generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    println!("🌟 Project path: {}", pipeline.project_path);
+    thread::sleep(SLEEP_DURATION);
+
+    let workflow = render_github_actions_workflow(&pipeline);
+
+    let output_path = gitea_workflow_path(&get_output_directory(&current_dir), "deploy.yml");
+    write_file_with_backup(&output_path, &workflow)?;
+
+    println!("🎉 Generated Gitea Actions workflow in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    if let Some(rollback_workflow) = render_github_actions_rollback_workflow(&pipeline) {
+        let rollback_path =
+            gitea_workflow_path(&get_output_directory(&current_dir), "rollback.yml");
+        write_file_with_backup(&rollback_path, &rollback_workflow)?;
+        println!(
+            "🎉 Generated Gitea Actions rollback workflow in {:?}",
+            rollback_path
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::ci_generator::handle_ci_generation::CiProvider;
+
+    #[test]
+    fn workflows_are_written_to_the_gitea_directory() {
+        assert_eq!(
+            gitea_workflow_path(Path::new("/repo"), "deploy.yml"),
+            PathBuf::from("/repo/.gitea/workflows/deploy.yml")
+        );
+        assert_eq!(
+            gitea_workflow_path(Path::new("/repo"), "rollback.yml"),
+            PathBuf::from("/repo/.gitea/workflows/rollback.yml")
+        );
+    }
+
+    #[test]
+    fn gitea_and_forgejo_use_this_generator() {
+        for ci_provider in ["gitea", "forgejo"] {
+            assert_eq!(
+                CiProvider::from_config(&json!({ "ci_provider": ci_provider })).unwrap(),
+                CiProvider::Gitea
+            );
+        }
+        assert_eq!(
+            CiProvider::from_config(&json!({ "ci_provider": "github" })).unwrap(),
+            CiProvider::GitHub
+        );
+        assert!(CiProvider::from_config(&json!({ "ci_provider": "gogs" })).is_err());
+    }
+}
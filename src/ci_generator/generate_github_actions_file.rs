@@ -0,0 +1,595 @@
+use std::{env, io, thread};
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        deploy_environment::DeployEnvironment,
+        deploy_target::CiVariable,
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::IMAGE_BUILD_SCRIPT,
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::{helm_upgrade_command, KUBECONFIG_CONTENT_VARIABLE},
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+pub fn generate_github_actions_file() -> io::Result<()> {
+    println!("🚀 Starting GitHub Actions workflow generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    println!("🌟 Project path: {}", pipeline.project_path);
+    thread::sleep(SLEEP_DURATION);
+
+    let workflow = render_github_actions_workflow(&pipeline);
+
+    let output_path = get_output_directory(&current_dir)
+        .join(".github")
+        .join("workflows")
+        .join("deploy.yml");
+    write_file_with_backup(&output_path, &workflow)?;
+
+    println!("🎉 Generated GitHub Actions workflow in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    if let Some(rollback_workflow) = render_github_actions_rollback_workflow(&pipeline) {
+        let rollback_path = get_output_directory(&current_dir)
+            .join(".github")
+            .join("workflows")
+            .join("rollback.yml");
+        write_file_with_backup(&rollback_path, &rollback_workflow)?;
+        println!(
+            "🎉 Generated GitHub Actions rollback workflow in {:?}",
+            rollback_path
+        );
+    }
+
+    Ok(())
+}
+
+/// The steps of the ansible and rollback jobs, which run `[[ playbook ]]` against the servers of
+/// the environment.
+const ANSIBLE_STEPS: &str = r#"    steps:
+      - name: Checkout
+        uses: actions/checkout@v4
+
+      - name: Install Ansible
+        run: |
+          if ! command -v ansible-playbook &> /dev/null; then
+            echo "Installing Ansible..."
+            pipx install --include-deps ansible
+            pipx ensurepath
+          fi
+[[ requirements ]]
+[[ vault_check ]]      - name: Validate the inventory
+[[ vault_env ]]        run: ansible-inventory -i ansible/hosts.yml --list[[ vault_options ]] > /dev/null
+
+      - name: Run Ansible playbook
+        env:
+[[ inventory_env ]]
+        run: ansible-playbook -i ansible/hosts.yml ansible/[[ playbook ]][[ vault_options ]][[ limit ]]
+"#;
+
+/// Renders the GitHub Actions workflow with the deploy, start and ansible jobs of every
+/// environment, or a single helm job per environment when the charts are installed with Helm.
+///
+/// The jobs run on a self-hosted runner labelled `docker` on the target server, like the GitLab
+/// pipeline. Registry credentials and the connection settings of the deploy target are read from
+/// repository secrets.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+///
+/// # Returns
+/// - `String`: The content of `.github/workflows/deploy.yml`.
+pub fn render_github_actions_workflow(pipeline: &DeploymentPipeline) -> String {
+    let workflow = r#"name: Deploy
+
+on:
+  push:
+    branches:
+[[ branches ]]
+env:
+  PROJECT_PATH: "[[ project_path ]]"
+
+jobs:
+[[ jobs ]]"#;
+
+    let jobs_template = r#"  deploy[[ suffix ]]:
+[[ needs_build ]][[ condition ]]    runs-on: [self-hosted, docker]
+[[ environment ]]    steps:
+      - name: Checkout
+        uses: actions/checkout@v4
+
+      - name: Copy files to the project path
+        run: |
+          sudo mkdir -p "$PROJECT_PATH"
+          sudo find "$PROJECT_PATH" -mindepth 1 -maxdepth 1 ! \( [[ preserved_directories ]] \) -exec rm -rf {} +
+          sudo cp -r [[ sources ]] "$PROJECT_PATH"
+[[ image_tag ]]
+  start[[ suffix ]]:
+    needs: deploy[[ suffix ]]
+    runs-on: [self-hosted, docker]
+[[ registry_env ]]    steps:
+[[ registry_steps ]]      - name: Start the services
+        working-directory: ${{ env.PROJECT_PATH }}
+[[ compose_file_env ]]        run: |
+[[ compose_up ]]
+
+  ansible[[ suffix ]]:
+    needs: start[[ suffix ]]
+    runs-on: [self-hosted, docker]
+[[ registry_env ]][[ ansible_steps ]]"#;
+
+    let helm_job_template = r#"  helm[[ suffix ]]:
+[[ needs_build ]][[ condition ]]    runs-on: [self-hosted, docker]
+[[ environment ]]    steps:
+      - name: Checkout
+        uses: actions/checkout@v4
+
+      - name: Install the charts
+        env:
+          [[ content_variable ]]: ${{ secrets.[[ kubeconfig_variable ]] }}
+        run: [[ helm_upgrade ]]
+"#;
+
+    let mut branches: Vec<&str> = Vec::new();
+    let mut jobs = Vec::new();
+    for environment in &pipeline.environments {
+        let branch = environment.branch.as_deref().unwrap_or("master");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+
+        // Configured environments only deploy from their own branch and are tracked as GitHub
+        // environments, whose protection rules provide the manual approval
+        let (condition, environment_name, compose_file_env) = if environment.configured {
+            (
+                format!("    if: github.ref == 'refs/heads/{}'\n", branch),
+                format!("    environment: {}\n", environment.name),
+                format!(
+                    "        env:\n          COMPOSE_FILE: \"{}\"\n",
+                    environment.compose_file()
+                ),
+            )
+        } else {
+            (String::new(), String::new(), String::new())
+        };
+
+        if pipeline.helm.is_some() {
+            jobs.push(
+                helm_job_template
+                    .replace("[[ condition ]]", &condition)
+                    .replace("[[ environment ]]", &environment_name)
+                    .replace("[[ content_variable ]]", KUBECONFIG_CONTENT_VARIABLE)
+                    .replace(
+                        "[[ kubeconfig_variable ]]",
+                        &environment.kubeconfig_variable().name,
+                    )
+                    .replace("[[ helm_upgrade ]]", &helm_upgrade_command(environment))
+                    .replace("[[ suffix ]]", &environment.job_suffix()),
+            );
+            continue;
+        }
+
+        jobs.push(
+            jobs_template
+                .replace(
+                    "[[ ansible_steps ]]",
+                    &render_ansible_steps(pipeline, environment, "ansible-deploy.yml"),
+                )
+                .replace("[[ condition ]]", &condition)
+                .replace("[[ environment ]]", &environment_name)
+                .replace("[[ compose_file_env ]]", &compose_file_env)
+                .replace("[[ suffix ]]", &environment.job_suffix()),
+        );
+    }
+
+    // The images are built once and deployed to every environment
+    if pipeline.image_registry.is_some() {
+        jobs.insert(0, render_build_job(pipeline));
+    }
+
+    workflow
+        .replace(
+            "[[ branches ]]",
+            &branches
+                .iter()
+                .map(|branch| format!("      - {}\n", branch))
+                .collect::<String>(),
+        )
+        .replace("[[ jobs ]]", &jobs.join("\n"))
+        .replace(
+            "[[ needs_build ]]",
+            if pipeline.image_registry.is_some() {
+                "    needs: build\n"
+            } else {
+                ""
+            },
+        )
+        .replace("[[ sources ]]", pipeline.deploy_sources())
+        .replace(
+            "[[ image_tag ]]",
+            &pipeline
+                .image_tag_command()
+                .map(|command| format!("          {}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace(
+            "[[ registry_env ]]",
+            &render_registry_env(&pipeline.server_registry_variables()),
+        )
+        .replace(
+            "[[ registry_steps ]]",
+            &render_base_image_steps(&pipeline.base_image_registry, pipeline.server_base_images()),
+        )
+        .replace(
+            "[[ compose_up ]]",
+            &indent_lines(pipeline.compose_up_script(), 10),
+        )
+        .replace(
+            "[[ preserved_directories ]]",
+            &pipeline.preserved_directories_expression(),
+        )
+        .replace("[[ project_path ]]", &pipeline.project_path)
+}
+
+/// Renders the manually dispatched workflow running `ansible-rollback.yml` against the environment
+/// picked when it is started, or `None` when the playbook does not keep previous releases.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+///
+/// # Returns
+/// - `Option<String>`: The content of `.github/workflows/rollback.yml`.
+pub fn render_github_actions_rollback_workflow(pipeline: &DeploymentPipeline) -> Option<String> {
+    if !pipeline.rollback {
+        return None;
+    }
+
+    let workflow = r#"name: Rollback
+
+on:
+  workflow_dispatch:
+    inputs:
+      environment:
+        description: The environment to roll back to its previous release
+        type: choice
+        options:
+[[ options ]]
+jobs:
+[[ jobs ]]"#;
+
+    let job_template = r#"  rollback[[ suffix ]]:
+    if: inputs.environment == '[[ name ]]'
+    runs-on: [self-hosted, docker]
+[[ environment ]][[ registry_env ]][[ ansible_steps ]]"#;
+
+    let jobs = pipeline
+        .environments
+        .iter()
+        .map(|environment| {
+            let environment_name = if environment.configured {
+                format!("    environment: {}\n", environment.name)
+            } else {
+                String::new()
+            };
+
+            job_template
+                .replace(
+                    "[[ ansible_steps ]]",
+                    &render_ansible_steps(pipeline, environment, "ansible-rollback.yml"),
+                )
+                .replace("[[ environment ]]", &environment_name)
+                .replace(
+                    "[[ registry_env ]]",
+                    &render_registry_env(&pipeline.server_registry_variables()),
+                )
+                .replace("[[ name ]]", &environment.name)
+                .replace("[[ suffix ]]", &environment.job_suffix())
+        })
+        .collect::<Vec<String>>();
+
+    Some(
+        workflow
+            .replace(
+                "[[ options ]]",
+                &pipeline
+                    .environments
+                    .iter()
+                    .map(|environment| format!("          - {}\n", environment.name))
+                    .collect::<String>(),
+            )
+            .replace("[[ jobs ]]", &jobs.join("\n")),
+    )
+}
+
+/// Renders the job building the images and pushing them to the image registry.
+fn render_build_job(pipeline: &DeploymentPipeline) -> String {
+    format!(
+        r#"  build:
+    runs-on: [self-hosted, docker]
+{registry_env}    steps:
+      - name: Checkout
+        uses: actions/checkout@v4
+
+{base_image_steps}      - name: Build and push the images
+        run: sh {build_script}
+"#,
+        registry_env = render_registry_env(&pipeline.registry_variables()),
+        base_image_steps =
+            render_base_image_steps(&pipeline.base_image_registry, &pipeline.base_images),
+        build_script = IMAGE_BUILD_SCRIPT,
+    )
+}
+
+/// Renders the steps logging in to the registry and pulling the base images of the .NET builds.
+fn render_base_image_steps(registry: &BaseImageRegistry, base_images: &[String]) -> String {
+    if base_images.is_empty() {
+        return String::new();
+    }
+
+    let docker_pulls = base_images
+        .iter()
+        .map(|image| format!("          {}", registry.pull_command(image)))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let login = registry
+        .login_command()
+        .map(|login_command| {
+            format!(
+                "      - name: Log in to the Docker registry\n        run: {}\n\n",
+                login_command
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"{login}      - name: Pull the base images
+        run: |
+{docker_pulls}
+
+"#,
+        login = login,
+        docker_pulls = docker_pulls
+    )
+}
+
+/// Passes the given registry credentials to a job from the repository secrets.
+fn render_registry_env(variables: &[CiVariable]) -> String {
+    if variables.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        "    env:\n{}",
+        variables
+            .iter()
+            .map(|variable| format!(
+                "      {name}: ${{{{ secrets.{name} }}}}\n",
+                name = variable.name
+            ))
+            .collect::<String>()
+    )
+}
+
+/// Renders the steps of a job running a playbook against the servers of an environment.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `environment`: The environment whose servers the playbook runs against.
+/// - `playbook`: The playbook in the `ansible` directory, e.g. `ansible-deploy.yml`.
+///
+/// # Returns
+/// - `String`: The `steps:` of the job.
+fn render_ansible_steps(
+    pipeline: &DeploymentPipeline,
+    environment: &DeployEnvironment,
+    playbook: &str,
+) -> String {
+    let limit = if environment.configured {
+        format!(" --limit {}", environment.host_group)
+    } else {
+        String::new()
+    };
+
+    // The host variables read the connection settings of the servers from these variables
+    let inventory_env = pipeline
+        .inventory_variables(environment)
+        .iter()
+        .map(|variable| {
+            format!(
+                "          {name}: ${{{{ secrets.{name} }}}}",
+                name = variable.name
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let (vault_check, vault_env) = match (
+        pipeline.vault_check_script(),
+        &pipeline.vault_password_variable,
+    ) {
+        (Some(script), Some(variable)) => (
+            format!(
+                "      - name: Check the Ansible Vault\n        run: |\n{}\n\n",
+                indent_lines(&script, 10)
+            ),
+            format!(
+                "        env:\n          {name}: ${{{{ secrets.{name} }}}}\n",
+                name = variable
+            ),
+        ),
+        _ => (String::new(), String::new()),
+    };
+
+    ANSIBLE_STEPS
+        .replace(
+            "[[ requirements ]]",
+            &pipeline
+                .ansible_requirements_command()
+                .map(|command| format!("          {}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace("[[ vault_check ]]", &vault_check)
+        .replace("[[ vault_env ]]", &vault_env)
+        .replace("[[ vault_options ]]", &pipeline.ansible_vault_options())
+        .replace("[[ inventory_env ]]", &inventory_env)
+        .replace("[[ limit ]]", &limit)
+        .replace("[[ playbook ]]", playbook)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_yaml::Value as Yaml;
+
+    use super::*;
+
+    fn config() -> serde_json::Value {
+        json!({
+            "project_type": "dotnet",
+            "project_location": "/etc/www",
+            "project_directory": "shop",
+            "dotnet_version": "9.0",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "image_name": "shop-api",
+            "container_name": "shop-api",
+            "port": 8080,
+            "environments": [
+                { "name": "staging", "branch": "develop" },
+                { "name": "production", "branch": "main", "manual": true }
+            ]
+        })
+    }
+
+    fn render(config: &serde_json::Value) -> Yaml {
+        let pipeline = DeploymentPipeline::from_config(config).unwrap();
+        serde_yaml::from_str(&render_github_actions_workflow(&pipeline)).unwrap()
+    }
+
+    #[test]
+    fn workflow_runs_on_pushes_to_the_environment_branches() {
+        let workflow = render(&config());
+
+        assert_eq!(
+            workflow["on"]["push"]["branches"],
+            serde_yaml::from_str::<Yaml>("[develop, main]").unwrap()
+        );
+        assert_eq!(workflow["env"]["PROJECT_PATH"], "/etc/www/shop");
+    }
+
+    #[test]
+    fn workflow_chains_the_jobs_of_every_environment() {
+        let workflow = render(&config());
+        let jobs = workflow["jobs"].as_mapping().unwrap();
+
+        let names = jobs
+            .keys()
+            .map(|name| name.as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            [
+                "deploy-staging",
+                "start-staging",
+                "ansible-staging",
+                "deploy-production",
+                "start-production",
+                "ansible-production"
+            ]
+        );
+        assert_eq!(
+            workflow["jobs"]["deploy-staging"]["if"],
+            "github.ref == 'refs/heads/develop'"
+        );
+        assert_eq!(
+            workflow["jobs"]["deploy-production"]["environment"],
+            "production"
+        );
+        assert_eq!(
+            workflow["jobs"]["start-production"]["needs"],
+            "deploy-production"
+        );
+        assert_eq!(
+            workflow["jobs"]["ansible-production"]["needs"],
+            "start-production"
+        );
+    }
+
+    #[test]
+    fn workflow_reads_the_credentials_from_secrets() {
+        let workflow = render(&config());
+
+        let start_env = &workflow["jobs"]["start-staging"]["env"];
+        assert_eq!(
+            start_env["SONATYPE_NEXUS_PASSWORD"],
+            "${{ secrets.SONATYPE_NEXUS_PASSWORD }}"
+        );
+
+        let playbook_step = workflow["jobs"]["ansible-staging"]["steps"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|step| step["name"] == "Run Ansible playbook")
+            .unwrap();
+        for variable in ["HOST", "PORT", "USER", "BECOME_PASS"] {
+            assert_eq!(
+                playbook_step["env"][format!("STAGING_ANSIBLE_{}", variable).as_str()],
+                format!("${{{{ secrets.STAGING_ANSIBLE_{} }}}}", variable).as_str()
+            );
+        }
+        assert!(playbook_step["run"]
+            .as_str()
+            .unwrap()
+            .ends_with("ansible/ansible-deploy.yml --limit staging"));
+    }
+
+    #[test]
+    fn workflow_builds_the_images_before_deploying_with_a_registry() {
+        let mut config = config();
+        config["image_registry"] = json!({ "url": "registry.example.com/shop" });
+        let workflow = render(&config);
+
+        assert!(workflow["jobs"]["build"].is_mapping());
+        assert!(
+            workflow["jobs"]["start-staging"]["env"]["IMAGE_REGISTRY_PASSWORD"]
+                .as_str()
+                .unwrap()
+                .starts_with("${{ secrets.")
+        );
+    }
+
+    #[test]
+    fn rollback_workflow_is_dispatched_with_an_environment() {
+        let pipeline = DeploymentPipeline::from_config(&config()).unwrap();
+        assert!(render_github_actions_rollback_workflow(&pipeline).is_none());
+
+        let mut config = config();
+        config["ansible"] = json!({ "keep_releases": 3 });
+        let pipeline = DeploymentPipeline::from_config(&config).unwrap();
+        let workflow: Yaml =
+            serde_yaml::from_str(&render_github_actions_rollback_workflow(&pipeline).unwrap())
+                .unwrap();
+
+        assert_eq!(
+            workflow["on"]["workflow_dispatch"]["inputs"]["environment"]["options"],
+            serde_yaml::from_str::<Yaml>("[staging, production]").unwrap()
+        );
+        assert_eq!(
+            workflow["jobs"]["rollback-production"]["if"],
+            "inputs.environment == 'production'"
+        );
+    }
+}
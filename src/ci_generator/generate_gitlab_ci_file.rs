@@ -0,0 +1,606 @@
+use std::{collections::HashMap, env, fs, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        deploy_environment::DeployEnvironment,
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::IMAGE_BUILD_SCRIPT,
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::{helm_upgrade_command, KUBECONFIG_CONTENT_VARIABLE},
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// The jobs of the deploy pipeline, in stage order.
+const DEPLOY_STAGES: [&str; 3] = ["deploy", "start", "ansible"];
+
+/// When and where the generated GitLab CI jobs run.
+pub struct GitLabSettings {
+    /// Branches that deploy. Values wrapped in slashes, like `/^release\/.*$/`, are regexes.
+    pub branches: Vec<String>,
+    /// Whether tag pipelines deploy as well.
+    pub deploy_tags: bool,
+    /// Whether merge request pipelines run. They only validate the compose and Ansible files.
+    pub merge_requests: bool,
+    /// The enabled deploy stages, a subset of `deploy`, `start` and `ansible`.
+    pub stages: Vec<String>,
+    /// Stages whose job waits for a manual approval before running.
+    pub manual_stages: Vec<String>,
+    /// The runner tags of every job, unless overridden in `job_runner_tags`.
+    pub runner_tags: Vec<String>,
+    /// Runner tags per stage, e.g. `{"ansible": ["ansible"]}`.
+    pub job_runner_tags: HashMap<String, Vec<String>>,
+}
+
+impl GitLabSettings {
+    /// Reads the settings from the optional `gitlab` section of the configuration.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<GitLabSettings>`: The settings, or an error if a stage name is unknown.
+    pub fn from_config(config: &Value) -> io::Result<GitLabSettings> {
+        let gitlab = &config["gitlab"];
+
+        let stages = string_list(&gitlab["stages"], &DEPLOY_STAGES);
+        let manual_stages = string_list(&gitlab["manual_stages"], &[]);
+        if let Some(stage) = stages
+            .iter()
+            .chain(manual_stages.iter())
+            .find(|stage| !DEPLOY_STAGES.contains(&stage.as_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown GitLab CI stage: {}", stage),
+            ));
+        }
+
+        let job_runner_tags = gitlab["job_runner_tags"]
+            .as_object()
+            .map(|job_runner_tags| {
+                job_runner_tags
+                    .iter()
+                    .map(|(stage, tags)| (stage.clone(), string_list(tags, &[])))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(GitLabSettings {
+            branches: string_list(&gitlab["branches"], &["master"]),
+            deploy_tags: gitlab["deploy_tags"].as_bool().unwrap_or(false),
+            merge_requests: gitlab["merge_requests"].as_bool().unwrap_or(false),
+            stages,
+            manual_stages,
+            runner_tags: string_list(&gitlab["runner_tags"], &["docker"]),
+            job_runner_tags,
+        })
+    }
+
+    /// Returns the runner tags of the job in the given stage.
+    pub fn runner_tags_for(&self, stage: &str) -> &[String] {
+        self.job_runner_tags.get(stage).unwrap_or(&self.runner_tags)
+    }
+}
+
+/// Reads a list of strings from the configuration, falling back to a default when missing.
+fn string_list(value: &Value, default: &[&str]) -> Vec<String> {
+    match value.as_array() {
+        Some(values) => values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(|value| value.to_string())
+            .collect(),
+        None => default.iter().map(|value| value.to_string()).collect(),
+    }
+}
+
+pub fn generate_gitlab_ci_file() -> io::Result<()> {
+    println!("🚀 Starting gitlab ci file generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    let settings = GitLabSettings::from_config(&config)?;
+    println!("🌟 Project path: {}", pipeline.project_path);
+    println!("🌿 Deploy branches: {}", settings.branches.join(", "));
+    thread::sleep(SLEEP_DURATION);
+
+    let gitlab_ci = render_gitlab_ci(&pipeline, &settings);
+
+    let output_path = get_output_directory(&current_dir).join(".gitlab-ci.yml");
+
+    // Pipelines generated before the deploy targets read `ANSIBLE_HOST_DELL_SERVER` and similar
+    // variables, which the new pipeline no longer reads
+    if fs::read_to_string(&output_path).is_ok_and(|existing| existing.contains("_DELL_SERVER")) {
+        println!(
+            "⚠️  The existing .gitlab-ci.yml reads the ANSIBLE_*_DELL_SERVER variables, which are now named <variable_prefix>_ANSIBLE_*. Rename them in Settings > CI/CD > Variables, see `fileforge ci-vars`."
+        );
+    }
+    write_file_with_backup(&output_path, &gitlab_ci)?;
+
+    println!("🎉 Generated .gitlab-ci.yml in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
+
+/// Renders `.gitlab-ci.yml` with the enabled deploy stages of every environment, or a single
+/// helm job per environment when the charts are installed with Helm.
+///
+/// Pipelines are limited with `workflow:rules` and every deploy job repeats the branch and tag
+/// rules of its environment, so merge request pipelines only run the validation job. The jobs of
+/// an environment are chained with `needs:`, so a manual approval only holds back its own
+/// environment.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The branches, stages and runner tags.
+///
+/// # Returns
+/// - `String`: The content of `.gitlab-ci.yml`.
+pub fn render_gitlab_ci(pipeline: &DeploymentPipeline, settings: &GitLabSettings) -> String {
+    let mut stages = Vec::new();
+    if settings.merge_requests {
+        stages.push("validate".to_string());
+    }
+    if pipeline.image_registry.is_some() {
+        stages.push("build".to_string());
+    }
+    if pipeline.helm.is_some() {
+        stages.push("helm".to_string());
+    } else {
+        stages.extend(settings.stages.iter().cloned());
+    }
+    let rollback = pipeline.rollback && settings.stages.iter().any(|stage| stage == "ansible");
+    if rollback {
+        stages.push("rollback".to_string());
+    }
+
+    let mut gitlab_ci = format!(
+        r#"
+variables:
+  PROJECT_PATH: "{project_path}"
+
+workflow:
+  rules:
+{workflow_rules}
+stages:
+{stages}"#,
+        project_path = pipeline.project_path,
+        workflow_rules = render_workflow_rules(pipeline, settings),
+        stages = stages
+            .iter()
+            .map(|stage| format!("  - {}\n", stage))
+            .collect::<String>(),
+    );
+
+    if settings.merge_requests {
+        gitlab_ci.push('\n');
+        gitlab_ci.push_str(&render_validate_job(pipeline, settings));
+    }
+
+    // The images are built once and deployed to every environment
+    if pipeline.image_registry.is_some() {
+        gitlab_ci.push('\n');
+        gitlab_ci.push_str(&render_build_job(pipeline, settings));
+    }
+
+    for environment in &pipeline.environments {
+        if pipeline.helm.is_some() {
+            gitlab_ci.push('\n');
+            gitlab_ci.push_str(&render_helm_job(settings, environment));
+            continue;
+        }
+
+        for (index, stage) in settings.stages.iter().enumerate() {
+            let job = match stage.as_str() {
+                "deploy" => render_deploy_job(pipeline),
+                "start" => render_start_job(pipeline, environment),
+                _ => render_ansible_job(pipeline, environment, "ansible", "ansible-deploy.yml"),
+            };
+            gitlab_ci.push('\n');
+            gitlab_ci.push_str(&format!("{}:\n", job_name(stage, environment)));
+            gitlab_ci.push_str(&job);
+
+            if environment.configured {
+                if index > 0 {
+                    gitlab_ci.push_str(&format!(
+                        "  needs:\n    - {}\n",
+                        job_name(&settings.stages[index - 1], environment)
+                    ));
+                }
+                if index == settings.stages.len() - 1 {
+                    gitlab_ci
+                        .push_str(&format!("  environment:\n    name: {}\n", environment.name));
+                }
+            }
+
+            // A manual environment waits for approval before its first job
+            let when =
+                if settings.manual_stages.contains(stage) || (environment.manual && index == 0) {
+                    WHEN_MANUAL
+                } else {
+                    ""
+                };
+            gitlab_ci.push_str(&render_job_rules(settings, environment, when));
+            gitlab_ci.push_str(&render_runner_tags(settings, stage));
+        }
+
+        // The rollback can run while the deploy jobs failed, so it does not wait for them
+        if rollback {
+            gitlab_ci.push('\n');
+            gitlab_ci.push_str(&format!("{}:\n", job_name("rollback", environment)));
+            gitlab_ci.push_str(&render_ansible_job(
+                pipeline,
+                environment,
+                "rollback",
+                "ansible-rollback.yml",
+            ));
+            gitlab_ci.push_str("  needs: []\n");
+            if environment.configured {
+                gitlab_ci.push_str(&format!("  environment:\n    name: {}\n", environment.name));
+            }
+            gitlab_ci.push_str(&render_job_rules(
+                settings,
+                environment,
+                WHEN_MANUAL_OPTIONAL,
+            ));
+            gitlab_ci.push_str(&render_runner_tags(settings, "ansible"));
+        }
+    }
+
+    gitlab_ci
+}
+
+/// Returns the job name of a stage, e.g. `deploy-job`, or `deploy-staging` for a configured
+/// environment.
+fn job_name(stage: &str, environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!("{}-{}", stage, environment.name)
+    } else {
+        format!("{}-job", stage)
+    }
+}
+
+/// Returns the branches deploying an environment.
+fn environment_branches<'a>(
+    settings: &'a GitLabSettings,
+    environment: &'a DeployEnvironment,
+) -> Vec<&'a String> {
+    match &environment.branch {
+        Some(branch) => vec![branch],
+        None => settings.branches.iter().collect(),
+    }
+}
+
+/// Returns the `if:` condition matching a deploy branch.
+fn branch_condition(branch: &str) -> String {
+    if branch.len() > 1 && branch.starts_with('/') && branch.ends_with('/') {
+        format!("$CI_COMMIT_BRANCH =~ {}", branch)
+    } else {
+        format!(r#"$CI_COMMIT_BRANCH == "{}""#, branch)
+    }
+}
+
+fn render_workflow_rules(pipeline: &DeploymentPipeline, settings: &GitLabSettings) -> String {
+    let mut rules = String::new();
+
+    if settings.merge_requests {
+        rules.push_str("    - if: $CI_PIPELINE_SOURCE == \"merge_request_event\"\n");
+    }
+
+    let mut branches: Vec<&String> = Vec::new();
+    for environment in &pipeline.environments {
+        for branch in environment_branches(settings, environment) {
+            if !branches.contains(&branch) {
+                branches.push(branch);
+            }
+        }
+    }
+    for branch in branches {
+        rules.push_str(&format!("    - if: {}\n", branch_condition(branch)));
+    }
+
+    // Tags deploy the environments without a branch of their own
+    let deploys_tags = pipeline
+        .environments
+        .iter()
+        .any(|environment| environment.branch.is_none());
+    if settings.deploy_tags && deploys_tags {
+        rules.push_str("    - if: $CI_COMMIT_TAG\n");
+    }
+
+    rules
+}
+
+/// Manual jobs block the later jobs of the environment until someone approves them.
+const WHEN_MANUAL: &str = "\n      when: manual\n      allow_failure: false";
+
+/// Optional manual jobs, like the rollback, leave the pipeline passing when nobody runs them.
+const WHEN_MANUAL_OPTIONAL: &str = "\n      when: manual\n      allow_failure: true";
+
+/// Renders the rules running a job on the branches and tags of its environment.
+///
+/// # Parameters
+/// - `settings`: The branches and tags that deploy.
+/// - `environment`: The environment the job deploys.
+/// - `when`: The `when` keywords appended to every rule, empty for automatic jobs.
+///
+/// # Returns
+/// - `String`: The `rules:` of the job.
+fn render_job_rules(
+    settings: &GitLabSettings,
+    environment: &DeployEnvironment,
+    when: &str,
+) -> String {
+    let mut conditions = environment_branches(settings, environment)
+        .iter()
+        .map(|branch| branch_condition(branch))
+        .collect::<Vec<String>>();
+    if settings.deploy_tags && environment.branch.is_none() {
+        conditions.push("$CI_COMMIT_TAG".to_string());
+    }
+
+    let rules = conditions
+        .iter()
+        .map(|condition| format!("    - if: {}{}\n", condition, when))
+        .collect::<String>();
+
+    format!("  rules:\n{}", rules)
+}
+
+fn render_runner_tags(settings: &GitLabSettings, stage: &str) -> String {
+    let runner_tags = settings.runner_tags_for(stage);
+    if runner_tags.is_empty() {
+        return String::new();
+    }
+
+    let tags = runner_tags
+        .iter()
+        .map(|tag| format!("    - {}\n", tag))
+        .collect::<String>();
+    format!("  tags:\n{}", tags)
+}
+
+fn render_validate_job(pipeline: &DeploymentPipeline, settings: &GitLabSettings) -> String {
+    let deploy_check = match &pipeline.helm {
+        Some(helm) => format!(
+            "    - echo \"Linting Helm charts...\"\n    - {}\n",
+            helm.lint_command()
+        ),
+        None => r#"    - echo "Validating Ansible playbook..."
+    - if command -v ansible-playbook &> /dev/null; then
+        ansible-playbook -i ansible/hosts.yml ansible/ansible-deploy.yml --syntax-check;
+      fi
+"#
+        .to_string(),
+    };
+
+    format!(
+        r#"validate-job:
+  stage: validate
+  script:
+    - echo "Validating compose file..."
+    - docker compose config --quiet
+{deploy_check}  rules:
+    - if: $CI_PIPELINE_SOURCE == "merge_request_event"
+{runner_tags}"#,
+        deploy_check = deploy_check,
+        runner_tags = render_runner_tags(settings, "validate"),
+    )
+}
+
+fn render_deploy_job(pipeline: &DeploymentPipeline) -> String {
+    format!(
+        r#"  stage: deploy
+  before_script:
+    - sudo mkdir -p $PROJECT_PATH
+  script:
+    - sudo find $PROJECT_PATH -mindepth 1 -maxdepth 1 ! \( {preserved_directories} \) -exec rm -rf {{}} + # DELETE ALL FILES FIRST EXCEPT THE PRESERVED FOLDERS
+    - sudo cp -r {sources} $PROJECT_PATH # COPY ALL FILES FROM CURRENT GITLAB DIRECTORY TO A SPECIFIC PROJECT PATH
+{image_tag}"#,
+        preserved_directories = pipeline.preserved_directories_expression(),
+        sources = pipeline.deploy_sources(),
+        image_tag = pipeline
+            .image_tag_command()
+            .map(|command| format!("    - {}\n", command))
+            .unwrap_or_default(),
+    )
+}
+
+/// Renders the job building the images and pushing them to the image registry. It runs on the
+/// branches and tags of every environment.
+fn render_build_job(pipeline: &DeploymentPipeline, settings: &GitLabSettings) -> String {
+    let mut conditions = Vec::new();
+    for environment in &pipeline.environments {
+        for branch in environment_branches(settings, environment) {
+            let condition = branch_condition(branch);
+            if !conditions.contains(&condition) {
+                conditions.push(condition);
+            }
+        }
+    }
+    if settings.deploy_tags
+        && pipeline
+            .environments
+            .iter()
+            .any(|environment| environment.branch.is_none())
+    {
+        conditions.push("$CI_COMMIT_TAG".to_string());
+    }
+
+    format!(
+        r#"build-job:
+  stage: build
+{registry_login}  script:
+    - sh {build_script}
+  rules:
+{rules}{runner_tags}"#,
+        registry_login =
+            render_registry_login(&pipeline.base_image_registry, &pipeline.base_images),
+        build_script = IMAGE_BUILD_SCRIPT,
+        rules = conditions
+            .iter()
+            .map(|condition| format!("    - if: {}\n", condition))
+            .collect::<String>(),
+        runner_tags = render_runner_tags(settings, "build"),
+    )
+}
+
+fn render_start_job(pipeline: &DeploymentPipeline, environment: &DeployEnvironment) -> String {
+    format!(
+        r#"  stage: start
+{compose_file}{registry_login}  script:
+    - cd $PROJECT_PATH
+    - |
+{compose_up}
+"#,
+        compose_file = render_compose_file_variable(environment),
+        registry_login =
+            render_registry_login(&pipeline.base_image_registry, pipeline.server_base_images(),),
+        compose_up = indent_lines(pipeline.compose_up_script(), 6),
+    )
+}
+
+/// Renders the `before_script` logging in to the registry and pulling the base images, which are
+/// only needed when .NET images are built.
+fn render_registry_login(registry: &BaseImageRegistry, base_images: &[String]) -> String {
+    if base_images.is_empty() {
+        return String::new();
+    }
+
+    let docker_pulls = base_images
+        .iter()
+        .map(|image| format!("    - {}\n", registry.pull_command(image)))
+        .collect::<String>();
+
+    let login = match registry.login_command() {
+        Some(login_command) => format!(
+            r#"    - echo "Logging into Microsoft Docker Registry ({name})..."
+    - if {login_command}; then
+      echo "Successfully Logged into Microsoft Docker Registry";
+      else
+      echo "Failed to Login to Microsoft Docker Registry";
+      exit 1;
+      fi
+
+"#,
+            name = registry.display_name(),
+            login_command = login_command
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        r#"  before_script:
+{login}    - set -e  # Exit immediately if a command exits with a non-zero status
+    - echo "Ensuring Required Docker Images Exist..."
+{docker_pulls}    - echo "Docker Images Pulled Successfully"
+"#,
+        login = login,
+        docker_pulls = docker_pulls
+    )
+}
+
+/// The inventory reads the `<prefix>_ANSIBLE_*` CI variables from the environment, so the job only
+/// runs the playbook. The inventory is validated without printing it, since it may hold secrets.
+fn render_ansible_job(
+    pipeline: &DeploymentPipeline,
+    environment: &DeployEnvironment,
+    stage: &str,
+    playbook: &str,
+) -> String {
+    let vault_check = pipeline
+        .vault_check_script()
+        .map(|script| format!("    - |\n{}\n", indent_lines(&script, 6)))
+        .unwrap_or_default();
+
+    format!(
+        r#"  stage: {stage}
+  before_script:
+    # Check if ansible-playbook is accessible, otherwise install it
+    - if ! command -v ansible-playbook &> /dev/null; then
+        echo "Installing Ansible...";
+        pipx install --include-deps ansible;
+        pipx ensurepath;
+      fi
+{requirements}{vault_check}    - echo "Validating Ansible inventory..."
+    - ansible-inventory -i ansible/hosts.yml --list{vault_options} > /dev/null
+  script:
+    - echo "Running Ansible playbook..."
+    - ansible-playbook -i ansible/hosts.yml ansible/{playbook}{vault_options}{limit}
+"#,
+        stage = stage,
+        requirements = pipeline
+            .ansible_requirements_command()
+            .map(|command| format!("    - {}\n", command))
+            .unwrap_or_default(),
+        vault_check = vault_check,
+        playbook = playbook,
+        vault_options = pipeline.ansible_vault_options(),
+        limit = render_limit(environment),
+    )
+}
+
+/// Renders the job installing the charts on the cluster of an environment once the images are
+/// pushed. A manual environment waits for approval before it runs.
+fn render_helm_job(settings: &GitLabSettings, environment: &DeployEnvironment) -> String {
+    let mut job = format!(
+        r#"{job_name}:
+  stage: helm
+  needs:
+    - build-job
+  variables:
+    {content_variable}: ${kubeconfig_variable}
+  script:
+    - {command}
+"#,
+        job_name = job_name("helm", environment),
+        content_variable = KUBECONFIG_CONTENT_VARIABLE,
+        kubeconfig_variable = environment.kubeconfig_variable().name,
+        command = helm_upgrade_command(environment),
+    );
+    if environment.configured {
+        job.push_str(&format!("  environment:\n    name: {}\n", environment.name));
+    }
+    job.push_str(&render_job_rules(
+        settings,
+        environment,
+        if environment.manual { WHEN_MANUAL } else { "" },
+    ));
+    job.push_str(&render_runner_tags(settings, "helm"));
+    job
+}
+
+/// Selects the compose override of a configured environment for `docker compose`.
+fn render_compose_file_variable(environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!(
+            "  variables:\n    COMPOSE_FILE: \"{}\"\n",
+            environment.compose_file()
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// Limits the playbook to the inventory group of a configured environment.
+fn render_limit(environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!(" --limit {}", environment.host_group)
+    } else {
+        String::new()
+    }
+}
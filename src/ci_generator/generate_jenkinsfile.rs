@@ -0,0 +1,592 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        deploy_environment::DeployEnvironment,
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::IMAGE_BUILD_SCRIPT,
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::{helm_upgrade_command, KUBECONFIG_CONTENT_VARIABLE},
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// The Jenkins credential IDs and agent used by the generated `Jenkinsfile`.
+pub struct JenkinsSettings {
+    pub agent_label: String,
+    /// Secret text credential holding the registry URL.
+    pub registry_url_credentials_id: String,
+    /// Username with password credential for the registry login.
+    pub registry_credentials_id: String,
+    /// Username with password credential for the login to the image registry.
+    pub image_registry_credentials_id: String,
+}
+
+impl JenkinsSettings {
+    /// Reads the settings from the optional `jenkins` section of the configuration.
+    pub fn from_config(config: &Value) -> JenkinsSettings {
+        let jenkins = &config["jenkins"];
+        let setting =
+            |key: &str, default: &str| jenkins[key].as_str().unwrap_or(default).to_string();
+
+        JenkinsSettings {
+            agent_label: setting("agent_label", "docker"),
+            registry_url_credentials_id: setting(
+                "registry_url_credentials_id",
+                "sonatype-nexus-url",
+            ),
+            registry_credentials_id: setting("registry_credentials_id", "sonatype-nexus"),
+            image_registry_credentials_id: setting(
+                "image_registry_credentials_id",
+                "image-registry",
+            ),
+        }
+    }
+}
+
+pub fn generate_jenkinsfile() -> io::Result<()> {
+    println!("🚀 Starting Jenkinsfile generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    let settings = JenkinsSettings::from_config(&config);
+    println!("🌟 Project path: {}", pipeline.project_path);
+    println!("🏷️  Agent label: {}", settings.agent_label);
+    thread::sleep(SLEEP_DURATION);
+
+    let jenkinsfile = render_jenkinsfile(&pipeline, &settings);
+
+    let output_path = get_output_directory(&current_dir).join("Jenkinsfile");
+    write_file_with_backup(&output_path, &jenkinsfile)?;
+
+    println!("🎉 Generated Jenkinsfile in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    if let Some(rollback_pipeline) = render_jenkins_rollback_pipeline(&pipeline, &settings) {
+        let rollback_path = get_output_directory(&current_dir).join("Jenkinsfile.rollback");
+        write_file_with_backup(&rollback_path, &rollback_pipeline)?;
+        println!("🎉 Generated Jenkinsfile.rollback in {:?}", rollback_path);
+    }
+
+    Ok(())
+}
+
+/// Renders a declarative `Jenkinsfile` with the deploy, start and ansible stages of every
+/// environment, or a single helm stage per environment when the charts are installed with Helm.
+///
+/// Shell scripts are embedded in `'''` strings, so `$` reaches the shell untouched and only
+/// backslashes need escaping for Groovy.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The Jenkins credential IDs and agent label.
+///
+/// # Returns
+/// - `String`: The content of the `Jenkinsfile`.
+pub fn render_jenkinsfile(pipeline: &DeploymentPipeline, settings: &JenkinsSettings) -> String {
+    let mut start_script =
+        render_base_image_pulls(&pipeline.base_image_registry, pipeline.server_base_images());
+    start_script.push_str("cd \"$PROJECT_PATH\"\n");
+    start_script.push_str(pipeline.compose_up_script());
+
+    let start_steps = wrap_with_credentials(
+        &start_script,
+        render_registry_credentials(
+            settings,
+            (!pipeline.server_base_images().is_empty()).then_some(&pipeline.base_image_registry),
+            pipeline.image_registry.is_some(),
+        )
+        .as_deref(),
+    );
+
+    let deploy_script = format!(
+        r#"sudo mkdir -p "$PROJECT_PATH"
+sudo find "$PROJECT_PATH" -mindepth 1 -maxdepth 1 ! \( {preserved_directories} \) -exec rm -rf {{}} +
+sudo cp -r {sources} "$PROJECT_PATH"
+{image_tag}"#,
+        preserved_directories = pipeline.preserved_directories_expression(),
+        sources = pipeline.deploy_sources(),
+        image_tag = pipeline
+            .image_tag_command()
+            .map(|command| format!("{}\n", command))
+            .unwrap_or_default(),
+    );
+
+    let stages_template = r#"        stage('Deploy[[ title ]]') {
+[[ when ]]            steps {
+                sh '''
+[[ deploy_script ]]
+                '''
+            }
+        }
+
+        stage('Start[[ title ]]') {
+            when { branch '[[ branch ]]' }
+[[ compose_file_environment ]]            steps {
+[[ start_steps ]]
+            }
+        }
+
+        stage('Ansible[[ title ]]') {
+            when { branch '[[ branch ]]' }
+            steps {
+[[ ansible_steps ]]
+            }
+        }"#;
+
+    let mut stages = Vec::new();
+    for environment in &pipeline.environments {
+        let branch = environment.branch.as_deref().unwrap_or("master");
+
+        // A manual environment asks for approval before its first stage, once the branch matched
+        let (title, when, compose_file_environment) = if environment.configured {
+            let when = if environment.manual {
+                format!(
+                    "            when {{\n                beforeInput true\n                branch '{branch}'\n            }}\n            input {{\n                message 'Deploy to {name}?'\n            }}\n",
+                    branch = branch,
+                    name = environment.name
+                )
+            } else {
+                format!("            when {{ branch '{}' }}\n", branch)
+            };
+            (
+                format!(" ({})", environment.name),
+                when,
+                format!(
+                    "            environment {{\n                COMPOSE_FILE = '{}'\n            }}\n",
+                    environment.compose_file()
+                ),
+            )
+        } else {
+            (
+                String::new(),
+                format!("            when {{ branch '{}' }}\n", branch),
+                String::new(),
+            )
+        };
+
+        if pipeline.helm.is_some() {
+            let credentials = format!(
+                "string(credentialsId: '{}', variable: '{}')",
+                jenkins_credentials_id(&environment.kubeconfig_variable().name),
+                KUBECONFIG_CONTENT_VARIABLE
+            );
+            stages.push(format!(
+                "        stage('Helm{title}') {{\n{when}            steps {{\n{steps}\n            }}\n        }}",
+                title = title,
+                when = when,
+                steps = wrap_with_credentials(
+                    &format!("{}\n", helm_upgrade_command(environment)),
+                    Some(&credentials)
+                ),
+            ));
+            continue;
+        }
+
+        stages.push(
+            stages_template
+                .replace("[[ title ]]", &title)
+                .replace("[[ when ]]", &when)
+                .replace("[[ compose_file_environment ]]", &compose_file_environment)
+                .replace(
+                    "[[ ansible_steps ]]",
+                    &render_ansible_steps(pipeline, settings, environment, "ansible-deploy.yml"),
+                )
+                .replace("[[ branch ]]", branch),
+        );
+    }
+
+    // The images are built once and deployed to every environment
+    if pipeline.image_registry.is_some() {
+        stages.insert(0, render_build_stage(pipeline, settings));
+    }
+
+    let jenkinsfile = r#"pipeline {
+    agent { label '[[ agent_label ]]' }
+
+    environment {
+        PROJECT_PATH = '[[ project_path ]]'
+    }
+
+    options {
+        disableConcurrentBuilds()
+    }
+
+    stages {
+[[ stages ]]
+    }
+}
+"#;
+
+    jenkinsfile
+        .replace("[[ stages ]]", &stages.join("\n\n"))
+        .replace(
+            "[[ deploy_script ]]",
+            &indent_lines(&escape_for_groovy(&deploy_script), 20),
+        )
+        .replace("[[ start_steps ]]", &start_steps)
+        .replace(
+            "[[ agent_label ]]",
+            &escape_groovy_string(&settings.agent_label),
+        )
+        .replace("[[ project_path ]]", &pipeline.project_path)
+}
+
+/// Renders the manually started pipeline running `ansible-rollback.yml` against the environment
+/// picked in its `ENVIRONMENT` parameter, or `None` when the playbook does not keep previous
+/// releases.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The Jenkins credential IDs and agent label.
+///
+/// # Returns
+/// - `Option<String>`: The content of `Jenkinsfile.rollback`.
+pub fn render_jenkins_rollback_pipeline(
+    pipeline: &DeploymentPipeline,
+    settings: &JenkinsSettings,
+) -> Option<String> {
+    if !pipeline.rollback {
+        return None;
+    }
+
+    let stages = pipeline
+        .environments
+        .iter()
+        .map(|environment| {
+            format!(
+                r#"        stage('Rollback ({name})') {{
+            when {{ expression {{ params.ENVIRONMENT == '{name}' }} }}
+            steps {{
+{steps}
+            }}
+        }}"#,
+                name = environment.name,
+                steps =
+                    render_ansible_steps(pipeline, settings, environment, "ansible-rollback.yml"),
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let rollback_pipeline = r#"pipeline {
+    agent { label '[[ agent_label ]]' }
+
+    parameters {
+        choice(name: 'ENVIRONMENT', choices: [[[ choices ]]], description: 'The environment to roll back to its previous release')
+    }
+
+    options {
+        disableConcurrentBuilds()
+    }
+
+    stages {
+[[ stages ]]
+    }
+}
+"#;
+
+    Some(
+        rollback_pipeline
+            .replace("[[ stages ]]", &stages.join("\n\n"))
+            .replace(
+                "[[ choices ]]",
+                &pipeline
+                    .environments
+                    .iter()
+                    .map(|environment| format!("'{}'", environment.name))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )
+            .replace(
+                "[[ agent_label ]]",
+                &escape_groovy_string(&settings.agent_label),
+            ),
+    )
+}
+
+/// Renders the stage building the images and pushing them to the image registry. It runs on the
+/// branches of every environment.
+fn render_build_stage(pipeline: &DeploymentPipeline, settings: &JenkinsSettings) -> String {
+    let mut branches: Vec<&str> = Vec::new();
+    for environment in &pipeline.environments {
+        let branch = environment.branch.as_deref().unwrap_or("master");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+    }
+
+    let mut build_script =
+        render_base_image_pulls(&pipeline.base_image_registry, &pipeline.base_images);
+    build_script.push_str(&format!("sh {}\n", IMAGE_BUILD_SCRIPT));
+
+    format!(
+        r#"        stage('Build') {{
+            when {{
+                anyOf {{
+{branches}                }}
+            }}
+            steps {{
+{steps}
+            }}
+        }}"#,
+        branches = branches
+            .iter()
+            .map(|branch| format!("                    branch '{}'\n", branch))
+            .collect::<String>(),
+        steps = wrap_with_credentials(
+            &build_script,
+            render_registry_credentials(
+                settings,
+                (!pipeline.base_images.is_empty()).then_some(&pipeline.base_image_registry),
+                pipeline.image_registry.is_some(),
+            )
+            .as_deref(),
+        ),
+    )
+}
+
+/// Returns the commands logging in to the registry and pulling the base images of the .NET
+/// builds.
+fn render_base_image_pulls(registry: &BaseImageRegistry, base_images: &[String]) -> String {
+    if base_images.is_empty() {
+        return String::new();
+    }
+
+    registry
+        .login_command()
+        .into_iter()
+        .chain(base_images.iter().map(|image| registry.pull_command(image)))
+        .map(|command| format!("{}\n", command))
+        .collect()
+}
+
+/// Returns the credential bindings of the base image registry and of the image registry, or
+/// `None` when neither login is needed.
+///
+/// # Parameters
+/// - `settings`: The Jenkins credential IDs.
+/// - `registry`: The registry of the base images, if the stage pulls them.
+/// - `image_registry`: Whether the stage pushes or pulls the images of the image registry.
+///
+/// # Returns
+/// - `Option<String>`: The bindings, one per line.
+fn render_registry_credentials(
+    settings: &JenkinsSettings,
+    registry: Option<&BaseImageRegistry>,
+    image_registry: bool,
+) -> Option<String> {
+    let mut credentials = Vec::new();
+    if let Some(registry) = registry {
+        if let Some(url_variable) = registry.url_variable() {
+            credentials.push(format!(
+                "string(credentialsId: '{}', variable: '{}')",
+                escape_groovy_string(&settings.registry_url_credentials_id),
+                url_variable
+            ));
+        }
+        if registry.login {
+            let (username, password) = registry.credential_variables();
+            credentials.push(format!(
+                "usernamePassword(credentialsId: '{}', usernameVariable: '{}', passwordVariable: '{}')",
+                escape_groovy_string(&settings.registry_credentials_id),
+                username,
+                password
+            ));
+        }
+    }
+    if image_registry {
+        credentials.push(format!(
+            "usernamePassword(credentialsId: '{}', usernameVariable: 'IMAGE_REGISTRY_USERNAME', passwordVariable: 'IMAGE_REGISTRY_PASSWORD')",
+            escape_groovy_string(&settings.image_registry_credentials_id)
+        ));
+    }
+
+    if credentials.is_empty() {
+        None
+    } else {
+        Some(credentials.join(",\n                    "))
+    }
+}
+
+/// Renders the steps running a playbook against the servers of an environment, with the
+/// credentials the inventory reads.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+/// - `settings`: The Jenkins credential IDs.
+/// - `environment`: The environment whose servers the playbook runs against.
+/// - `playbook`: The playbook in the `ansible` directory, e.g. `ansible-deploy.yml`.
+///
+/// # Returns
+/// - `String`: The steps of the stage.
+fn render_ansible_steps(
+    pipeline: &DeploymentPipeline,
+    settings: &JenkinsSettings,
+    environment: &DeployEnvironment,
+    playbook: &str,
+) -> String {
+    let limit = if environment.configured {
+        format!(" --limit {}", environment.host_group)
+    } else {
+        String::new()
+    };
+
+    // `sh` runs the script with /bin/sh, so the script sticks to POSIX redirections
+    let ansible_script = r#"if ! command -v ansible-playbook > /dev/null 2>&1; then
+  echo "Installing Ansible..."
+  pipx install --include-deps ansible
+  pipx ensurepath
+fi
+[[ requirements ]][[ vault_check ]]ansible-inventory -i ansible/hosts.yml --list[[ vault_options ]] > /dev/null
+ansible-playbook -i ansible/hosts.yml ansible/[[ playbook ]][[ vault_options ]][[ limit ]]
+"#;
+    let ansible_script = ansible_script
+        .replace(
+            "[[ requirements ]]",
+            &pipeline
+                .ansible_requirements_command()
+                .map(|command| format!("{}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace(
+            "[[ vault_check ]]",
+            &pipeline.vault_check_script().unwrap_or_default(),
+        )
+        .replace("[[ vault_options ]]", &pipeline.ansible_vault_options())
+        .replace("[[ limit ]]", &limit)
+        .replace("[[ playbook ]]", playbook);
+
+    // The host variables read the connection settings of the servers from these variables
+    let mut ansible_credentials = pipeline
+        .inventory_variables(environment)
+        .iter()
+        .map(|variable| {
+            format!(
+                "string(credentialsId: '{credentials_id}', variable: '{variable}')",
+                credentials_id = jenkins_credentials_id(&variable.name),
+                variable = variable.name,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",\n                    ");
+    if let Some(registry_credentials) = render_registry_credentials(
+        settings,
+        (!pipeline.server_base_images().is_empty()).then_some(&pipeline.base_image_registry),
+        pipeline.image_registry.is_some(),
+    ) {
+        ansible_credentials = format!(
+            "{},\n                    {}",
+            ansible_credentials, registry_credentials
+        );
+    }
+    wrap_with_credentials(&ansible_script, Some(&ansible_credentials))
+}
+
+/// Returns the ID of the secret text credential holding a CI variable, e.g. `production-ansible-host`.
+pub fn jenkins_credentials_id(variable: &str) -> String {
+    variable.to_lowercase().replace('_', "-")
+}
+
+/// Wraps a shell script in an `sh` step, inside `withCredentials` when credentials are given.
+fn wrap_with_credentials(script: &str, credentials: Option<&str>) -> String {
+    match credentials {
+        Some(credentials) => format!(
+            r#"                withCredentials([
+                    {credentials}
+                ]) {{
+                    sh '''
+{script}
+                    '''
+                }}"#,
+            credentials = credentials,
+            script = indent_lines(&escape_for_groovy(script), 24),
+        ),
+        None => format!(
+            r#"                sh '''
+{script}
+                '''"#,
+            script = indent_lines(&escape_for_groovy(script), 20),
+        ),
+    }
+}
+
+/// Escapes backslashes, which Groovy interprets even in `'''` strings.
+fn escape_for_groovy(script: &str) -> String {
+    script.replace('\\', "\\\\")
+}
+
+/// Escapes a configured value for a single-quoted Groovy string, like the credential IDs. `$` is
+/// not interpolated in those strings, so only quotes and backslashes need escaping.
+fn escape_groovy_string(value: &str) -> String {
+    escape_for_groovy(value).replace('\'', "\\'")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn config() -> serde_json::Value {
+        json!({
+            "project_type": "dotnet",
+            "project_location": "/etc/www",
+            "project_directory": "shop",
+            "dotnet_version": "9.0",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "image_name": "shop-api",
+            "container_name": "shop-api",
+            "port": 8080,
+            "image_registry": { "url": "registry.example.com/shop" },
+            "environments": [
+                { "name": "staging", "branch": "develop" },
+                { "name": "production", "branch": "main", "manual": true }
+            ],
+            "jenkins": { "image_registry_credentials_id": "it's-$REGISTRY\\login" }
+        })
+    }
+
+    fn render(config: &serde_json::Value) -> String {
+        let pipeline = DeploymentPipeline::from_config(config).unwrap();
+        render_jenkinsfile(&pipeline, &JenkinsSettings::from_config(config))
+    }
+
+    #[test]
+    fn manual_environments_ask_for_an_approval() {
+        let jenkinsfile = render(&config());
+
+        assert!(jenkinsfile.contains(
+            "        stage('Deploy (production)') {\n            when {\n                beforeInput true\n                branch 'main'\n            }\n            input {\n                message 'Deploy to production?'\n            }\n"
+        ));
+        assert!(jenkinsfile.contains(
+            "        stage('Deploy (staging)') {\n            when { branch 'develop' }\n"
+        ));
+        assert!(jenkinsfile.contains(
+            "string(credentialsId: 'production-ansible-host', variable: 'PRODUCTION_ANSIBLE_HOST')"
+        ));
+    }
+
+    #[test]
+    fn credential_ids_are_escaped() {
+        let jenkinsfile = render(&config());
+
+        // `$` stays literal in single-quoted strings, quotes and backslashes are escaped
+        assert!(jenkinsfile.contains(
+            "usernamePassword(credentialsId: 'it\\'s-$REGISTRY\\\\login', usernameVariable: 'IMAGE_REGISTRY_USERNAME'"
+        ));
+        assert!(!jenkinsfile.contains("'it's"));
+    }
+}
@@ -0,0 +1,445 @@
+use std::{env, io, thread};
+
+use crate::{
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        deploy_target::CiVariable,
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::IMAGE_BUILD_SCRIPT,
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::{helm_upgrade_command, KUBECONFIG_CONTENT_VARIABLE},
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+pub fn generate_woodpecker_file() -> io::Result<()> {
+    println!("🚀 Starting Woodpecker pipeline generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    println!("🌟 Project path: {}", pipeline.project_path);
+    thread::sleep(SLEEP_DURATION);
+
+    let woodpecker_pipeline = render_woodpecker_pipeline(&pipeline);
+
+    let output_path = get_output_directory(&current_dir).join(".woodpecker.yml");
+    write_file_with_backup(&output_path, &woodpecker_pipeline)?;
+
+    println!("🎉 Generated .woodpecker.yml in {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
+
+/// The step running `[[ playbook ]]` against the servers of an environment, used by the ansible
+/// and rollback steps.
+const ANSIBLE_STEP: &str = r#"  - name: [[ step ]][[ suffix ]]
+    image: bash
+[[ step_when ]]    environment:
+[[ registry_environment ]][[ inventory_environment ]]    commands:
+      - |
+        if ! command -v ansible-playbook &> /dev/null; then
+          echo "Installing Ansible..."
+          pipx install --include-deps ansible
+          pipx ensurepath
+        fi
+[[ requirements ]][[ vault_check ]]      - ansible-inventory -i ansible/hosts.yml --list[[ vault_options ]] > /dev/null
+      - ansible-playbook -i ansible/hosts.yml ansible/[[ playbook ]][[ vault_options ]][[ limit ]]
+"#;
+
+/// Renders the Woodpecker pipeline with the deploy, start and ansible steps of every environment,
+/// or a single helm step per environment when the charts are installed with Helm.
+///
+/// The steps run on the `local` backend of an agent on the target server. Woodpecker substitutes
+/// `$VARIABLE` references while parsing the file, so every `$` in the commands is escaped as `$$`
+/// to leave them to the shell.
+///
+/// When the playbook keeps previous releases, a deployment event targeting `rollback-<name>`
+/// runs `ansible-rollback.yml` against the servers of that environment instead.
+///
+/// # Parameters
+/// - `pipeline`: The deployment pipeline description.
+///
+/// # Returns
+/// - `String`: The content of `.woodpecker.yml`.
+pub fn render_woodpecker_pipeline(pipeline: &DeploymentPipeline) -> String {
+    let woodpecker_pipeline = r#"when:
+[[ pipeline_when ]]
+labels:
+  backend: local
+
+steps:
+[[ steps ]]"#;
+
+    let steps_template = r#"  - name: deploy[[ suffix ]]
+    image: bash
+[[ step_when ]]    environment:
+      PROJECT_PATH: "[[ project_path ]]"
+    commands:
+      - sudo mkdir -p "$$PROJECT_PATH"
+      - sudo find "$$PROJECT_PATH" -mindepth 1 -maxdepth 1 ! \( [[ preserved_directories ]] \) -exec rm -rf {} +
+      - sudo cp -r [[ sources ]] "$$PROJECT_PATH"
+[[ image_tag ]]
+  - name: start[[ suffix ]]
+    image: bash
+[[ step_when ]]    environment:
+      PROJECT_PATH: "[[ project_path ]]"
+[[ compose_file_environment ]][[ registry_environment ]]    commands:
+[[ registry_commands ]]      - cd "$$PROJECT_PATH"
+      - |
+[[ compose_up ]]
+
+[[ ansible_step ]]"#;
+
+    let mut branches: Vec<&str> = Vec::new();
+    let mut steps = Vec::new();
+    for environment in &pipeline.environments {
+        let branch = environment.branch.as_deref().unwrap_or("master");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+
+        // Woodpecker has no approval per step, so manual environments only deploy from pipelines
+        // started by hand
+        let (step_when, compose_file_environment, limit) = if environment.configured {
+            (
+                format!(
+                    "    when:\n      - event: {}\n        branch: {}\n",
+                    if environment.manual { "manual" } else { "push" },
+                    branch
+                ),
+                format!("      COMPOSE_FILE: \"{}\"\n", environment.compose_file()),
+                format!(" --limit {}", environment.host_group),
+            )
+        } else if pipeline.rollback {
+            // Keeps the deploy steps out of the rollback deployments
+            (
+                "    when:\n      - event: [push, manual]\n".to_string(),
+                String::new(),
+                String::new(),
+            )
+        } else {
+            (String::new(), String::new(), String::new())
+        };
+
+        // The host variables read the connection settings of the servers from these variables
+        let inventory_environment = pipeline
+            .inventory_variables(environment)
+            .iter()
+            .map(|variable| {
+                format!(
+                    "      {name}:\n        from_secret: {secret}\n",
+                    name = variable.name,
+                    secret = variable.name.to_lowercase()
+                )
+            })
+            .collect::<String>();
+
+        if pipeline.helm.is_some() {
+            steps.push(format!(
+                r#"  - name: helm{suffix}
+    image: bash
+{step_when}    environment:
+      {content_variable}:
+        from_secret: {secret}
+    commands:
+      - {command}
+"#,
+                suffix = environment.job_suffix(),
+                step_when = step_when,
+                content_variable = KUBECONFIG_CONTENT_VARIABLE,
+                secret = environment.kubeconfig_variable().name.to_lowercase(),
+                command = helm_upgrade_command(environment),
+            ));
+            continue;
+        }
+
+        let ansible_step = ANSIBLE_STEP
+            .replace("[[ inventory_environment ]]", &inventory_environment)
+            .replace("[[ limit ]]", &limit);
+
+        steps.push(
+            steps_template
+                .replace(
+                    "[[ ansible_step ]]",
+                    &ansible_step
+                        .replace("[[ step ]]", "ansible")
+                        .replace("[[ playbook ]]", "ansible-deploy.yml"),
+                )
+                .replace("[[ step_when ]]", &step_when)
+                .replace("[[ compose_file_environment ]]", &compose_file_environment)
+                .replace("[[ suffix ]]", &environment.job_suffix()),
+        );
+
+        if pipeline.rollback {
+            steps.push(
+                ansible_step
+                    .replace("[[ step ]]", "rollback")
+                    .replace("[[ playbook ]]", "ansible-rollback.yml")
+                    .replace(
+                        "[[ step_when ]]",
+                        &format!(
+                            "    when:\n      - event: deployment\n        environment: rollback-{}\n",
+                            environment.name
+                        ),
+                    )
+                    .replace("[[ suffix ]]", &environment.job_suffix()),
+            );
+        }
+    }
+
+    // The images are built once, before the steps of the environments
+    if pipeline.image_registry.is_some() {
+        steps.insert(0, render_build_step(pipeline));
+    }
+
+    let mut pipeline_when = format!("  - event: push\n    branch: [{}]\n", branches.join(", "));
+    if pipeline
+        .environments
+        .iter()
+        .any(|environment| environment.configured && environment.manual)
+    {
+        pipeline_when.push_str("  - event: manual\n");
+    }
+    if pipeline.rollback {
+        pipeline_when.push_str("  - event: deployment\n");
+    }
+
+    woodpecker_pipeline
+        .replace("[[ pipeline_when ]]", &pipeline_when)
+        .replace("[[ steps ]]", &steps.join("\n"))
+        .replace(
+            "[[ vault_check ]]",
+            &pipeline
+                .vault_check_script()
+                .map(|script| {
+                    format!(
+                        "      - |\n{}\n",
+                        indent_lines(&escape_variables(&script), 8)
+                    )
+                })
+                .unwrap_or_default(),
+        )
+        .replace("[[ vault_options ]]", &pipeline.ansible_vault_options())
+        .replace(
+            "[[ requirements ]]",
+            &pipeline
+                .ansible_requirements_command()
+                .map(|command| format!("      - {}\n", command))
+                .unwrap_or_default(),
+        )
+        .replace("[[ sources ]]", pipeline.deploy_sources())
+        .replace(
+            "[[ image_tag ]]",
+            &pipeline
+                .image_tag_command()
+                .map(|command| format!("      - {}\n", escape_variables(command)))
+                .unwrap_or_default(),
+        )
+        .replace(
+            "[[ registry_environment ]]",
+            &render_registry_environment(&pipeline.server_registry_variables()),
+        )
+        .replace(
+            "[[ registry_commands ]]",
+            &render_base_image_commands(
+                &pipeline.base_image_registry,
+                pipeline.server_base_images(),
+            ),
+        )
+        .replace(
+            "[[ compose_up ]]",
+            &indent_lines(&escape_variables(pipeline.compose_up_script()), 8),
+        )
+        .replace(
+            "[[ preserved_directories ]]",
+            &pipeline.preserved_directories_expression(),
+        )
+        .replace("[[ project_path ]]", &pipeline.project_path)
+}
+
+/// Renders the step building the images and pushing them to the image registry. Rollback
+/// deployments reuse the images of the previous release and skip it.
+fn render_build_step(pipeline: &DeploymentPipeline) -> String {
+    format!(
+        r#"  - name: build
+    image: bash
+    when:
+      - event: [push, manual]
+    environment:
+{registry_environment}    commands:
+{base_image_commands}      - sh {build_script}
+"#,
+        registry_environment = render_registry_environment(&pipeline.registry_variables()),
+        base_image_commands =
+            render_base_image_commands(&pipeline.base_image_registry, &pipeline.base_images),
+        build_script = IMAGE_BUILD_SCRIPT,
+    )
+}
+
+/// Renders the `environment:` entries reading the given registry credentials from the secrets.
+fn render_registry_environment(variables: &[CiVariable]) -> String {
+    variables
+        .iter()
+        .map(|variable| {
+            format!(
+                "      {name}:\n        from_secret: {secret}\n",
+                name = variable.name,
+                secret = variable.name.to_lowercase()
+            )
+        })
+        .collect()
+}
+
+/// Renders the commands logging in to the registry and pulling the base images of the .NET
+/// builds.
+fn render_base_image_commands(registry: &BaseImageRegistry, base_images: &[String]) -> String {
+    if base_images.is_empty() {
+        return String::new();
+    }
+
+    registry
+        .login_command()
+        .into_iter()
+        .chain(base_images.iter().map(|image| registry.pull_command(image)))
+        .map(|command| format!("      - {}\n", escape_variables(&command)))
+        .collect()
+}
+
+/// Escapes `$` as `$$` so Woodpecker passes variable references through to the shell.
+fn escape_variables(command: &str) -> String {
+    command.replace('$', "$$")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_yaml::Value as Yaml;
+
+    use super::*;
+
+    fn config() -> serde_json::Value {
+        json!({
+            "project_type": "dotnet",
+            "project_location": "/etc/www",
+            "project_directory": "shop",
+            "dotnet_version": "9.0",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "image_name": "shop-api",
+            "container_name": "shop-api",
+            "port": 8080,
+            "environments": [
+                { "name": "staging", "branch": "develop" },
+                { "name": "production", "branch": "main", "manual": true }
+            ]
+        })
+    }
+
+    fn render(config: &serde_json::Value) -> Yaml {
+        let pipeline = DeploymentPipeline::from_config(config).unwrap();
+        serde_yaml::from_str(&render_woodpecker_pipeline(&pipeline)).unwrap()
+    }
+
+    fn step<'a>(pipeline: &'a Yaml, name: &str) -> &'a Yaml {
+        pipeline["steps"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|step| step["name"] == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn pipeline_runs_on_pushes_and_manual_events() {
+        let pipeline = render(&config());
+
+        assert_eq!(pipeline["when"][0]["event"], "push");
+        assert_eq!(
+            pipeline["when"][0]["branch"],
+            serde_yaml::from_str::<Yaml>("[develop, main]").unwrap()
+        );
+        assert_eq!(pipeline["when"][1]["event"], "manual");
+        assert_eq!(pipeline["labels"]["backend"], "local");
+
+        assert_eq!(
+            step(&pipeline, "deploy-staging")["when"][0]["event"],
+            "push"
+        );
+        assert_eq!(
+            step(&pipeline, "deploy-staging")["when"][0]["branch"],
+            "develop"
+        );
+        // Manual environments only deploy when the pipeline is started by hand
+        assert_eq!(
+            step(&pipeline, "deploy-production")["when"][0]["event"],
+            "manual"
+        );
+    }
+
+    #[test]
+    fn pipeline_has_the_steps_of_every_environment() {
+        let pipeline = render(&config());
+
+        let names = pipeline["steps"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|step| step["name"].as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            [
+                "deploy-staging",
+                "start-staging",
+                "ansible-staging",
+                "deploy-production",
+                "start-production",
+                "ansible-production"
+            ]
+        );
+        assert_eq!(
+            step(&pipeline, "start-staging")["environment"]["COMPOSE_FILE"],
+            "compose.yaml:compose.staging.yaml"
+        );
+    }
+
+    #[test]
+    fn pipeline_reads_the_credentials_from_secrets() {
+        let pipeline = render(&config());
+
+        let environment = &step(&pipeline, "ansible-production")["environment"];
+        for variable in ["HOST", "PORT", "USER", "BECOME_PASS"] {
+            assert_eq!(
+                environment[format!("PRODUCTION_ANSIBLE_{}", variable).as_str()]["from_secret"],
+                format!("production_ansible_{}", variable.to_lowercase()).as_str()
+            );
+        }
+        assert_eq!(
+            step(&pipeline, "start-staging")["environment"]["SONATYPE_NEXUS_URL"]["from_secret"],
+            "sonatype_nexus_url"
+        );
+    }
+
+    #[test]
+    fn pipeline_escapes_variables_from_woodpecker() {
+        let pipeline = render(&config());
+
+        for command in step(&pipeline, "deploy-staging")["commands"]
+            .as_sequence()
+            .unwrap()
+        {
+            let command = command.as_str().unwrap();
+            assert!(command.contains("\"$$PROJECT_PATH\""), "{}", command);
+        }
+    }
+}
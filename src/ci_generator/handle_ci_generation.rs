@@ -0,0 +1,75 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        generate_azure_pipelines_file::generate_azure_pipelines_file,
+        generate_gitea_actions_file::generate_gitea_actions_file,
+        generate_github_actions_file::generate_github_actions_file,
+        generate_gitlab_ci_file::generate_gitlab_ci_file,
+        generate_jenkinsfile::generate_jenkinsfile,
+        generate_woodpecker_file::generate_woodpecker_file,
+    },
+    constants::SLEEP_DURATION,
+    shared::get_current_config::get_current_config,
+};
+
+/// The CI provider the pipeline is generated for, from `ci_provider`.
+#[derive(Debug, PartialEq)]
+pub enum CiProvider {
+    GitLab,
+    GitHub,
+    /// Gitea and Forgejo, which share the `.gitea/workflows` directory.
+    Gitea,
+    Woodpecker,
+    Jenkins,
+    Azure,
+}
+
+impl CiProvider {
+    /// Reads the provider from the configuration, GitLab by default.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<CiProvider>`: The provider, or an error if it is unknown.
+    pub fn from_config(config: &Value) -> io::Result<CiProvider> {
+        let ci_provider = config["ci_provider"].as_str().unwrap_or("gitlab");
+        match ci_provider {
+            "gitlab" => Ok(CiProvider::GitLab),
+            "github" => Ok(CiProvider::GitHub),
+            "gitea" | "forgejo" => Ok(CiProvider::Gitea),
+            "woodpecker" => Ok(CiProvider::Woodpecker),
+            "jenkins" => Ok(CiProvider::Jenkins),
+            "azure" => Ok(CiProvider::Azure),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown ci_provider: {}", ci_provider),
+            )),
+        }
+    }
+}
+
+/// Generates the CI configuration for the `ci_provider` set in the configuration.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the provider is unknown or generation fails.
+pub fn handle_ci_generation() -> io::Result<()> {
+    let config = get_current_config(env::current_dir()?);
+    println!(
+        "🔧 CI provider: {}",
+        config["ci_provider"].as_str().unwrap_or("gitlab")
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    match CiProvider::from_config(&config)? {
+        CiProvider::GitLab => generate_gitlab_ci_file(),
+        CiProvider::GitHub => generate_github_actions_file(),
+        CiProvider::Gitea => generate_gitea_actions_file(),
+        CiProvider::Woodpecker => generate_woodpecker_file(),
+        CiProvider::Jenkins => generate_jenkinsfile(),
+        CiProvider::Azure => generate_azure_pipelines_file(),
+    }
+}
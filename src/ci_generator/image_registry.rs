@@ -0,0 +1,199 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{deploy_environment::get_compose_services, deploy_target::CiVariable},
+    constants::SLEEP_DURATION,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, make_executable, write_file_with_backup},
+    },
+};
+
+/// The script the build job runs to build and push the images.
+pub const IMAGE_BUILD_SCRIPT: &str = "deploy/build-images.sh";
+
+/// The script pulling the images of a release before it is started.
+pub const IMAGE_PULL_SCRIPT: &str = "deploy/pull-images.sh";
+
+/// The compose override adding the `build` sections back for the build job.
+pub const IMAGE_BUILD_COMPOSE_FILE: &str = "compose.build.yaml";
+
+/// The environment of the Ansible compose task, for the registry login of `pull-images.sh`.
+pub const IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT: &str = r#"        IMAGE_REGISTRY_USERNAME: "{{ image_registry_username }}"
+        IMAGE_REGISTRY_PASSWORD: "{{ image_registry_password }}"
+"#;
+
+/// The registry the CI pipeline pushes the images to, read from the optional `image_registry`
+/// section of the configuration.
+///
+/// With a registry, the images are built once by the CI pipeline and `compose.yaml` references
+/// the pushed tag, so the servers only receive the compose files and pull the images.
+pub struct ImageRegistrySettings {
+    /// The registry and namespace the images are pushed to, e.g. `registry.example.com/shop`.
+    pub url: String,
+}
+
+impl ImageRegistrySettings {
+    /// Reads the registry settings, if the `image_registry` section is present.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<ImageRegistrySettings>>`: The settings, `None` to build on the
+    ///   servers, or an error if the URL is missing or invalid.
+    pub fn from_config(config: &Value) -> io::Result<Option<ImageRegistrySettings>> {
+        let image_registry = &config["image_registry"];
+        if image_registry.is_null() {
+            return Ok(None);
+        }
+
+        let url = image_registry["url"]
+            .as_str()
+            .map(|url| url.trim_end_matches('/'))
+            .unwrap_or_default();
+        if url.is_empty()
+            || url.contains("://")
+            || url.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid image_registry.url: {:?} (use the registry host and namespace without a scheme, e.g. registry.example.com/shop)",
+                    url
+                ),
+            ));
+        }
+
+        Ok(Some(ImageRegistrySettings {
+            url: url.to_string(),
+        }))
+    }
+
+    /// Returns the registry host `docker login` authenticates against, e.g. `registry.example.com`.
+    pub fn host(&self) -> &str {
+        self.url.split('/').next().unwrap_or(&self.url)
+    }
+
+    /// Returns the image reference of a service, tagged with the `IMAGE_TAG` of the release.
+    pub fn image(&self, image_name: &str) -> String {
+        format!("{}/{}:${{IMAGE_TAG:-latest}}", self.url, image_name)
+    }
+}
+
+/// Returns the CI variables used to push and pull the images.
+pub fn image_registry_variables() -> Vec<CiVariable> {
+    [
+        ("IMAGE_REGISTRY_USERNAME", "User name of the image registry"),
+        ("IMAGE_REGISTRY_PASSWORD", "Password of the image registry"),
+    ]
+    .iter()
+    .map(|(name, description)| CiVariable {
+        name: name.to_string(),
+        description: description.to_string(),
+    })
+    .collect()
+}
+
+/// Logs in to the registry at `[[ host ]]` when the credentials are set, for the deploy scripts.
+pub const REGISTRY_LOGIN: &str = r#"if [ -n "${IMAGE_REGISTRY_PASSWORD:-}" ]; then
+  echo "$IMAGE_REGISTRY_PASSWORD" | docker login "[[ host ]]" -u "${IMAGE_REGISTRY_USERNAME:-}" --password-stdin
+fi
+"#;
+
+const BUILD_SCRIPT: &str = r#"#!/bin/sh
+# Builds the images of the compose services and pushes them to [[ url ]], generated by fileforge.
+#
+# Every image is tagged with the commit SHA, which the deploy writes to the `.env` file next to
+# compose.yaml. A commit tagged with a semantic version, like v1.4.2, is pushed as 1.4.2 as well.
+# The registry login uses IMAGE_REGISTRY_USERNAME and IMAGE_REGISTRY_PASSWORD when they are set.
+set -eu
+
+IMAGE_TAG="${IMAGE_TAG:-$(git rev-parse HEAD)}"
+export IMAGE_TAG
+export COMPOSE_FILE="compose.yaml:[[ build_compose_file ]]"
+
+[[ login ]]
+echo "Building the images of $IMAGE_TAG..."
+docker compose build
+docker compose push
+
+version="$(git describe --tags --exact-match 2> /dev/null || true)"
+version="${version#v}"
+case "$version" in
+  [0-9]*.[0-9]*.[0-9]*)
+    for image in $(docker compose config --images); do
+      echo "Tagging $image as $version..."
+      docker tag "$image" "${image%:*}:$version"
+      docker push "${image%:*}:$version"
+    done
+    ;;
+esac
+"#;
+
+const PULL_SCRIPT: &str = r#"#!/bin/sh
+# Pulls the images of the release from [[ url ]], generated by fileforge.
+#
+# Run it from the project directory, whose `.env` file holds the IMAGE_TAG of the release. The
+# registry login uses IMAGE_REGISTRY_USERNAME and IMAGE_REGISTRY_PASSWORD when they are set.
+set -eu
+
+[[ login ]]
+docker compose pull
+"#;
+
+/// Generates the files of the registry build when the `image_registry` section is configured:
+/// `compose.build.yaml` with the `build` sections left out of `compose.yaml`, and the
+/// `deploy/build-images.sh` and `deploy/pull-images.sh` scripts.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn generate_image_registry_files() -> io::Result<()> {
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+
+    let config = get_current_config(current_dir.clone());
+    let settings = match ImageRegistrySettings::from_config(&config)? {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+    let services = get_compose_services(&config)?;
+    let output_dir = get_output_directory(&current_dir);
+
+    println!("📦 Writing the image build files for {}...", settings.url);
+    thread::sleep(SLEEP_DURATION);
+
+    let mut build_override = String::from("services:\n");
+    for service in &services {
+        build_override.push_str(&format!(
+            "  {name}:\n    build:\n      context: {context}\n      dockerfile: {dockerfile}\n{args}",
+            name = service.name,
+            context = service.build_context,
+            dockerfile = service.dockerfile,
+            args = service.build_args
+        ));
+    }
+    write_file_with_backup(&output_dir.join(IMAGE_BUILD_COMPOSE_FILE), &build_override)?;
+
+    let login = REGISTRY_LOGIN.replace("[[ host ]]", settings.host());
+    for (script, content) in [
+        (
+            IMAGE_BUILD_SCRIPT,
+            BUILD_SCRIPT.replace("[[ build_compose_file ]]", IMAGE_BUILD_COMPOSE_FILE),
+        ),
+        (IMAGE_PULL_SCRIPT, PULL_SCRIPT.to_string()),
+    ] {
+        let script_path = output_dir.join(script);
+        write_file_with_backup(
+            &script_path,
+            &content
+                .replace("[[ login ]]", &login)
+                .replace("[[ url ]]", &settings.url),
+        )?;
+        make_executable(&script_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+pub mod ansible_inventory;
+pub mod ansible_releases;
+pub mod ansible_role;
+pub mod base_image_registry;
+pub mod blue_green;
+pub mod deploy_environment;
+pub mod deploy_target;
+pub mod deployment_pipeline;
+pub mod generate_azure_pipelines_file;
+pub mod generate_compose_overrides;
+pub mod generate_gitea_actions_file;
+pub mod generate_github_actions_file;
+pub mod generate_gitlab_ci_file;
+pub mod generate_jenkinsfile;
+pub mod generate_woodpecker_file;
+pub mod handle_ci_generation;
+pub mod image_registry;
+pub mod reverse_proxy;
+pub mod swarm_stack;
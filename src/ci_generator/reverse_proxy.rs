@@ -0,0 +1,425 @@
+use std::{io, path::Path, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::blue_green::compose_project_name, constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::get_dotnet_container_port,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::output_helper::write_file_with_backup,
+};
+
+/// The directory the Caddyfile snippet is written to.
+pub const CADDY_DIRECTORY: &str = "caddy";
+
+/// The reverse proxy routing the hostnames to the services.
+pub enum ReverseProxyProvider {
+    /// Traefik reads the routes from the labels of the containers.
+    Traefik,
+    /// Caddy imports the routes from a Caddyfile snippet.
+    Caddy,
+}
+
+/// The route of a service: the hostname and path it is reachable on.
+struct ProxyRoute {
+    service: String,
+    /// The port the service listens on inside its container.
+    port: u16,
+    domain: String,
+    /// The path the service is served below, e.g. `/api`, or `None` for the whole domain.
+    path_prefix: Option<String>,
+    /// Whether the prefix is removed before the request reaches the service.
+    strip_prefix: bool,
+    /// Whether the route is served over HTTPS, with a certificate from ACME.
+    tls: bool,
+    /// The Traefik entrypoint, `websecure` with TLS and `web` without by default.
+    entrypoint: String,
+    /// The Traefik middlewares, e.g. `secure-headers@file`, or the Caddyfile snippets imported
+    /// into the route.
+    middlewares: Vec<String>,
+}
+
+/// The reverse proxy routes, read from the optional `reverse_proxy` section of the configuration.
+///
+/// The proxy runs on the servers next to the services and joins the external `api-network`,
+/// where it reaches them by service name.
+pub struct ReverseProxySettings {
+    pub provider: ReverseProxyProvider,
+    /// The Traefik certificate resolver issuing the certificates.
+    cert_resolver: String,
+    /// The email Caddy registers the ACME account with.
+    acme_email: Option<String>,
+    routes: Vec<ProxyRoute>,
+}
+
+impl ReverseProxySettings {
+    /// Reads the reverse proxy settings, if the `reverse_proxy` section is present.
+    ///
+    /// Every service listening on a port is routed, or only the ones in
+    /// `reverse_proxy.services` when it is set. The `domain`, `path_prefix`, `strip_prefix`,
+    /// `tls` and `entrypoint` of a service entry replace the ones of the section, its
+    /// `middlewares` are added to them.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<ReverseProxySettings>>`: The settings, `None` without a reverse
+    ///   proxy, or an error if a setting is invalid, two services share a route or the proxy is
+    ///   combined with blue/green deploys.
+    pub fn from_config(config: &Value) -> io::Result<Option<ReverseProxySettings>> {
+        let reverse_proxy = &config["reverse_proxy"];
+        if reverse_proxy.is_null() || reverse_proxy.as_bool() == Some(false) {
+            return Ok(None);
+        }
+
+        let provider = match reverse_proxy["provider"].as_str().unwrap_or("traefik") {
+            "traefik" => ReverseProxyProvider::Traefik,
+            "caddy" => ReverseProxyProvider::Caddy,
+            provider => {
+                return Err(invalid(format!(
+                    "Unknown reverse_proxy.provider: {} (use traefik or caddy)",
+                    provider
+                )))
+            }
+        };
+        if !config["blue_green"].is_null() {
+            return Err(invalid(
+                "reverse_proxy cannot be combined with blue_green, which runs its own proxy"
+                    .to_string(),
+            ));
+        }
+
+        let services: Vec<&Value> = match config["project_type"].as_str() {
+            Some("monorepo") => get_monorepo_services(config)?.iter().collect(),
+            _ => vec![config],
+        };
+        let entries = reverse_proxy["services"].as_object();
+        if let Some(name) = entries
+            .into_iter()
+            .flat_map(|entries| entries.keys())
+            .find(|name| {
+                !services
+                    .iter()
+                    .any(|service| service["service_name"].as_str() == Some(name.as_str()))
+            })
+        {
+            return Err(invalid(format!(
+                "reverse_proxy.services.{} is not a service of the project",
+                name
+            )));
+        }
+
+        let mut routes: Vec<ProxyRoute> = Vec::new();
+        for service in services {
+            let name = service["service_name"]
+                .as_str()
+                .unwrap_or("default_service");
+            let entry = match entries {
+                Some(entries) => match entries.get(name) {
+                    Some(entry) => entry,
+                    None => continue,
+                },
+                None => &Value::Null,
+            };
+            let port = match service["project_type"].as_str() {
+                Some("angular") => Some(80),
+                _ => get_dotnet_container_port(service["app_type"].as_str().unwrap_or("web")),
+            };
+            let port = match port {
+                Some(port) => port,
+                // Workers are only routed when they are named
+                None if entry.is_null() => continue,
+                None => {
+                    return Err(invalid(format!(
+                        "reverse_proxy.services.{}: the service does not listen on a port",
+                        name
+                    )))
+                }
+            };
+
+            let route = read_route(reverse_proxy, entry, name, port)
+                .map_err(|e| invalid(format!("reverse_proxy: {}", e)))?;
+            if let Some(other) = routes.iter().find(|other| {
+                other.domain == route.domain && other.path_prefix == route.path_prefix
+            }) {
+                return Err(invalid(format!(
+                    "reverse_proxy: {} and {} are both routed to {}{}",
+                    other.service,
+                    route.service,
+                    route.domain,
+                    route.path_prefix.as_deref().unwrap_or_default()
+                )));
+            }
+            routes.push(route);
+        }
+
+        let acme_email = reverse_proxy["acme_email"].as_str().map(str::to_string);
+        if let Some(email) = &acme_email {
+            if !email.contains('@') || email.contains(|c: char| c.is_whitespace() || c == '"') {
+                return Err(invalid(format!(
+                    "Invalid reverse_proxy.acme_email: {}",
+                    email
+                )));
+            }
+        }
+
+        Ok(Some(ReverseProxySettings {
+            provider,
+            cert_resolver: reverse_proxy["cert_resolver"]
+                .as_str()
+                .unwrap_or("letsencrypt")
+                .to_string(),
+            acme_email,
+            routes,
+        }))
+    }
+
+    /// Returns whether a service is routed by the proxy, and so must join the `api-network`.
+    pub fn is_routed(&self, service_name: &str) -> bool {
+        self.route(service_name).is_some()
+    }
+
+    fn route(&self, service_name: &str) -> Option<&ProxyRoute> {
+        self.routes
+            .iter()
+            .find(|route| route.service == service_name)
+    }
+
+    /// Returns the Traefik labels of a service, or nothing if it is not routed or Caddy is the
+    /// proxy.
+    ///
+    /// # Parameters
+    /// - `service_name`: The name of the service.
+    /// - `network_label`: The label naming the network Traefik reaches the service on,
+    ///   `traefik.docker.network` for compose or `traefik.swarm.network` for a stack.
+    ///
+    /// # Returns
+    /// - `Vec<String>`: The labels, as `key=value`.
+    pub fn traefik_labels(&self, service_name: &str, network_label: &str) -> Vec<String> {
+        let route = match (&self.provider, self.route(service_name)) {
+            (ReverseProxyProvider::Traefik, Some(route)) => route,
+            _ => return Vec::new(),
+        };
+        let router = format!("traefik.http.routers.{}", route.service);
+
+        let mut rule = format!("Host(`{}`)", route.domain);
+        if let Some(path_prefix) = &route.path_prefix {
+            rule.push_str(&format!(" && PathPrefix(`{}`)", path_prefix));
+        }
+        let mut labels = vec![
+            "traefik.enable=true".to_string(),
+            format!("{}=api-network", network_label),
+            format!("{}.rule={}", router, rule),
+            format!("{}.entrypoints={}", router, route.entrypoint),
+        ];
+        if route.tls {
+            labels.push(format!("{}.tls=true", router));
+            labels.push(format!(
+                "{}.tls.certresolver={}",
+                router, self.cert_resolver
+            ));
+        }
+
+        let mut middlewares = Vec::new();
+        if let (Some(path_prefix), true) = (&route.path_prefix, route.strip_prefix) {
+            let middleware = format!("{}-strip-prefix", route.service);
+            labels.push(format!(
+                "traefik.http.middlewares.{}.stripprefix.prefixes={}",
+                middleware, path_prefix
+            ));
+            middlewares.push(middleware);
+        }
+        middlewares.extend(route.middlewares.iter().cloned());
+        if !middlewares.is_empty() {
+            labels.push(format!("{}.middlewares={}", router, middlewares.join(",")));
+        }
+
+        labels.push(format!("{}.service={}", router, route.service));
+        labels.push(format!(
+            "traefik.http.services.{}.loadbalancer.server.port={}",
+            route.service, route.port
+        ));
+        labels
+    }
+
+    /// Renders the `labels` block of a compose service, indented to sit in the service, or an
+    /// empty string if the service has no labels.
+    ///
+    /// # Parameters
+    /// - `service_name`: The name of the service.
+    ///
+    /// # Returns
+    /// - `String`: The labels block.
+    pub fn render_compose_labels(&self, service_name: &str) -> String {
+        let labels = self.traefik_labels(service_name, "traefik.docker.network");
+        if labels.is_empty() {
+            return String::new();
+        }
+
+        let mut block = String::from("    labels:\n");
+        for label in labels {
+            block.push_str(&format!("      - \"{}\"\n", label));
+        }
+        block
+    }
+
+    /// Renders the Caddyfile snippet with a site block per domain.
+    fn render_caddyfile(&self, project_name: &str) -> String {
+        let mut caddyfile = format!(
+            "# The routes of {}, generated by fileforge. Import it from the Caddyfile of the proxy,\n# which must join the api-network.\n",
+            project_name
+        );
+
+        let mut domains: Vec<(&str, bool)> = Vec::new();
+        for route in &self.routes {
+            if !domains.iter().any(|(domain, _)| *domain == route.domain) {
+                domains.push((&route.domain, route.tls));
+            }
+        }
+        for (domain, tls) in domains {
+            // Caddy gets a certificate for every site unless it is served over plain HTTP
+            let address = if tls {
+                domain.to_string()
+            } else {
+                format!("http://{}", domain)
+            };
+            caddyfile.push_str(&format!("\n{} {{\n", address));
+            if let (true, Some(email)) = (tls, &self.acme_email) {
+                caddyfile.push_str(&format!("\ttls {}\n", email));
+            }
+
+            // Caddy sorts the handle blocks by path, the routes without a prefix come last
+            for route in self.routes.iter().filter(|route| route.domain == domain) {
+                match &route.path_prefix {
+                    Some(path_prefix) => {
+                        caddyfile.push_str(&format!("\thandle {}* {{\n", path_prefix));
+                        if route.strip_prefix {
+                            caddyfile.push_str(&format!("\t\turi strip_prefix {}\n", path_prefix));
+                        }
+                    }
+                    None => caddyfile.push_str("\thandle {\n"),
+                }
+                for middleware in &route.middlewares {
+                    caddyfile.push_str(&format!("\t\timport {}\n", middleware));
+                }
+                caddyfile.push_str(&format!(
+                    "\t\treverse_proxy {}:{}\n\t}}\n",
+                    route.service, route.port
+                ));
+            }
+            caddyfile.push_str("}\n");
+        }
+        caddyfile
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_route(
+    reverse_proxy: &Value,
+    entry: &Value,
+    service: &str,
+    port: u16,
+) -> Result<ProxyRoute, String> {
+    // The entry of the service wins over the section
+    let setting = |field: &str| {
+        if entry[field].is_null() {
+            &reverse_proxy[field]
+        } else {
+            &entry[field]
+        }
+    };
+
+    let domain = setting("domain")
+        .as_str()
+        .ok_or_else(|| format!("{} has no domain", service))?
+        .to_lowercase();
+    if domain.is_empty()
+        || !domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '*')
+    {
+        return Err(format!("invalid domain of {}: {}", service, domain));
+    }
+
+    let path_prefix = match setting("path_prefix").as_str() {
+        None | Some("") | Some("/") => None,
+        Some(path) => {
+            let path = format!("/{}", path.trim_matches('/'));
+            if path.contains(|c: char| c.is_whitespace() || "`\"'*{}".contains(c)) {
+                return Err(format!("invalid path_prefix of {}: {}", service, path));
+            }
+            Some(path)
+        }
+    };
+
+    let tls = setting("tls").as_bool().unwrap_or(true);
+    let entrypoint = setting("entrypoint")
+        .as_str()
+        .unwrap_or(if tls { "websecure" } else { "web" })
+        .to_string();
+
+    let mut middlewares = Vec::new();
+    for item in reverse_proxy["middlewares"]
+        .as_array()
+        .into_iter()
+        .chain(entry["middlewares"].as_array())
+        .flatten()
+    {
+        match item.as_str() {
+            Some(name)
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "@._-".contains(c)) =>
+            {
+                middlewares.push(name.to_string())
+            }
+            _ => return Err(format!("invalid middlewares entry: {}", item)),
+        }
+    }
+
+    Ok(ProxyRoute {
+        service: service.to_string(),
+        port,
+        domain,
+        path_prefix,
+        strip_prefix: setting("strip_prefix").as_bool().unwrap_or(false),
+        tls,
+        entrypoint,
+        middlewares,
+    })
+}
+
+/// Writes the `caddy/<project>.caddy` snippet when Caddy routes the services. Traefik needs no
+/// file, it reads the labels of the compose services.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+/// - `output_dir`: The directory `compose.yaml` is written to.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or the file cannot be written.
+pub fn write_reverse_proxy_files(config: &Value, output_dir: &Path) -> io::Result<()> {
+    let settings = match ReverseProxySettings::from_config(config)? {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+    if !matches!(settings.provider, ReverseProxyProvider::Caddy) || settings.routes.is_empty() {
+        return Ok(());
+    }
+
+    println!("🌐 Writing the Caddy routes...");
+    thread::sleep(SLEEP_DURATION);
+
+    let project_name = compose_project_name(config);
+    write_file_with_backup(
+        &output_dir
+            .join(CADDY_DIRECTORY)
+            .join(format!("{}.caddy", project_name)),
+        &settings.render_caddyfile(&project_name),
+    )
+}
@@ -0,0 +1,528 @@
+use std::{io, path::Path, thread};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    ci_generator::{
+        blue_green::compose_project_name,
+        deploy_environment::get_deploy_environments,
+        image_registry::{ImageRegistrySettings, REGISTRY_LOGIN},
+        reverse_proxy::ReverseProxySettings,
+    },
+    constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::{
+        get_dotnet_container_port, get_dotnet_healthcheck_test,
+    },
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        output_helper::{make_executable, write_file_with_backup},
+        yaml_helper::render_yaml,
+    },
+};
+
+/// The stack file `docker stack deploy` reads, next to `compose.yaml`.
+pub const SWARM_STACK_FILE: &str = "stack.yaml";
+
+/// The script the start jobs and the Ansible playbook run instead of `docker compose up`.
+pub const STACK_DEPLOY_SCRIPT: &str = "deploy/stack-deploy.sh";
+
+/// Deploys the stack to the Swarm.
+pub const STACK_DEPLOY_COMMAND: &str = "sh deploy/stack-deploy.sh\n";
+
+/// Deploys the stack from a single host of the play, which must be a manager node. Unlike
+/// `run_once`, the condition holds once per play when the hosts are deployed in batches.
+pub const STACK_DEPLOY_CONDITION: &str =
+    "      when: inventory_hostname == ansible_play_hosts_all[0]\n";
+
+/// A Swarm config mounted into a service.
+struct StackConfig {
+    name: String,
+    /// The path of the file in the container, `/<name>` when unset.
+    target: Option<String>,
+}
+
+/// The `deploy` settings, secrets and configs of a service of the stack.
+struct StackServiceSettings {
+    replicas: u64,
+    /// The `deploy.resources` section, e.g. `{ "limits": { "cpus": "0.5", "memory": "512M" } }`.
+    resources: Option<Value>,
+    /// The placement constraints, e.g. `node.role == worker`.
+    constraints: Vec<String>,
+    /// The external secrets mounted in `/run/secrets`.
+    secrets: Vec<String>,
+    /// The external configs mounted into the container.
+    configs: Vec<StackConfig>,
+}
+
+/// The Docker Swarm deploy, read from the optional `swarm` section of the configuration.
+///
+/// With Swarm, `stack.yaml` describes the services with a `deploy` section and the start jobs
+/// run `docker stack deploy` on a manager node instead of `docker compose up`. The images come
+/// from the image registry, since a stack cannot build them.
+pub struct SwarmSettings {
+    /// The name of the stack, the project directory by default.
+    pub stack_name: String,
+    /// The settings of every service, by service name.
+    services: Vec<(String, StackServiceSettings)>,
+}
+
+impl SwarmSettings {
+    /// Reads the Swarm settings, if the `swarm` section is present.
+    ///
+    /// The `replicas`, `resources` and `constraints` of an entry in `swarm.services` replace the
+    /// ones of the section, its `secrets` and `configs` are added to them.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<SwarmSettings>>`: The settings, `None` to deploy with docker compose,
+    ///   or an error if a setting is invalid, no image registry is configured or the deploy is
+    ///   combined with a role, releases, blue/green deploys or Helm.
+    pub fn from_config(config: &Value) -> io::Result<Option<SwarmSettings>> {
+        let swarm = &config["swarm"];
+        if swarm.is_null() || swarm.as_bool() == Some(false) {
+            return Ok(None);
+        }
+
+        if config["image_registry"].is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "swarm needs an image_registry the nodes pull the images from",
+            ));
+        }
+        let ansible = &config["ansible"];
+        if ansible["role"].as_bool().unwrap_or(false)
+            || !ansible["keep_releases"].is_null()
+            || !config["blue_green"].is_null()
+            || !config["helm"].is_null()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "swarm cannot be combined with ansible.role, ansible.keep_releases, blue_green or helm",
+            ));
+        }
+
+        let stack_name = swarm["stack_name"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| compose_project_name(config));
+        if stack_name.is_empty()
+            || !stack_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid swarm.stack_name: {:?}", stack_name),
+            ));
+        }
+
+        let service_names = get_stack_service_configs(config)?
+            .iter()
+            .map(|service| service_name(service).to_string())
+            .collect::<Vec<String>>();
+        if let Some(name) = swarm["services"]
+            .as_object()
+            .into_iter()
+            .flat_map(|services| services.keys())
+            .find(|name| !service_names.contains(name))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("swarm.services.{} is not a service of the project", name),
+            ));
+        }
+
+        let mut services = Vec::new();
+        for name in service_names {
+            let settings = read_service_settings(swarm, &swarm["services"][&name])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("swarm: {}", e)))?;
+            services.push((name, settings));
+        }
+
+        Ok(Some(SwarmSettings {
+            stack_name,
+            services,
+        }))
+    }
+
+    fn service(&self, name: &str) -> Option<&StackServiceSettings> {
+        self.services
+            .iter()
+            .find(|(service_name, _)| service_name == name)
+            .map(|(_, settings)| settings)
+    }
+}
+
+/// Returns the service entries of the project: the services of a monorepo, or the configuration
+/// itself.
+fn get_stack_service_configs(config: &Value) -> io::Result<Vec<Value>> {
+    match config["project_type"].as_str() {
+        Some("monorepo") => get_monorepo_services(config).cloned(),
+        _ => Ok(vec![config.clone()]),
+    }
+}
+
+fn service_name(service: &Value) -> &str {
+    service["service_name"]
+        .as_str()
+        .unwrap_or("default_service")
+}
+
+/// Reads a list of names, e.g. `secrets`, from a service entry or the section.
+fn read_names(value: &Value, field: &str) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    for item in value[field].as_array().into_iter().flatten() {
+        match item.as_str() {
+            Some(name) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                names.push(name.to_string())
+            }
+            _ => return Err(format!("invalid {} entry: {}", field, item)),
+        }
+    }
+    Ok(names)
+}
+
+/// Reads the configs of a service entry or the section, given by name or as `{ name, target }`.
+fn read_configs(value: &Value) -> Result<Vec<StackConfig>, String> {
+    let mut configs = Vec::new();
+    for item in value["configs"].as_array().into_iter().flatten() {
+        let (name, target) = match item {
+            Value::String(name) => (Some(name.as_str()), None),
+            item => (
+                item["name"].as_str(),
+                item["target"].as_str().map(str::to_string),
+            ),
+        };
+        match name {
+            Some(name) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                configs.push(StackConfig {
+                    name: name.to_string(),
+                    target,
+                })
+            }
+            _ => return Err(format!("invalid configs entry: {}", item)),
+        }
+    }
+    Ok(configs)
+}
+
+fn read_service_settings(swarm: &Value, service: &Value) -> Result<StackServiceSettings, String> {
+    // The entry of the service wins over the section
+    let setting = |field: &str| {
+        if service[field].is_null() {
+            &swarm[field]
+        } else {
+            &service[field]
+        }
+    };
+
+    let resources = match setting("resources") {
+        Value::Null => None,
+        Value::Object(resources) => Some(Value::Object(resources.clone())),
+        resources => return Err(format!("resources must be an object, got {}", resources)),
+    };
+
+    // Constraints are expressions with spaces, e.g. `node.role == worker`
+    let constraints = setting("constraints")
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|constraint| {
+            constraint
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("invalid constraints entry: {}", constraint))
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+
+    let mut secrets = read_names(swarm, "secrets")?;
+    secrets.extend(read_names(service, "secrets")?);
+    let mut configs = read_configs(swarm)?;
+    configs.extend(read_configs(service)?);
+
+    Ok(StackServiceSettings {
+        replicas: setting("replicas").as_u64().unwrap_or(1),
+        resources,
+        constraints,
+        secrets,
+        configs,
+    })
+}
+
+const STACK_DEPLOY_SCRIPT_CONTENT: &str = r#"#!/bin/sh
+# Deploys the services to the Swarm as the [[ stack ]] stack, generated by fileforge.
+#
+# Run it from the project directory on a manager node with COMPOSE_FILE set like for
+# `docker compose up`: every compose.<environment>.yaml in it adds stack.<environment>.yaml. The
+# `.env` file holds the IMAGE_TAG of the release. The registry login uses IMAGE_REGISTRY_USERNAME
+# and IMAGE_REGISTRY_PASSWORD when they are set, and is passed on to the nodes.
+set -eu
+
+# docker stack deploy does not read the .env file
+if [ -f .env ]; then
+  set -a
+  . ./.env
+  set +a
+fi
+
+stack_files="-c [[ stack_file ]]"
+for file in $(echo "${COMPOSE_FILE:-compose.yaml}" | tr ':' ' '); do
+  case "$file" in
+    compose.*.yaml)
+      if [ -f "stack.${file#compose.}" ]; then
+        stack_files="$stack_files -c stack.${file#compose.}"
+      fi
+      ;;
+  esac
+done
+
+[[ login ]]
+echo "Deploying the [[ stack ]] stack..."
+# shellcheck disable=SC2086
+docker stack deploy $stack_files --with-registry-auth --prune --detach=false [[ stack ]]
+"#;
+
+/// Renders the `deploy` section of a service: a rolling update starting the new task before the
+/// old one is stopped, rolled back when it fails.
+fn render_deploy_section(settings: &StackServiceSettings) -> Value {
+    let mut deploy = json!({
+        "replicas": settings.replicas,
+        "update_config": {
+            "parallelism": 1,
+            "delay": "10s",
+            "order": "start-first",
+            "failure_action": "rollback",
+        },
+        "rollback_config": {
+            "parallelism": 1,
+            "order": "start-first",
+        },
+        "restart_policy": {
+            "condition": "on-failure",
+            "delay": "5s",
+            "max_attempts": 3,
+            "window": "120s",
+        },
+    });
+    if let Some(resources) = &settings.resources {
+        deploy["resources"] = resources.clone();
+    }
+    if !settings.constraints.is_empty() {
+        deploy["placement"] = json!({ "constraints": settings.constraints });
+    }
+    deploy
+}
+
+/// Renders the stack service of a .NET or Angular service entry. Unlike in `compose.yaml`, the
+/// service has no container name or restart policy, which Swarm manages through `deploy`. Traefik
+/// reads the labels of the service rather than the ones of its containers, so the route goes to
+/// `deploy.labels`.
+fn render_stack_service(
+    service: &Value,
+    settings: &StackServiceSettings,
+    image_registry: &ImageRegistrySettings,
+    reverse_proxy: Option<&ReverseProxySettings>,
+) -> io::Result<Value> {
+    let name = service_name(service);
+    let routed = reverse_proxy.is_some_and(|proxy| proxy.is_routed(name));
+    let image_name = service["image_name"].as_str().unwrap_or("default_image");
+    let port = service["port"].as_u64().unwrap_or(5000);
+
+    let mut stack_service = Map::new();
+    stack_service.insert("image".to_string(), json!(image_registry.image(image_name)));
+
+    match service["project_type"].as_str() {
+        Some("angular") => {
+            stack_service.insert("ports".to_string(), json!([format!("{}:80", port)]));
+            stack_service.insert(
+                "environment".to_string(),
+                json!({ "NODE_ENV": "production" }),
+            );
+            if routed {
+                stack_service.insert("networks".to_string(), json!(["api-network"]));
+            }
+        }
+        _ => {
+            let app_type = service["app_type"].as_str().unwrap_or("web");
+            if let Some(container_port) = get_dotnet_container_port(app_type) {
+                stack_service.insert(
+                    "ports".to_string(),
+                    json!([format!("{}:{}", port, container_port)]),
+                );
+            }
+
+            // Swarm waits for the healthcheck before it stops the old task
+            if service["enable_healthcheck"].as_bool().unwrap_or(false) {
+                let test = get_dotnet_healthcheck_test(service)?;
+                stack_service.insert(
+                    "healthcheck".to_string(),
+                    json!({
+                        "test": serde_json::from_str::<Value>(&test).unwrap_or(json!(test)),
+                        "interval": "40s",
+                        "timeout": "30s",
+                        "retries": 3,
+                        "start_period": "60s",
+                    }),
+                );
+            }
+            stack_service.insert("networks".to_string(), json!(["api-network"]));
+        }
+    }
+
+    let mut deploy = render_deploy_section(settings);
+    if let Some(reverse_proxy) = reverse_proxy {
+        let labels = reverse_proxy.traefik_labels(name, "traefik.swarm.network");
+        if !labels.is_empty() {
+            deploy["labels"] = json!(labels);
+        }
+    }
+    stack_service.insert("deploy".to_string(), deploy);
+    if !settings.secrets.is_empty() {
+        stack_service.insert("secrets".to_string(), json!(settings.secrets));
+    }
+    if !settings.configs.is_empty() {
+        let configs = settings
+            .configs
+            .iter()
+            .map(|config| match &config.target {
+                Some(target) => json!({ "source": config.name, "target": target }),
+                None => json!(config.name),
+            })
+            .collect::<Vec<Value>>();
+        stack_service.insert("configs".to_string(), Value::Array(configs));
+    }
+
+    Ok(Value::Object(stack_service))
+}
+
+/// Writes the Swarm deploy files when the `swarm` section is configured: `stack.yaml` with a
+/// `deploy` section per service, a `stack.<environment>.yaml` per environment with its replica
+/// counts and variables, and the `deploy/stack-deploy.sh` script.
+///
+/// The secrets and configs are declared external, they are created on the Swarm beforehand with
+/// `docker secret create` and `docker config create`.
+///
+/// # Parameters
+/// - `config`: The project configuration.
+/// - `output_dir`: The directory `compose.yaml` is written to.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn write_swarm_stack_files(config: &Value, output_dir: &Path) -> io::Result<()> {
+    let settings = match SwarmSettings::from_config(config)? {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+    let image_registry = match ImageRegistrySettings::from_config(config)? {
+        Some(image_registry) => image_registry,
+        None => return Ok(()),
+    };
+    let services = get_stack_service_configs(config)?;
+    let reverse_proxy = ReverseProxySettings::from_config(config)?;
+
+    println!("🐳 Writing the Swarm stack {}...", settings.stack_name);
+    thread::sleep(SLEEP_DURATION);
+
+    let mut stack_services = Map::new();
+    let mut secrets = Map::new();
+    let mut configs = Map::new();
+    for service in &services {
+        let name = service_name(service);
+        let service_settings = match settings.service(name) {
+            Some(service_settings) => service_settings,
+            None => continue,
+        };
+        for secret in &service_settings.secrets {
+            secrets.insert(secret.clone(), json!({ "external": true }));
+        }
+        for stack_config in &service_settings.configs {
+            configs.insert(stack_config.name.clone(), json!({ "external": true }));
+        }
+        stack_services.insert(
+            name.to_string(),
+            render_stack_service(
+                service,
+                service_settings,
+                &image_registry,
+                reverse_proxy.as_ref(),
+            )?,
+        );
+    }
+
+    let mut stack = Map::new();
+    stack.insert("services".to_string(), Value::Object(stack_services));
+    // The API network must be an attachable overlay network on the Swarm
+    if services.iter().any(|service| {
+        service["project_type"].as_str() != Some("angular")
+            || reverse_proxy
+                .as_ref()
+                .is_some_and(|proxy| proxy.is_routed(service_name(service)))
+    }) {
+        stack.insert(
+            "networks".to_string(),
+            json!({ "api-network": { "external": true } }),
+        );
+    }
+    if !secrets.is_empty() {
+        stack.insert("secrets".to_string(), Value::Object(secrets));
+    }
+    if !configs.is_empty() {
+        stack.insert("configs".to_string(), Value::Object(configs));
+    }
+    write_file_with_backup(
+        &output_dir.join(SWARM_STACK_FILE),
+        &render_yaml(&Value::Object(stack)),
+    )?;
+
+    // Stack files are merged like compose files, without the `!override` and `!reset` tags of
+    // the compose overrides, so the environments only set replica counts and variables
+    let single_service = services.len() == 1;
+    for environment in get_deploy_environments(config)?
+        .iter()
+        .filter(|environment| environment.configured)
+    {
+        let mut environment_services = Map::new();
+        for service in &services {
+            let name = service_name(service);
+            let mut environment_service = Map::new();
+            if let Some(replicas) = environment.replicas_override(name, single_service) {
+                environment_service.insert("deploy".to_string(), json!({ "replicas": replicas }));
+            }
+            let env = environment.env_overrides(name);
+            if !env.is_empty() {
+                environment_service.insert(
+                    "environment".to_string(),
+                    Value::Object(
+                        env.into_iter()
+                            .map(|(key, value)| (key, Value::String(value)))
+                            .collect(),
+                    ),
+                );
+            }
+            if !environment_service.is_empty() {
+                environment_services.insert(name.to_string(), Value::Object(environment_service));
+            }
+        }
+        write_file_with_backup(
+            &output_dir.join(format!("stack.{}.yaml", environment.name)),
+            &render_yaml(&json!({ "services": environment_services })),
+        )?;
+    }
+
+    let script_path = output_dir.join(STACK_DEPLOY_SCRIPT);
+    write_file_with_backup(
+        &script_path,
+        &STACK_DEPLOY_SCRIPT_CONTENT
+            .replace("[[ stack_file ]]", SWARM_STACK_FILE)
+            .replace(
+                "[[ login ]]",
+                &REGISTRY_LOGIN.replace("[[ host ]]", image_registry.host()),
+            )
+            .replace("[[ stack ]]", &settings.stack_name),
+    )?;
+    make_executable(&script_path)?;
+
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::{
     fs::File,
     io::{self, Write},
 };
 
+use crate::config::angular_workspace::{find_angular_applications, AngularApplication};
 use crate::shared::{
     directory_helper,
     shared_input_helper::{
@@ -15,6 +17,14 @@ use crate::shared::{
 pub struct AngularConfig {
     pub project_type: String,
     pub node_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dist_path: Option<String>,
     pub service_name: String,
     pub image_name: String,
     pub container_name: String,
@@ -31,37 +41,177 @@ pub fn create_or_update_config() -> io::Result<()> {
     });
     println!("📂 Root Directory: {:?}", current_dir);
 
+    // Look for the application projects of an Angular CLI or Nx workspace
+    let (workspace_type, applications) = find_angular_applications(&std::env::current_dir()?)?;
+    let applications = if applications.is_empty() {
+        println!("No application projects found in the workspace. Using the default build.");
+        Vec::new()
+    } else {
+        println!(
+            "Found {} application project(s) in the {} workspace.",
+            applications.len(),
+            workspace_type
+        );
+        get_applications_from_user(applications)
+    };
+
     let node_version = get_input_from_user("Node Version: ");
-    let service_name = get_input_from_user_with_default("Service name: ", &current_dir);
-    let image_name = get_input_from_user_with_default("Image name: ", &current_dir);
-    let container_name = get_input_from_user_with_default("Container name: ", &current_dir);
 
-    let port = get_port_from_user();
-    let project_location = get_input_from_user_with_default(
-        "Project location (default: /etc/www, don't include the trailing slash): ",
-        "/etc/www",
-    );
-    let project_directory = get_input_from_user_with_default("Project directory: ", &current_dir);
+    let config_json = if applications.len() > 1 {
+        // Several applications become the services of a monorepo configuration, each with its
+        // own Dockerfile at the workspace root
+        let project_location = get_project_location_from_user();
+        let project_directory =
+            get_input_from_user_with_default("Project directory: ", &current_dir);
 
-    // Save to config file
-    let config = AngularConfig {
-        project_type: "angular".to_string(),
-        node_version,
-        service_name,
-        image_name,
-        container_name,
-        port,
-        project_location,
-        project_directory,
+        let mut services = Vec::new();
+        for application in &applications {
+            println!("\n📦 Configuring application: {}", application.name);
+            let service = get_service_config_from_user(
+                &node_version,
+                &workspace_type,
+                Some(application),
+                &application.name,
+                &project_location,
+                &project_directory,
+            );
+
+            let mut service = serde_json::to_value(service)?;
+            service["path"] = json!(".");
+            service["dockerfile"] = json!(format!("Dockerfile.{}", application.name));
+            services.push(service);
+        }
+
+        let config = json!({
+            "project_type": "monorepo",
+            "project_location": project_location,
+            "project_directory": project_directory,
+            "services": Value::Array(services),
+        });
+        serde_json::to_string_pretty(&config)?
+    } else {
+        let project_location = get_project_location_from_user();
+        let project_directory =
+            get_input_from_user_with_default("Project directory: ", &current_dir);
+        let application = applications.first();
+        let default_name = application
+            .map(|application| application.name.clone())
+            .unwrap_or_else(|| current_dir.clone());
+
+        let config = get_service_config_from_user(
+            &node_version,
+            &workspace_type,
+            application,
+            &default_name,
+            &project_location,
+            &project_directory,
+        );
+        serde_json::to_string_pretty(&config)?
     };
 
+    // Save to config file
     let current_dir = std::env::current_dir()?;
     let config_path = current_dir.join("fileforge.config.json");
     let mut config_file = File::create(config_path)?;
-    let config_json = serde_json::to_string_pretty(&config)?;
     config_file.write_all(config_json.as_bytes())?;
 
     println!("Angular Configuration saved to fileforge.config.json");
 
     Ok(())
 }
+
+/// Prompts the user for the service settings of one Angular application.
+fn get_service_config_from_user(
+    node_version: &str,
+    workspace_type: &str,
+    application: Option<&AngularApplication>,
+    default_name: &str,
+    project_location: &str,
+    project_directory: &str,
+) -> AngularConfig {
+    let service_name = get_input_from_user_with_default("Service name: ", default_name);
+    let image_name = get_input_from_user_with_default("Image name: ", default_name);
+    let container_name = get_input_from_user_with_default("Container name: ", default_name);
+    let port = get_port_from_user();
+
+    AngularConfig {
+        project_type: "angular".to_string(),
+        node_version: node_version.to_string(),
+        workspace_type: application.map(|_| workspace_type.to_string()),
+        app_name: application.map(|application| application.name.clone()),
+        build_command: application.map(|application| application.build_command.clone()),
+        dist_path: application.map(|application| application.dist_path.clone()),
+        service_name,
+        image_name,
+        container_name,
+        port,
+        project_location: project_location.to_string(),
+        project_directory: project_directory.to_string(),
+    }
+}
+
+fn get_project_location_from_user() -> String {
+    get_input_from_user_with_default(
+        "Project location (default: /etc/www, don't include the trailing slash): ",
+        "/etc/www",
+    )
+}
+
+/// Lets the user choose which of the workspace applications to generate files for.
+///
+/// # Parameters
+/// - `applications`: The application projects found in the workspace.
+///
+/// # Returns
+/// - `Vec<AngularApplication>`: The chosen applications, in workspace order.
+fn get_applications_from_user(applications: Vec<AngularApplication>) -> Vec<AngularApplication> {
+    if applications.len() == 1 {
+        println!("Using application: {}", applications[0].name);
+        return applications;
+    }
+
+    for (index, application) in applications.iter().enumerate() {
+        println!(
+            "  {}. {} ({})",
+            index + 1,
+            application.name,
+            application.dist_path
+        );
+    }
+
+    loop {
+        let selection = get_input_from_user_with_default(
+            "Applications to generate (comma separated numbers or 'all'): ",
+            "1",
+        );
+
+        if selection.eq_ignore_ascii_case("all") {
+            return applications;
+        }
+
+        let indexes = selection
+            .split(',')
+            .map(|index| index.trim().parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>();
+
+        match indexes {
+            Ok(indexes)
+                if !indexes.is_empty()
+                    && indexes
+                        .iter()
+                        .all(|index| *index >= 1 && *index <= applications.len()) =>
+            {
+                return applications
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| indexes.contains(&(index + 1)))
+                    .map(|(_, application)| application)
+                    .collect();
+            }
+            _ => println!(
+                "Invalid selection. Please enter numbers between 1 and {}.",
+                applications.len()
+            ),
+        }
+    }
+}
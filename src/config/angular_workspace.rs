@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    io::{self},
+    path::Path,
+};
+
+use serde_json::Value;
+
+/// Directories skipped while looking for Nx `project.json` files.
+const IGNORED_DIRECTORIES: [&str; 6] = ["node_modules", "dist", ".git", ".angular", ".nx", "tmp"];
+
+/// An application project found in an Angular CLI or Nx workspace.
+pub struct AngularApplication {
+    pub name: String,
+    pub build_command: String,
+    pub dist_path: String,
+}
+
+/// Enumerates the application projects of the Angular workspace in the given directory.
+///
+/// `angular.json` is used when present (Angular CLI workspaces and older Nx workspaces), otherwise
+/// the `project.json` files of an Nx workspace (identified by `nx.json`) are read. Library
+/// projects are ignored.
+///
+/// # Parameters
+/// - `workspace_dir`: The root directory of the workspace.
+///
+/// # Returns
+/// - `io::Result<(String, Vec<AngularApplication>)>`: The workspace type (`angular_cli`, `nx` or
+///   `none` when neither file exists) and the application projects.
+pub fn find_angular_applications(
+    workspace_dir: &Path,
+) -> io::Result<(String, Vec<AngularApplication>)> {
+    let angular_json_path = workspace_dir.join("angular.json");
+    if angular_json_path.exists() {
+        let angular_json: Value = serde_json::from_str(&fs::read_to_string(angular_json_path)?)?;
+        let uses_nx = workspace_dir.join("nx.json").exists();
+
+        let applications = angular_json["projects"]
+            .as_object()
+            .map(|projects| {
+                projects
+                    .iter()
+                    .filter(|(_, project)| project["projectType"].as_str() == Some("application"))
+                    .map(|(name, project)| {
+                        to_angular_application(name, &project["architect"]["build"], uses_nx)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let workspace_type = if uses_nx { "nx" } else { "angular_cli" };
+        return Ok((workspace_type.to_string(), applications));
+    }
+
+    if workspace_dir.join("nx.json").exists() {
+        let mut applications = Vec::new();
+        collect_nx_applications(workspace_dir, &mut applications)?;
+        applications.sort_by(|a, b| a.name.cmp(&b.name));
+        return Ok(("nx".to_string(), applications));
+    }
+
+    Ok(("none".to_string(), Vec::new()))
+}
+
+/// Recursively collects the application projects described by Nx `project.json` files.
+fn collect_nx_applications(
+    dir: &Path,
+    applications: &mut Vec<AngularApplication>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            let is_ignored = path
+                .file_name()
+                .is_some_and(|name| IGNORED_DIRECTORIES.iter().any(|ignored| name == *ignored));
+            if !is_ignored {
+                collect_nx_applications(&path, applications)?;
+            }
+        } else if path.file_name().is_some_and(|name| name == "project.json") {
+            let project: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            if project["projectType"].as_str() != Some("application") {
+                continue;
+            }
+
+            // Nx falls back to the directory name when the project has no explicit name
+            let name = project["name"]
+                .as_str()
+                .map(|name| name.to_string())
+                .or_else(|| {
+                    path.parent()
+                        .and_then(|parent| parent.file_name())
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+                .unwrap_or_default();
+
+            applications.push(to_angular_application(
+                &name,
+                &project["targets"]["build"],
+                true,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds an application from its `build` target, resolving where the browser bundle ends up.
+fn to_angular_application(name: &str, build_target: &Value, uses_nx: bool) -> AngularApplication {
+    let builder = build_target["builder"]
+        .as_str()
+        .or_else(|| build_target["executor"].as_str())
+        .unwrap_or("");
+
+    // `outputPath` is either a string or, since Angular 17, an object with a `base` path
+    let default_output_path = if uses_nx {
+        format!("dist/apps/{}", name)
+    } else {
+        format!("dist/{}", name)
+    };
+    let output_path = &build_target["options"]["outputPath"];
+    let output_path = output_path
+        .as_str()
+        .or_else(|| output_path["base"].as_str())
+        .unwrap_or(&default_output_path)
+        .trim_end_matches('/')
+        .to_string();
+
+    // The esbuild based application builder writes the browser bundle to a `browser` subfolder
+    let dist_path = if builder.ends_with(":application") {
+        format!("{}/browser", output_path)
+    } else {
+        output_path
+    };
+
+    let build_command = if uses_nx {
+        format!("npx nx build {} --configuration=production", name)
+    } else {
+        format!("npx ng build {} --configuration production", name)
+    };
+
+    AngularApplication {
+        name: name.to_string(),
+        build_command,
+        dist_path,
+    }
+}
@@ -11,11 +11,19 @@ pub struct DotnetConfig {
     pub project_type: String,
     pub root_namespace: String,
     pub dotnet_version: String,
+    pub app_type: String,
     pub service_name: String,
     pub image_name: String,
     pub container_name: String,
-    pub port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
     pub enable_healthcheck: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthcheck_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthcheck_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthcheck_command: Option<String>,
     pub project_location: String,
     pub project_directory: String,
 }
@@ -86,6 +94,17 @@ pub fn create_or_update_config() -> io::Result<()> {
         get_input_from_user("Dotnet version (e.g., 9.0): ")
     };
 
+    // Detect the kind of application from the project SDK
+    let app_type = if let Some(ref csproj_entry) = csproj_path {
+        let csproj_content = fs::read_to_string(csproj_entry.path())?;
+        let app_type = detect_app_type(&csproj_content);
+        println!("Detected application type: {}", app_type);
+        app_type
+    } else {
+        println!("No .csproj file found. Assuming an ASP.NET web application.");
+        "web".to_string()
+    };
+
     let hyphened_root_namespace = root_namespace.replace("_", "-").to_lowercase();
 
     // Ask for other configurations
@@ -94,8 +113,22 @@ pub fn create_or_update_config() -> io::Result<()> {
     let container_name =
         get_input_from_user_with_default("Container name: ", &hyphened_root_namespace);
 
-    let port = get_port_from_user();
+    // Worker services don't serve HTTP, so they have no port and no HTTP healthcheck
+    let is_worker = app_type == "worker";
+    let port = if is_worker {
+        println!("Worker service detected. Skipping port configuration.");
+        None
+    } else {
+        Some(get_port_from_user())
+    };
     let enable_healthcheck = get_healthcheck_from_user();
+
+    let (healthcheck_type, healthcheck_file, healthcheck_command) =
+        if is_worker && enable_healthcheck {
+            get_worker_healthcheck_from_user()
+        } else {
+            (None, None, None)
+        };
     let project_location = get_input_from_user_with_default(
         "Project location (default: /etc/www, don't include the trailing slash): ",
         "/etc/www",
@@ -108,11 +141,15 @@ pub fn create_or_update_config() -> io::Result<()> {
         project_type: "dotnet".to_string(),
         root_namespace,
         dotnet_version,
+        app_type,
         service_name,
         image_name,
         container_name,
         port,
         enable_healthcheck,
+        healthcheck_type,
+        healthcheck_file,
+        healthcheck_command,
         project_location,
         project_directory,
     };
@@ -127,6 +164,28 @@ pub fn create_or_update_config() -> io::Result<()> {
     Ok(())
 }
 
+/// Determines the application type from the contents of a `.csproj` file.
+///
+/// Blazor WebAssembly standalone apps (`Microsoft.NET.Sdk.BlazorWebAssembly`) are served as static
+/// files by nginx and worker services (`Microsoft.NET.Sdk.Worker`) run on the plain .NET runtime
+/// image. Everything else, including hosted Blazor apps whose server project uses
+/// `Microsoft.NET.Sdk.Web`, runs on the ASP.NET runtime image.
+///
+/// # Parameters
+/// - `csproj_content`: The raw contents of the `.csproj` file.
+///
+/// # Returns
+/// - `String`: One of `blazor_wasm`, `worker` or `web`.
+fn detect_app_type(csproj_content: &str) -> String {
+    if csproj_content.contains("Microsoft.NET.Sdk.BlazorWebAssembly") {
+        "blazor_wasm".to_string()
+    } else if csproj_content.contains("Microsoft.NET.Sdk.Worker") {
+        "worker".to_string()
+    } else {
+        "web".to_string()
+    }
+}
+
 fn get_healthcheck_from_user() -> bool {
     loop {
         let healthcheck: String =
@@ -150,3 +209,31 @@ fn get_healthcheck_from_user() -> bool {
         }
     }
 }
+
+/// Prompts the user for the healthcheck of a worker service, which has no HTTP endpoint.
+///
+/// # Returns
+/// - `(Option<String>, Option<String>, Option<String>)`: The healthcheck type (`file` or `exec`),
+///   the heartbeat file for `file` and the command for `exec`.
+fn get_worker_healthcheck_from_user() -> (Option<String>, Option<String>, Option<String>) {
+    loop {
+        let healthcheck_type =
+            get_input_from_user_with_default("Worker healthcheck type (file/exec): ", "file")
+                .to_lowercase();
+
+        match healthcheck_type.as_str() {
+            "file" => {
+                let healthcheck_file = get_input_from_user_with_default(
+                    "Heartbeat file touched by the worker: ",
+                    "/tmp/healthy",
+                );
+                return (Some(healthcheck_type), Some(healthcheck_file), None);
+            }
+            "exec" => {
+                let healthcheck_command = get_input_from_user("Healthcheck command: ");
+                return (Some(healthcheck_type), None, Some(healthcheck_command));
+            }
+            _ => println!("Invalid input. Please enter 'file' or 'exec'."),
+        }
+    }
+}
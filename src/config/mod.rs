@@ -1,3 +1,4 @@
 pub mod dotnet_config; 
 pub mod angular_config;
-pub mod handle_config_generation;
\ No newline at end of file
+pub mod handle_config_generation;
+pub mod angular_workspace;
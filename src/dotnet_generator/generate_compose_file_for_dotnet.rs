@@ -4,8 +4,19 @@ use std::io::{self, Read, Write};
 use std::path::Path;
 use std::{env, thread};
 
+use crate::ci_generator::base_image_registry::BaseImageRegistry;
+use crate::ci_generator::image_registry::ImageRegistrySettings;
+use crate::ci_generator::reverse_proxy::{write_reverse_proxy_files, ReverseProxySettings};
+use crate::ci_generator::swarm_stack::write_swarm_stack_files;
 use crate::constants::SLEEP_DURATION;
 
+/// The top-level network declaration shared by every .NET service in a compose file.
+pub const DOTNET_COMPOSE_NETWORKS: &str = r#"
+networks:
+  api-network:
+    external: true
+"#;
+
 pub fn generate_compose_file_for_dotnet() -> io::Result<()> {
     println!("🚀 Starting docker-compose file generation...");
     thread::sleep(SLEEP_DURATION);
@@ -27,66 +38,20 @@ pub fn generate_compose_file_for_dotnet() -> io::Result<()> {
     thread::sleep(SLEEP_DURATION);
 
     let config: Value = serde_json::from_str(&config_content)?;
+    let image_registry = ImageRegistrySettings::from_config(&config)?;
+    let registry = BaseImageRegistry::from_config(&config)?;
+    let reverse_proxy = ReverseProxySettings::from_config(&config)?;
 
-    // Extract configuration values
-    let service_name = config["service_name"].as_str().unwrap_or("default_service");
-    let image_name = config["image_name"].as_str().unwrap_or("default_image");
-    let container_name = config["container_name"]
-        .as_str()
-        .unwrap_or("default_container");
-    let port = config["port"].as_u64().unwrap_or(5000);
-    let enable_healthcheck = config["enable_healthcheck"].as_bool().unwrap_or(false);
-
-    println!("⚙️  Extracted config values: service_name = {}, image_name = {}, container_name = {}, port = {}, enable_healthcheck = {}",
-        service_name, image_name, container_name, port, enable_healthcheck);
-    thread::sleep(SLEEP_DURATION);
-
-    // Base template without the healthcheck block
-    let mut template = format!(
-        r#"
-services:
-  {service_name}:
-    image: "{image_name}"
-    container_name: "{container_name}"
-    restart: unless-stopped
-    build:
-      context: .
-      dockerfile: Dockerfile
-    ports:
-      - '{port}:5000'
-"#,
-        service_name = service_name,
-        image_name = image_name,
-        container_name = container_name,
-        port = port,
-    );
-
-    // Append the healthcheck block if enabled
-    if enable_healthcheck {
-        println!("🩺 Adding healthcheck block...");
-        thread::sleep(SLEEP_DURATION);
-        let healthcheck_block = r#"
-    healthcheck:
-      test: curl --fail http://localhost:5000/health || exit 1
-      interval: 40s
-      timeout: 30s
-      retries: 3
-      start_period: 60s
-  "#;
-        template.push_str(healthcheck_block);
-    }
-
-    // Append the network configuration
-    template.push_str(
-        r#"
-    networks:
-      - api-network
-
-networks:
-  api-network:
-    external: true
-"#,
-    );
+    // Wrap the service in a compose document joined to the external API network
+    let mut template = String::from("\nservices:\n");
+    template.push_str(&render_compose_service_for_dotnet(
+        &config,
+        ".",
+        image_registry.as_ref(),
+        &registry,
+        reverse_proxy.as_ref(),
+    )?);
+    template.push_str(DOTNET_COMPOSE_NETWORKS);
     println!("✅ Network configuration added.");
     thread::sleep(SLEEP_DURATION);
 
@@ -151,6 +116,10 @@ networks:
     output_file.write_all(template.as_bytes())?;
     thread::sleep(SLEEP_DURATION);
 
+    // Swarm hosts deploy the same services as a stack
+    write_swarm_stack_files(&config, &output_dir)?;
+    write_reverse_proxy_files(&config, &output_dir)?;
+
     println!(
         "🎉 Compose file generated successfully at {:?}",
         output_path
@@ -159,3 +128,237 @@ networks:
 
     Ok(())
 }
+
+/// Renders the compose service definition for a .NET project, indented to sit under `services:`.
+///
+/// # Parameters
+/// - `config`: The .NET project configuration.
+/// - `build_context`: The build context of the service, relative to the compose file.
+/// - `image_registry`: The registry the image is pulled from, which leaves the `build` section to
+///   `compose.build.yaml`, or `None` to build the image on the server.
+/// - `registry`: The registry the base images are pulled from.
+/// - `reverse_proxy`: The proxy routing a hostname to the service, or `None`.
+///
+/// # Returns
+/// - `io::Result<String>`: The service definition, or an error if the healthcheck is invalid.
+pub fn render_compose_service_for_dotnet(
+    config: &Value,
+    build_context: &str,
+    image_registry: Option<&ImageRegistrySettings>,
+    registry: &BaseImageRegistry,
+    reverse_proxy: Option<&ReverseProxySettings>,
+) -> io::Result<String> {
+    // Extract configuration values
+    let service_name = config["service_name"].as_str().unwrap_or("default_service");
+    let image_name = config["image_name"].as_str().unwrap_or("default_image");
+    let container_name = config["container_name"]
+        .as_str()
+        .unwrap_or("default_container");
+    let port = config["port"].as_u64().unwrap_or(5000);
+    let dockerfile = config["dockerfile"].as_str().unwrap_or("Dockerfile");
+    let enable_healthcheck = config["enable_healthcheck"].as_bool().unwrap_or(false);
+    let app_type = config["app_type"].as_str().unwrap_or("web");
+
+    let container_port = get_dotnet_container_port(app_type);
+
+    let healthcheck_test = get_dotnet_healthcheck_test(config)?;
+
+    println!("⚙️  Extracted config values: service_name = {}, image_name = {}, container_name = {}, port = {}, enable_healthcheck = {}, app_type = {}",
+        service_name, image_name, container_name, port, enable_healthcheck, app_type);
+    thread::sleep(SLEEP_DURATION);
+
+    // Base template without the healthcheck block
+    let mut template = format!(
+        r#"  {service_name}:
+    image: "{image}"
+    container_name: "{container_name}"
+    restart: unless-stopped
+"#,
+        service_name = service_name,
+        image = image_registry
+            .map(|image_registry| image_registry.image(image_name))
+            .unwrap_or_else(|| image_name.to_string()),
+        container_name = container_name,
+    );
+
+    // Images pulled from the registry are built by the CI pipeline
+    if image_registry.is_none() {
+        template.push_str(&format!(
+            r#"    build:
+      context: {build_context}
+      dockerfile: {dockerfile}
+{build_args}"#,
+            build_context = build_context,
+            dockerfile = dockerfile,
+            build_args = registry.render_compose_build_args(),
+        ));
+    }
+
+    // Publish the port only for services that listen on one
+    if let Some(container_port) = container_port {
+        template.push_str(&format!(
+            r#"    ports:
+      - '{port}:{container_port}'
+"#,
+            port = port,
+            container_port = container_port,
+        ));
+    }
+
+    // Traefik reads the route of the service from its labels
+    if let Some(reverse_proxy) = reverse_proxy {
+        template.push_str(&reverse_proxy.render_compose_labels(service_name));
+    }
+
+    // Append the healthcheck block if enabled
+    if enable_healthcheck {
+        println!("🩺 Adding healthcheck block...");
+        thread::sleep(SLEEP_DURATION);
+        let healthcheck_block = format!(
+            r#"
+    healthcheck:
+      test: {healthcheck_test}
+      interval: 40s
+      timeout: 30s
+      retries: 3
+      start_period: 60s
+  "#,
+            healthcheck_test = healthcheck_test,
+        );
+        template.push_str(&healthcheck_block);
+    }
+
+    // Join the external API network
+    template.push_str(
+        r#"
+    networks:
+      - api-network
+"#,
+    );
+
+    Ok(template)
+}
+
+/// Returns the `test` of the healthcheck of a .NET service, as written in the compose file.
+///
+/// Blazor WebAssembly apps are checked through nginx, workers with their `healthcheck_command` or
+/// the heartbeat file they touch, and web apps on their `/health` endpoint. Worker checks use the
+/// exec form, a JSON array that is also a valid YAML sequence whatever the command contains.
+///
+/// # Parameters
+/// - `config`: The .NET project configuration.
+///
+/// # Returns
+/// - `io::Result<String>`: The healthcheck command, or a JSON array for the exec form, or an
+///   error if an `exec` healthcheck has no command.
+pub fn get_dotnet_healthcheck_test(config: &Value) -> io::Result<String> {
+    let app_type = config["app_type"].as_str().unwrap_or("web");
+
+    let test = match app_type {
+        "blazor_wasm" => "wget --quiet --spider http://localhost/ || exit 1".to_string(),
+        "worker" => {
+            serde_json::to_string(&["CMD-SHELL", &get_worker_healthcheck_command(config)?])?
+        }
+        _ => "curl --fail http://localhost:5000/health || exit 1".to_string(),
+    };
+    Ok(test)
+}
+
+/// Returns the shell command checking the health of a worker service: its `healthcheck_command`
+/// with the `exec` healthcheck type, or a check of the heartbeat file it touches.
+///
+/// # Parameters
+/// - `config`: The .NET project configuration.
+///
+/// # Returns
+/// - `io::Result<String>`: The command, or an error if an `exec` healthcheck has no command.
+pub fn get_worker_healthcheck_command(config: &Value) -> io::Result<String> {
+    if config["healthcheck_type"].as_str() == Some("exec") {
+        return match config["healthcheck_command"].as_str().map(str::trim) {
+            Some(command) if !command.is_empty() => Ok(command.to_string()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Missing healthcheck_command of {} (needed by the exec healthcheck)",
+                    config["service_name"].as_str().unwrap_or("default_service")
+                ),
+            )),
+        };
+    }
+
+    // The worker is expected to touch the heartbeat file at least once a minute
+    let healthcheck_file = config["healthcheck_file"]
+        .as_str()
+        .unwrap_or("/tmp/healthy");
+    Ok(format!("find {} -mmin -1 | grep -q .", healthcheck_file))
+}
+
+/// Returns the port a .NET service listens on inside its container.
+///
+/// Blazor WebAssembly apps are served by nginx on port 80, web apps by Kestrel on 5000 and worker
+/// services don't listen on any port.
+///
+/// # Parameters
+/// - `app_type`: The application type, `web`, `blazor_wasm` or `worker`.
+///
+/// # Returns
+/// - `Option<u16>`: The container port, or `None` for worker services.
+pub fn get_dotnet_container_port(app_type: &str) -> Option<u16> {
+    match app_type {
+        "blazor_wasm" => Some(80),
+        "worker" => None,
+        _ => Some(5000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use serde_yaml::Value as Yaml;
+
+    use super::*;
+
+    fn worker() -> Value {
+        json!({
+            "service_name": "jobs-worker",
+            "image_name": "jobs-worker",
+            "container_name": "jobs-worker",
+            "app_type": "worker",
+            "enable_healthcheck": true,
+            "healthcheck_type": "exec",
+            "healthcheck_command": "pgrep -f 'Shop.Api' || exit 1: x"
+        })
+    }
+
+    #[test]
+    fn exec_healthcheck_is_valid_yaml_whatever_the_command() {
+        let service = render_compose_service_for_dotnet(
+            &worker(),
+            ".",
+            None,
+            &BaseImageRegistry::from_config(&json!({})).unwrap(),
+            None,
+        )
+        .unwrap();
+        let compose: Yaml = serde_yaml::from_str(&format!("services:\n{}", service)).unwrap();
+
+        assert_eq!(
+            compose["services"]["jobs-worker"]["healthcheck"]["test"],
+            serde_yaml::from_str::<Yaml>(r#"["CMD-SHELL", "pgrep -f 'Shop.Api' || exit 1: x"]"#)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn exec_healthcheck_needs_a_command() {
+        let mut config = worker();
+        config["healthcheck_command"] = json!(" ");
+        assert!(get_dotnet_healthcheck_test(&config).is_err());
+
+        config
+            .as_object_mut()
+            .unwrap()
+            .remove("healthcheck_command");
+        assert!(get_dotnet_healthcheck_test(&config).is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::{env, thread};
 
+use crate::ci_generator::base_image_registry::BaseImageRegistry;
 use crate::constants::SLEEP_DURATION;
 
 pub fn generate_dockerfile_for_dotnet() -> io::Result<()> {
@@ -35,6 +36,54 @@ pub fn generate_dockerfile_for_dotnet() -> io::Result<()> {
 
     // Parse the JSON file
     let config: Value = serde_json::from_str(&config_content)?;
+    let registry = BaseImageRegistry::from_config(&config)?;
+    let updated_dockerfile = render_dockerfile_for_dotnet(&config, &registry)?;
+
+    // Write Dockerfile to output directory
+    #[cfg(debug_assertions)]
+    let output_dir = current_dir.join("testing_directory").join("dotnet-testing");
+    #[cfg(not(debug_assertions))]
+    let output_dir = current_dir.clone();
+
+    println!("📁 Ensuring output directory exists: {:?}", output_dir);
+    fs::create_dir_all(&output_dir)?;
+    thread::sleep(SLEEP_DURATION);
+
+    // Create a backup file path
+    let output_path = output_dir.join("Dockerfile");
+    let backup_path = output_dir.join("Dockerfile.backup");
+
+    // Check if Dockerfile exists and create a backup
+    if output_path.exists() {
+        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
+        fs::rename(&output_path, &backup_path)?;
+        println!("✅ Backup created successfully.");
+    }
+
+    // Write the updated Dockerfile
+    let mut output_file = File::create(&output_path)?;
+    output_file.write_all(updated_dockerfile.as_bytes())?;
+
+    println!("✅ Dockerfile written to: {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    println!("🎉 Dockerfile generation completed successfully!");
+    thread::sleep(SLEEP_DURATION);
+    Ok(())
+}
+
+/// Renders the Dockerfile for a .NET project from its configuration.
+///
+/// # Parameters
+/// - `config`: The .NET project configuration.
+/// - `registry`: The registry the base images are pulled from.
+///
+/// # Returns
+/// - `io::Result<String>`: The Dockerfile content, or an error if a required value is missing.
+pub fn render_dockerfile_for_dotnet(
+    config: &Value,
+    registry: &BaseImageRegistry,
+) -> io::Result<String> {
     let project_directory = config["project_directory"]
         .as_str()
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing 'project_directory'"))?
@@ -49,12 +98,15 @@ pub fn generate_dockerfile_for_dotnet() -> io::Result<()> {
     println!("📂 Found dotnet_version: {}", dotnet_version);
     thread::sleep(SLEEP_DURATION);
 
+    let app_type = config["app_type"].as_str().unwrap_or("web");
+    println!("📂 Found app_type: {}", app_type);
+    thread::sleep(SLEEP_DURATION);
+
     // Prepare Dockerfile template
-    let docker_template = r#"
-# Use an ARG for the Nexus URL and set a default fallback value
-ARG SONATYPE_NEXUS_URL=mcr.microsoft.com
+    let web_docker_template = r#"
+{{ registry_arg_declaration }}
 
-FROM ${SONATYPE_NEXUS_URL}/dotnet/aspnet:{{ dotnet_version }} AS base
+FROM ${{{ registry_arg }}}/dotnet/aspnet:{{ dotnet_version }} AS base
 # USER $APP_UID
 WORKDIR /app
 # EXPOSE 8080
@@ -72,7 +124,7 @@ RUN ln -snf /usr/share/zoneinfo/$TZ /etc/localtime && \
 # For HealthChecks
 RUN apt-get update && apt-get install -y curl
 
-FROM ${SONATYPE_NEXUS_URL}/dotnet/sdk:{{ dotnet_version }} AS build
+FROM ${{{ registry_arg }}}/dotnet/sdk:{{ dotnet_version }} AS build
 ARG BUILD_CONFIGURATION=Release
 WORKDIR /src
 COPY ["{{ project_directory }}.csproj", "./"]
@@ -91,41 +143,97 @@ COPY --from=publish /app/publish .
 ENTRYPOINT ["dotnet", "{{ project_directory }}.dll"]
 "#;
 
-    let updated_dockerfile = docker_template
-        .replace("{{ dotnet_version }}", &dotnet_version)
-        .replace("{{ project_directory }}", &project_directory);
-    println!("✅ Dockerfile template updated with project directory.");
-    thread::sleep(SLEEP_DURATION);
+    // Blazor WebAssembly apps are published as static files and served by nginx
+    let blazor_wasm_docker_template = r#"
+{{ registry_arg_declaration }}
 
-    // Write Dockerfile to output directory
-    #[cfg(debug_assertions)]
-    let output_dir = current_dir.join("testing_directory").join("dotnet-testing");
-    #[cfg(not(debug_assertions))]
-    let output_dir = current_dir.clone();
+FROM ${{{ registry_arg }}}/dotnet/sdk:{{ dotnet_version }} AS build
+ARG BUILD_CONFIGURATION=Release
+WORKDIR /src
+COPY ["{{ project_directory }}.csproj", "./"]
+RUN dotnet restore "{{ project_directory }}.csproj"
+COPY . .
+WORKDIR "/src/"
+RUN dotnet publish "{{ project_directory }}.csproj" -c $BUILD_CONFIGURATION -o /app/publish
 
-    println!("📁 Ensuring output directory exists: {:?}", output_dir);
-    fs::create_dir_all(&output_dir)?;
-    thread::sleep(SLEEP_DURATION);
+FROM nginx:1.24.0-alpine AS final
 
-    // Create a backup file path
-    let output_path = output_dir.join("Dockerfile");
-    let backup_path = output_dir.join("Dockerfile.backup");
+# Set the timezone for the container
+ENV TZ=Asia/Dhaka
 
-    // Check if Dockerfile exists and create a backup
-    if output_path.exists() {
-        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
-        fs::rename(&output_path, &backup_path)?;
-        println!("✅ Backup created successfully.");
-    }
+# Use the generated nginx configuration with the Blazor MIME types
+COPY nginx.conf /etc/nginx/nginx.conf
 
-    // Write the updated Dockerfile
-    let mut output_file = File::create(&output_path)?;
-    output_file.write_all(updated_dockerfile.as_bytes())?;
+# Copy the published wwwroot to the nginx default folder
+COPY --from=build /app/publish/wwwroot /usr/share/nginx/html
+"#;
 
-    println!("✅ Dockerfile written to: {:?}", output_path);
-    thread::sleep(SLEEP_DURATION);
+    // Worker services run on the plain .NET runtime without Kestrel or an HTTP healthcheck
+    let worker_docker_template = r#"
+{{ registry_arg_declaration }}
 
-    println!("🎉 Dockerfile generation completed successfully!");
+FROM ${{{ registry_arg }}}/dotnet/runtime:{{ dotnet_version }} AS base
+# USER $APP_UID
+WORKDIR /app
+
+# Set the timezone for the container
+ENV TZ=Asia/Dhaka
+
+# Create a symbolic link for the timezone
+RUN ln -snf /usr/share/zoneinfo/$TZ /etc/localtime && \
+    echo $TZ > /etc/timezone
+
+FROM ${{{ registry_arg }}}/dotnet/sdk:{{ dotnet_version }} AS build
+ARG BUILD_CONFIGURATION=Release
+WORKDIR /src
+COPY ["{{ project_directory }}.csproj", "./"]
+RUN dotnet restore "{{ project_directory }}.csproj"
+COPY . .
+WORKDIR "/src/"
+RUN dotnet build "{{ project_directory }}.csproj" -c $BUILD_CONFIGURATION -o /app/build
+
+FROM build AS publish
+ARG BUILD_CONFIGURATION=Release
+RUN dotnet publish "{{ project_directory }}.csproj" -c $BUILD_CONFIGURATION -o /app/publish /p:UseAppHost=false
+
+FROM base AS final
+WORKDIR /app
+COPY --from=publish /app/publish .
+ENTRYPOINT ["dotnet", "{{ project_directory }}.dll"]
+"#;
+
+    let docker_template = match app_type {
+        "blazor_wasm" => blazor_wasm_docker_template,
+        "worker" => worker_docker_template,
+        _ => web_docker_template,
+    };
+
+    let updated_dockerfile = docker_template
+        .replace(
+            "{{ registry_arg_declaration }}",
+            &registry.render_dockerfile_arg(),
+        )
+        .replace("{{ registry_arg }}", registry.dockerfile_arg())
+        .replace("{{ dotnet_version }}", &dotnet_version)
+        .replace("{{ project_directory }}", &project_directory);
+    println!("✅ Dockerfile template updated with project directory.");
     thread::sleep(SLEEP_DURATION);
-    Ok(())
+
+    Ok(updated_dockerfile)
+}
+
+/// Returns the `dotnet/*` base images the Dockerfile of an application type is built from.
+///
+/// # Parameters
+/// - `app_type`: The application type from the configuration (`web`, `blazor_wasm` or `worker`).
+///
+/// # Returns
+/// - `&'static [&'static str]`: The image names, relative to the registry root.
+pub fn get_dotnet_base_images(app_type: &str) -> &'static [&'static str] {
+    // Blazor WebAssembly apps only need the SDK image, the runtime is nginx
+    match app_type {
+        "blazor_wasm" => &["sdk"],
+        "worker" => &["runtime", "sdk"],
+        _ => &["aspnet", "sdk"],
+    }
 }
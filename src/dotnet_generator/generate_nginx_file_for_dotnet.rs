@@ -0,0 +1,155 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Write},
+    thread,
+};
+
+use crate::constants::SLEEP_DURATION;
+
+/// File extensions served from `_framework/` by a Blazor WebAssembly app and their MIME types.
+const BLAZOR_FRAMEWORK_TYPES: [(&str, &str); 7] = [
+    ("wasm", "application/wasm"),
+    ("dll", "application/octet-stream"),
+    ("pdb", "application/octet-stream"),
+    ("dat", "application/octet-stream"),
+    ("blat", "application/octet-stream"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+];
+
+pub fn generate_nginx_file_for_dotnet() -> io::Result<()> {
+    println!("🚀 Starting nginx file generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let template = render_nginx_file_for_dotnet();
+
+    // Determine the output directory based on the build mode
+    let output_dir = if cfg!(debug_assertions) {
+        current_dir.join("testing_directory").join("dotnet-testing")
+    } else {
+        current_dir.clone()
+    };
+
+    println!("📁 Ensuring output directory exists: {:?}", output_dir);
+    thread::sleep(SLEEP_DURATION);
+    fs::create_dir_all(&output_dir)?;
+
+    let output_path = output_dir.join("nginx.conf");
+    let backup_path = output_dir.join("nginx.conf.backup");
+
+    // Check if nginx.conf exists and create a backup
+    if output_path.exists() {
+        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
+        fs::rename(&output_path, &backup_path)?;
+        println!("✅ Backup created successfully.");
+    }
+
+    // Write the Nginx configuration to `nginx.conf`
+    println!("📝 Writing the nginx configuration to {:?}", output_path);
+    let mut output_file = File::create(&output_path)?;
+    output_file.write_all(template.as_bytes())?;
+    println!("🎉 Nginx file generated successfully at {:?}", output_path);
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
+
+/// Renders the nginx configuration used to serve the Blazor WebAssembly build.
+///
+/// # Returns
+/// - `String`: The `nginx.conf` content.
+pub fn render_nginx_file_for_dotnet() -> String {
+    // Serve the pre-compressed `.br` files produced by `dotnet publish` when the browser accepts
+    // brotli, keeping the MIME type of the original file. `try_files` sets `$uri` to the file it
+    // serves, so `Content-Encoding` is only sent when the `.br` file exists
+    let framework_locations = BLAZOR_FRAMEWORK_TYPES
+        .iter()
+        .map(|(extension, mime_type)| {
+            format!(
+                r#"
+            location ~ \.{extension}$ {{
+                types {{ }}
+                default_type {mime_type};
+                add_header Content-Encoding $blazor_content_encoding;
+                add_header Vary Accept-Encoding;
+                add_header Cache-Control "no-cache";
+                try_files $uri$blazor_brotli_suffix $uri =404;
+            }}
+"#,
+                extension = extension,
+                mime_type = mime_type,
+            )
+        })
+        .collect::<String>();
+
+    // Base Nginx configuration template
+    format!(
+        r#"
+events {{}}
+
+http {{
+    include       /etc/nginx/mime.types;
+    default_type  application/octet-stream;
+
+    types {{
+        application/wasm  wasm;
+    }}
+
+    map $http_accept_encoding $blazor_brotli_suffix {{
+        default     "";
+        "~*\bbr\b"  ".br";
+    }}
+
+    map $uri $blazor_content_encoding {{
+        default     "";
+        "~\.br$"    "br";
+    }}
+
+    gzip on;
+    gzip_types text/css application/javascript application/json application/wasm application/octet-stream;
+
+    server {{
+        listen       80;
+
+        root   /usr/share/nginx/html;
+        index  index.html;
+
+        location / {{
+            try_files $uri $uri/ /index.html;
+        }}
+
+        location /_framework/ {{{framework_locations}        }}
+
+        error_page   500 502 503 504  /50x.html;
+        location = /50x.html {{
+            root   /usr/share/nginx/html;
+        }}
+    }}
+}}
+"#,
+        framework_locations = framework_locations,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_encoding_follows_the_served_file() {
+        let nginx = render_nginx_file_for_dotnet();
+
+        assert!(nginx.contains("map $uri $blazor_content_encoding {\n        default     \"\";\n        \"~\\.br$\"    \"br\";"));
+        assert!(nginx.contains("try_files $uri$blazor_brotli_suffix $uri =404;"));
+        assert_eq!(
+            nginx.matches("add_header Vary Accept-Encoding;").count(),
+            BLAZOR_FRAMEWORK_TYPES.len()
+        );
+    }
+}
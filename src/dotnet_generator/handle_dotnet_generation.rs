@@ -1,10 +1,16 @@
-use std::{io, thread};
+use std::{env, io, thread};
 
+use crate::ci_generator::blue_green::generate_blue_green_files;
+use crate::ci_generator::generate_compose_overrides::generate_compose_overrides;
+use crate::ci_generator::handle_ci_generation::handle_ci_generation;
+use crate::ci_generator::image_registry::generate_image_registry_files;
 use crate::constants::SLEEP_DURATION;
 use crate::dotnet_generator::generate_ansible_files_for_dotnet::generate_ansible_files_for_dotnet;
 use crate::dotnet_generator::generate_compose_file_for_dotnet::generate_compose_file_for_dotnet;
 use crate::dotnet_generator::generate_dockerfile_for_dotnet::generate_dockerfile_for_dotnet;
-use crate::dotnet_generator::generate_gitlab_cil_file_for_dotnet::generate_gitlab_cil_file_for_dotnet;
+use crate::dotnet_generator::generate_nginx_file_for_dotnet::generate_nginx_file_for_dotnet;
+use crate::kubernetes_generator::helm_chart::generate_helm_charts;
+use crate::shared::get_current_config::get_current_config;
 
 pub fn handle_dotnet_generation() -> io::Result<()> {
     println!("\n🚀 Starting .NET Generator...");
@@ -28,16 +34,50 @@ pub fn handle_dotnet_generation() -> io::Result<()> {
     }
     println!("✅ Docker Compose file generated successfully!");
 
-    println!("\n🔧 Generating GitLab CI file...");
+    println!("\n🔧 Generating environment overrides...");
     thread::sleep(SLEEP_DURATION); // Simulate progress
-    if let Err(e) = generate_gitlab_cil_file_for_dotnet() {
+    if let Err(e) = generate_compose_overrides() {
         eprintln!(
-            "❌ Error: Failed to generate GitLab CI file. Details: {}",
+            "❌ Error: Failed to generate environment overrides. Details: {}",
             e
         );
         std::process::exit(1);
     }
-    println!("✅ GitLab CI file generated successfully!");
+
+    println!("\n🔧 Generating blue/green deploy files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_blue_green_files() {
+        eprintln!(
+            "❌ Error: Failed to generate blue/green deploy files. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating image build files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_image_registry_files() {
+        eprintln!(
+            "❌ Error: Failed to generate image build files. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating Helm charts...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_helm_charts() {
+        eprintln!("❌ Error: Failed to generate Helm charts. Details: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating CI file...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = handle_ci_generation() {
+        eprintln!("❌ Error: Failed to generate CI file. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ CI file generated successfully!");
 
     println!("\n🔧 Copying Ansible files...");
     thread::sleep(SLEEP_DURATION); // Simulate progress
@@ -47,6 +87,18 @@ pub fn handle_dotnet_generation() -> io::Result<()> {
     }
     println!("✅ Ansible files copied successfully!");
 
+    // Blazor WebAssembly apps are served by nginx and need their own configuration
+    let config = get_current_config(env::current_dir()?);
+    if config["app_type"].as_str() == Some("blazor_wasm") {
+        println!("\n🔧 Generating Nginx file...");
+        thread::sleep(SLEEP_DURATION); // Simulate progress
+        if let Err(e) = generate_nginx_file_for_dotnet() {
+            eprintln!("❌ Error: Failed to generate Nginx file. Details: {}", e);
+            std::process::exit(1);
+        }
+        println!("✅ Nginx file generated successfully!");
+    }
+
     println!(
         "\n🎉 .NET Generator completed successfully! All required files have been generated.\n"
     );
@@ -1,5 +1,5 @@
 pub mod generate_dockerfile_for_dotnet;
 pub mod generate_compose_file_for_dotnet;
-pub mod generate_gitlab_cil_file_for_dotnet;
 pub mod generate_ansible_files_for_dotnet; 
+pub mod generate_nginx_file_for_dotnet;
 pub mod handle_dotnet_generation; 
\ No newline at end of file
@@ -0,0 +1,606 @@
+use std::{env, io, thread};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    ci_generator::{deploy_environment::ComposeOverrides, image_registry::ImageRegistrySettings},
+    constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::{
+        get_dotnet_container_port, get_worker_healthcheck_command,
+    },
+    kubernetes_generator::kubernetes_schema::KubernetesSchema,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+        yaml_helper::render_yaml,
+    },
+};
+
+/// The directory the manifests are written to.
+pub const KUBERNETES_DIRECTORY: &str = "k8s";
+
+/// The label selecting the pods of a service.
+const NAME_LABEL: &str = "app.kubernetes.io/name";
+
+/// The Ingress routing the configured host to the services, read from `kubernetes.ingress`.
+pub struct IngressSettings {
+    /// The name of the Ingress, which defaults to the first service it routes to.
+    pub name: String,
+    pub host: String,
+    /// The `ingressClassName`, or `None` for the default class of the cluster.
+    pub class_name: Option<String>,
+    /// The Secret holding the TLS certificate of the host, or `None` to serve plain HTTP.
+    pub tls_secret: Option<String>,
+}
+
+/// A service of the project as it runs on Kubernetes.
+pub struct KubernetesService {
+    /// The name of the Deployment, Service and containers, from `service_name`.
+    pub name: String,
+    /// The image without its tag, pulled from the image registry when one is configured.
+    pub image_repository: String,
+    pub image_tag: String,
+    /// The port the container listens on, or `None` for workers, which get no Service.
+    pub container_port: Option<u16>,
+    /// The port of the Service, from `port`.
+    pub port: u64,
+    pub replicas: u64,
+    /// The environment variables set by the project type, e.g. `NODE_ENV`.
+    pub env: Vec<(String, String)>,
+    /// The environment variables of the generated ConfigMap.
+    pub config_env: Vec<(String, String)>,
+    /// The existing Secrets whose keys become environment variables.
+    pub secrets: Vec<String>,
+    pub liveness_probe: Option<Value>,
+    pub readiness_probe: Option<Value>,
+    /// The `resources` of the container, passed through as configured.
+    pub resources: Option<Value>,
+    /// The horizontal autoscaling of the Helm chart, if enabled.
+    pub autoscaling: Option<AutoscalingSettings>,
+    /// The path the Ingress routes to the service, if any.
+    pub ingress_path: Option<String>,
+}
+
+impl KubernetesService {
+    /// Returns the image of the container, e.g. `registry.example.com/shop/shop-api:1.4.2`.
+    pub fn image(&self) -> String {
+        format!("{}:{}", self.image_repository, self.image_tag)
+    }
+}
+
+/// The horizontal autoscaling of a service, read from `autoscaling`.
+pub struct AutoscalingSettings {
+    pub min_replicas: u64,
+    pub max_replicas: u64,
+    /// The average CPU usage to scale at, in percent of the requested CPU.
+    pub target_cpu_utilization: u64,
+}
+
+impl AutoscalingSettings {
+    /// Reads the autoscaling of a service, if the `autoscaling` object is set.
+    fn from_value(value: &Value) -> io::Result<Option<AutoscalingSettings>> {
+        if value.is_null() {
+            return Ok(None);
+        }
+
+        let min_replicas = value["min_replicas"].as_u64().unwrap_or(1);
+        let max_replicas = value["max_replicas"].as_u64().unwrap_or(3);
+        if min_replicas == 0 || max_replicas < min_replicas {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "autoscaling needs 1 <= min_replicas <= max_replicas",
+            ));
+        }
+
+        Ok(Some(AutoscalingSettings {
+            min_replicas,
+            max_replicas,
+            target_cpu_utilization: value["target_cpu_utilization"].as_u64().unwrap_or(80),
+        }))
+    }
+}
+
+/// The Kubernetes settings of the project, read from the optional `kubernetes` section of the
+/// configuration.
+///
+/// `replicas`, `env` and `secrets` apply to every service. In monorepos, set them per service
+/// under `"services": { "<service_name>": { ... } }`, together with the `ingress_path`.
+pub struct KubernetesSettings {
+    /// The namespace of every resource, or `None` to use the namespace of the kubectl context.
+    pub namespace: Option<String>,
+    /// The Secret the cluster logs in to the image registry with.
+    pub image_pull_secret: Option<String>,
+    /// The `app.kubernetes.io/part-of` label, from `project_directory`.
+    pub part_of: Option<String>,
+    pub ingress: Option<IngressSettings>,
+    pub services: Vec<KubernetesService>,
+}
+
+impl KubernetesSettings {
+    /// Reads the Kubernetes settings and the services of the project.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<KubernetesSettings>`: The settings, or an error if a name is not a valid
+    ///   Kubernetes name or the Ingress routes no service.
+    pub fn from_config(config: &Value) -> io::Result<KubernetesSettings> {
+        let kubernetes = &config["kubernetes"];
+        let image_registry = ImageRegistrySettings::from_config(config)?;
+        let image_tag = kubernetes["image_tag"].as_str().unwrap_or("latest");
+        let defaults = ComposeOverrides::from_value(kubernetes);
+
+        let monorepo = config["project_type"].as_str() == Some("monorepo");
+        let service_configs = if monorepo {
+            get_monorepo_services(config)?.iter().collect()
+        } else {
+            vec![config]
+        };
+
+        let mut services = Vec::new();
+        for service in service_configs {
+            let name = service["service_name"]
+                .as_str()
+                .unwrap_or("default_service")
+                .to_string();
+            check_name("service_name", &name)?;
+
+            let service_settings = &kubernetes["services"][&name];
+            let overrides = ComposeOverrides::from_value(service_settings);
+
+            let image_name = service["image_name"].as_str().unwrap_or("default_image");
+            let image_repository = match &image_registry {
+                Some(image_registry) => format!("{}/{}", image_registry.url, image_name),
+                None => image_name.to_string(),
+            };
+
+            let project_type = service["project_type"].as_str().unwrap_or("dotnet");
+            let (container_port, env) = match project_type {
+                "angular" => (
+                    Some(80),
+                    vec![("NODE_ENV".to_string(), "production".to_string())],
+                ),
+                _ => (
+                    get_dotnet_container_port(service["app_type"].as_str().unwrap_or("web")),
+                    Vec::new(),
+                ),
+            };
+
+            // The service settings replace the shared ones with the same name
+            let mut config_env = defaults.env.clone();
+            config_env.retain(|(key, _)| !overrides.env.iter().any(|(other, _)| other == key));
+            config_env.extend(overrides.env);
+
+            let mut secrets = read_names(&kubernetes["secrets"], "kubernetes.secrets")?;
+            for secret in read_names(&service_settings["secrets"], "kubernetes.services.secrets")? {
+                if !secrets.contains(&secret) {
+                    secrets.push(secret);
+                }
+            }
+
+            // Single projects route the whole host to their service
+            let ingress_path = match service_settings["ingress_path"].as_str() {
+                Some(path) => Some(path.to_string()),
+                None if !monorepo => Some("/".to_string()),
+                None => None,
+            }
+            .filter(|_| container_port.is_some());
+
+            let (liveness_probe, readiness_probe) = render_probes(service, container_port)?;
+
+            let resources = match (&service_settings["resources"], &kubernetes["resources"]) {
+                (Value::Null, Value::Null) => None,
+                (Value::Null, resources) | (resources, _) if resources.is_object() => {
+                    Some(resources.clone())
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("The resources of {} must be an object", name),
+                    ))
+                }
+            };
+            let autoscaling = match &service_settings["autoscaling"] {
+                Value::Null => AutoscalingSettings::from_value(&kubernetes["autoscaling"])?,
+                autoscaling => AutoscalingSettings::from_value(autoscaling)?,
+            };
+
+            services.push(KubernetesService {
+                name,
+                image_repository,
+                image_tag: image_tag.to_string(),
+                container_port,
+                port: service["port"].as_u64().unwrap_or(5000),
+                replicas: overrides.replicas.or(defaults.replicas).unwrap_or(1),
+                env,
+                config_env,
+                secrets,
+                liveness_probe,
+                readiness_probe,
+                resources,
+                autoscaling,
+                ingress_path,
+            });
+        }
+
+        let ingress = match &kubernetes["ingress"] {
+            Value::Null => None,
+            ingress => {
+                let host = ingress["host"].as_str().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Missing kubernetes.ingress.host",
+                    )
+                })?;
+                let first_service = services
+                    .iter()
+                    .find(|service| service.ingress_path.is_some())
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "The Ingress routes no service listening on a port (set ingress_path under kubernetes.services)",
+                        )
+                    })?;
+                let name = ingress["name"].as_str().unwrap_or(&first_service.name);
+                check_name("kubernetes.ingress.name", name)?;
+                Some(IngressSettings {
+                    name: name.to_string(),
+                    host: host.to_string(),
+                    class_name: ingress["class_name"].as_str().map(str::to_string),
+                    tls_secret: ingress["tls_secret"].as_str().map(str::to_string),
+                })
+            }
+        };
+
+        let namespace = kubernetes["namespace"].as_str().map(str::to_string);
+        if let Some(namespace) = &namespace {
+            check_name("kubernetes.namespace", namespace)?;
+        }
+
+        Ok(KubernetesSettings {
+            namespace,
+            image_pull_secret: kubernetes["image_pull_secret"].as_str().map(str::to_string),
+            part_of: config["project_directory"].as_str().map(str::to_string),
+            ingress,
+            services,
+        })
+    }
+
+    /// Renders the manifests of a service: its ConfigMap if it has environment variables, its
+    /// Deployment, and its Service if it listens on a port.
+    ///
+    /// # Parameters
+    /// - `service`: The service.
+    ///
+    /// # Returns
+    /// - `Vec<Value>`: The manifests, in the order `kubectl apply` should create them.
+    pub fn render_service_manifests(&self, service: &KubernetesService) -> Vec<Value> {
+        let mut manifests = Vec::new();
+        let labels = self.labels(service);
+        let config_map_name = format!("{}-config", service.name);
+
+        if !service.config_env.is_empty() {
+            manifests.push(json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": self.metadata(&config_map_name, &labels),
+                "data": to_map(&service.config_env),
+            }));
+        }
+
+        let mut container = Map::new();
+        container.insert("name".to_string(), json!(service.name));
+        container.insert("image".to_string(), json!(service.image()));
+        if let Some(container_port) = service.container_port {
+            container.insert(
+                "ports".to_string(),
+                json!([{ "name": "http", "containerPort": container_port, "protocol": "TCP" }]),
+            );
+        }
+        if !service.env.is_empty() {
+            container.insert(
+                "env".to_string(),
+                service
+                    .env
+                    .iter()
+                    .map(|(name, value)| json!({ "name": name, "value": value }))
+                    .collect(),
+            );
+        }
+        let mut env_from = Vec::new();
+        if !service.config_env.is_empty() {
+            env_from.push(json!({ "configMapRef": { "name": config_map_name } }));
+        }
+        env_from.extend(
+            service
+                .secrets
+                .iter()
+                .map(|secret| json!({ "secretRef": { "name": secret } })),
+        );
+        if !env_from.is_empty() {
+            container.insert("envFrom".to_string(), Value::Array(env_from));
+        }
+        if let Some(probe) = &service.liveness_probe {
+            container.insert("livenessProbe".to_string(), probe.clone());
+        }
+        if let Some(probe) = &service.readiness_probe {
+            container.insert("readinessProbe".to_string(), probe.clone());
+        }
+        if let Some(resources) = &service.resources {
+            container.insert("resources".to_string(), resources.clone());
+        }
+
+        let mut pod_spec = Map::new();
+        pod_spec.insert("containers".to_string(), json!([container]));
+        if let Some(image_pull_secret) = &self.image_pull_secret {
+            pod_spec.insert(
+                "imagePullSecrets".to_string(),
+                json!([{ "name": image_pull_secret }]),
+            );
+        }
+
+        manifests.push(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": self.metadata(&service.name, &labels),
+            "spec": {
+                "replicas": service.replicas,
+                "selector": { "matchLabels": { NAME_LABEL: service.name } },
+                "template": {
+                    "metadata": { "labels": labels },
+                    "spec": pod_spec,
+                },
+            },
+        }));
+
+        if service.container_port.is_some() {
+            manifests.push(json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": self.metadata(&service.name, &labels),
+                "spec": {
+                    "type": "ClusterIP",
+                    "selector": { NAME_LABEL: service.name },
+                    "ports": [{ "name": "http", "port": service.port, "targetPort": "http", "protocol": "TCP" }],
+                },
+            }));
+        }
+
+        manifests
+    }
+
+    /// Renders the Ingress routing the configured host to the services with an `ingress_path`.
+    ///
+    /// # Returns
+    /// - `Option<Value>`: The Ingress, or `None` without `kubernetes.ingress`.
+    pub fn render_ingress(&self) -> Option<Value> {
+        let ingress = self.ingress.as_ref()?;
+        let paths = self
+            .services
+            .iter()
+            .filter_map(|service| {
+                service.ingress_path.as_ref().map(|path| {
+                    json!({
+                        "path": path,
+                        "pathType": "Prefix",
+                        "backend": {
+                            "service": { "name": service.name, "port": { "name": "http" } },
+                        },
+                    })
+                })
+            })
+            .collect::<Vec<Value>>();
+
+        let mut spec = Map::new();
+        if let Some(class_name) = &ingress.class_name {
+            spec.insert("ingressClassName".to_string(), json!(class_name));
+        }
+        if let Some(tls_secret) = &ingress.tls_secret {
+            spec.insert(
+                "tls".to_string(),
+                json!([{ "hosts": [ingress.host], "secretName": tls_secret }]),
+            );
+        }
+        spec.insert(
+            "rules".to_string(),
+            json!([{ "host": ingress.host, "http": { "paths": paths } }]),
+        );
+
+        let mut labels = Map::new();
+        if let Some(part_of) = &self.part_of {
+            labels.insert("app.kubernetes.io/part-of".to_string(), json!(part_of));
+        }
+        labels.insert(
+            "app.kubernetes.io/managed-by".to_string(),
+            json!("fileforge"),
+        );
+
+        Some(json!({
+            "apiVersion": "networking.k8s.io/v1",
+            "kind": "Ingress",
+            "metadata": self.metadata(&ingress.name, &Value::Object(labels)),
+            "spec": spec,
+        }))
+    }
+
+    /// Returns the labels of the resources of a service.
+    fn labels(&self, service: &KubernetesService) -> Value {
+        let mut labels = Map::new();
+        labels.insert(NAME_LABEL.to_string(), json!(service.name));
+        if let Some(part_of) = &self.part_of {
+            labels.insert("app.kubernetes.io/part-of".to_string(), json!(part_of));
+        }
+        labels.insert(
+            "app.kubernetes.io/managed-by".to_string(),
+            json!("fileforge"),
+        );
+        Value::Object(labels)
+    }
+
+    /// Returns the metadata of a resource, in the configured namespace.
+    fn metadata(&self, name: &str, labels: &Value) -> Value {
+        let mut metadata = Map::new();
+        metadata.insert("name".to_string(), json!(name));
+        if let Some(namespace) = &self.namespace {
+            metadata.insert("namespace".to_string(), json!(namespace));
+        }
+        metadata.insert("labels".to_string(), labels.clone());
+        Value::Object(metadata)
+    }
+}
+
+/// Generates the Kubernetes manifests into the `k8s` directory: a `<service>.yaml` file per
+/// service and an `ingress.yaml` file when `kubernetes.ingress` is set.
+///
+/// Every manifest is validated against the bundled Kubernetes OpenAPI schema before anything is
+/// written.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid, a manifest does not match the
+///   schema or a file cannot be written.
+pub fn generate_kubernetes_manifests() -> io::Result<()> {
+    println!("🚀 Starting Kubernetes manifest generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let settings = KubernetesSettings::from_config(&config)?;
+    let schema = KubernetesSchema::load()?;
+
+    let mut files = Vec::new();
+    for service in &settings.services {
+        files.push((
+            format!("{}.yaml", service.name),
+            settings.render_service_manifests(service),
+        ));
+    }
+    if let Some(ingress) = settings.render_ingress() {
+        files.push(("ingress.yaml".to_string(), vec![ingress]));
+    }
+
+    println!("🔍 Validating the manifests against the Kubernetes schema...");
+    thread::sleep(SLEEP_DURATION);
+    for manifest in files.iter().flat_map(|(_, manifests)| manifests) {
+        schema.validate(manifest)?;
+    }
+    println!("✅ The manifests match the Kubernetes schema.");
+
+    let output_dir = get_output_directory(&current_dir).join(KUBERNETES_DIRECTORY);
+    for (file_name, manifests) in &files {
+        let content = manifests
+            .iter()
+            .map(render_yaml)
+            .collect::<Vec<String>>()
+            .join("---\n");
+        write_file_with_backup(&output_dir.join(file_name), &content)?;
+    }
+
+    println!("🎉 Generated the Kubernetes manifests in {:?}", output_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
+
+/// Renders the liveness and readiness probes of a service from its healthcheck settings.
+///
+/// The probes run the same check as the compose healthcheck, with the same timings for the
+/// liveness probe. Workers only get a liveness probe, since they receive no traffic.
+///
+/// # Parameters
+/// - `service`: The service configuration.
+/// - `container_port`: The port the container listens on, if any.
+///
+/// # Returns
+/// - `io::Result<(Option<Value>, Option<Value>)>`: The liveness and readiness probes, or `None`
+///   when `enable_healthcheck` is off, or an error if an `exec` healthcheck has no command.
+fn render_probes(
+    service: &Value,
+    container_port: Option<u16>,
+) -> io::Result<(Option<Value>, Option<Value>)> {
+    if !service["enable_healthcheck"].as_bool().unwrap_or(false) {
+        return Ok((None, None));
+    }
+
+    let app_type = service["app_type"].as_str().unwrap_or("web");
+    let check = match (service["project_type"].as_str(), app_type, container_port) {
+        (Some("angular"), _, _) | (_, "blazor_wasm", _) => {
+            json!({ "httpGet": { "path": "/", "port": "http" } })
+        }
+        (_, _, Some(_)) => json!({ "httpGet": { "path": "/health", "port": "http" } }),
+        _ => json!({
+            "exec": { "command": ["sh", "-c", get_worker_healthcheck_command(service)?] }
+        }),
+    };
+
+    let mut liveness_probe = check.clone();
+    liveness_probe["initialDelaySeconds"] = json!(60);
+    liveness_probe["periodSeconds"] = json!(40);
+    liveness_probe["timeoutSeconds"] = json!(30);
+    liveness_probe["failureThreshold"] = json!(3);
+
+    let readiness_probe = container_port.map(|_| {
+        let mut readiness_probe = check;
+        readiness_probe["periodSeconds"] = json!(10);
+        readiness_probe["timeoutSeconds"] = json!(5);
+        readiness_probe["failureThreshold"] = json!(3);
+        readiness_probe
+    });
+
+    Ok((Some(liveness_probe), readiness_probe))
+}
+
+/// Reads a list of Kubernetes resource names, e.g. the Secrets of a service.
+fn read_names(value: &Value, setting: &str) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for name in value.as_array().into_iter().flatten() {
+        let name = name.as_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} must list names", setting),
+            )
+        })?;
+        check_name(setting, name)?;
+        names.push(name.to_string());
+    }
+    Ok(names)
+}
+
+/// Checks that a name is a valid DNS label, which Kubernetes requires for Services and
+/// namespaces: at most 63 lowercase letters, digits and dashes, starting and ending with a letter
+/// or digit.
+fn check_name(setting: &str, name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} {:?} is not a valid Kubernetes name (use lowercase letters, digits and dashes)",
+                setting, name
+            ),
+        ))
+    }
+}
+
+/// Converts environment variables to the `data` of a ConfigMap.
+pub fn to_map(env: &[(String, String)]) -> Value {
+    Value::Object(
+        env.iter()
+            .map(|(key, value)| (key.clone(), json!(value)))
+            .collect(),
+    )
+}
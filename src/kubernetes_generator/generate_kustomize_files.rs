@@ -0,0 +1,249 @@
+use std::{env, io, thread};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    ci_generator::deploy_environment::get_deploy_environments,
+    constants::SLEEP_DURATION,
+    kubernetes_generator::{
+        generate_kubernetes_manifests::{KubernetesSettings, KUBERNETES_DIRECTORY},
+        kubernetes_schema::KubernetesSchema,
+    },
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+        yaml_helper::render_yaml,
+    },
+};
+
+/// The `apiVersion` of the generated `kustomization.yaml` files.
+const KUSTOMIZATION_API_VERSION: &str = "kustomize.config.k8s.io/v1beta1";
+
+/// What the overlay of an environment changes besides its compose overrides, read from
+/// `kubernetes.environments.<name>`.
+struct OverlaySettings {
+    /// The tag of the images, replacing `kubernetes.image_tag`.
+    image_tag: Option<String>,
+    /// The host of the Ingress, replacing `kubernetes.ingress.host`.
+    ingress_host: Option<String>,
+}
+
+impl OverlaySettings {
+    fn from_value(value: &Value) -> OverlaySettings {
+        OverlaySettings {
+            image_tag: value["image_tag"].as_str().map(str::to_string),
+            ingress_host: value["ingress_host"].as_str().map(str::to_string),
+        }
+    }
+}
+
+/// Renders a `kustomization.yaml` from its fields, leaving out the empty lists.
+fn render_kustomization(fields: Vec<(&str, Vec<Value>)>) -> String {
+    let mut kustomization = Map::new();
+    kustomization.insert("apiVersion".to_string(), json!(KUSTOMIZATION_API_VERSION));
+    kustomization.insert("kind".to_string(), json!("Kustomization"));
+    for (field, items) in fields {
+        if !items.is_empty() {
+            kustomization.insert(field.to_string(), Value::Array(items));
+        }
+    }
+    render_yaml(&Value::Object(kustomization))
+}
+
+/// Generates a Kustomize base and overlays into the `k8s` directory: `k8s/base` holds the
+/// manifests of `--target kubernetes`, and `k8s/overlays/<name>` patches them for every
+/// configured environment.
+///
+/// An overlay sets the image tag and Ingress host of `kubernetes.environments.<name>`, and the
+/// `replicas` and `env` overrides of the environment.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid, a manifest does not match the
+///   schema or a file cannot be written.
+pub fn generate_kustomize_files() -> io::Result<()> {
+    println!("🚀 Starting Kustomize base and overlays generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let settings = KubernetesSettings::from_config(&config)?;
+    let schema = KubernetesSchema::load()?;
+    let environments = get_deploy_environments(&config)?
+        .into_iter()
+        .filter(|environment| environment.configured)
+        .collect::<Vec<_>>();
+
+    let overlay_settings = config["kubernetes"]["environments"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    if let Some(name) = overlay_settings.keys().find(|name| {
+        !environments
+            .iter()
+            .any(|environment| environment.name == **name)
+    }) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "kubernetes.environments.{} is not one of the configured environments",
+                name
+            ),
+        ));
+    }
+
+    let mut base_files = Vec::new();
+    for service in &settings.services {
+        base_files.push((
+            format!("{}.yaml", service.name),
+            settings.render_service_manifests(service),
+        ));
+    }
+    let ingress = settings.render_ingress();
+    if let Some(ingress) = &ingress {
+        base_files.push(("ingress.yaml".to_string(), vec![ingress.clone()]));
+    }
+
+    println!("🔍 Validating the manifests against the Kubernetes schema...");
+    thread::sleep(SLEEP_DURATION);
+    for manifest in base_files.iter().flat_map(|(_, manifests)| manifests) {
+        schema.validate(manifest)?;
+    }
+    println!("✅ The manifests match the Kubernetes schema.");
+
+    let output_dir = get_output_directory(&current_dir).join(KUBERNETES_DIRECTORY);
+    let base_dir = output_dir.join("base");
+    for (file_name, manifests) in &base_files {
+        let content = manifests
+            .iter()
+            .map(render_yaml)
+            .collect::<Vec<String>>()
+            .join("---\n");
+        write_file_with_backup(&base_dir.join(file_name), &content)?;
+    }
+    write_file_with_backup(
+        &base_dir.join("kustomization.yaml"),
+        &render_kustomization(vec![(
+            "resources",
+            base_files
+                .iter()
+                .map(|(file_name, _)| json!(file_name))
+                .collect(),
+        )]),
+    )?;
+
+    // Patches find their resource by name and namespace
+    let metadata = |name: &str| {
+        let mut metadata = Map::new();
+        metadata.insert("name".to_string(), json!(name));
+        if let Some(namespace) = &settings.namespace {
+            metadata.insert("namespace".to_string(), json!(namespace));
+        }
+        Value::Object(metadata)
+    };
+
+    let single_service = settings.services.len() == 1;
+    for environment in &environments {
+        println!("🧩 Writing the overlay of {}...", environment.name);
+        thread::sleep(SLEEP_DURATION);
+
+        let overlay = OverlaySettings::from_value(
+            overlay_settings
+                .get(&environment.name)
+                .unwrap_or(&Value::Null),
+        );
+        let overlay_dir = output_dir.join("overlays").join(&environment.name);
+
+        let images = match &overlay.image_tag {
+            Some(image_tag) => settings
+                .services
+                .iter()
+                .map(|service| json!({ "name": service.image_repository, "newTag": image_tag }))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let replicas = settings
+            .services
+            .iter()
+            .filter_map(|service| {
+                environment
+                    .replicas_override(&service.name, single_service)
+                    .map(|count| json!({ "name": service.name, "count": count }))
+            })
+            .collect();
+
+        // Variables set on the container take precedence over the ConfigMap of the base
+        let mut patches = Vec::new();
+        for service in &settings.services {
+            let env = environment.env_overrides(&service.name);
+            if env.is_empty() {
+                continue;
+            }
+
+            let patch = json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": metadata(&service.name),
+                "spec": {
+                    "template": {
+                        "spec": {
+                            "containers": [{
+                                "name": service.name,
+                                "env": env
+                                    .iter()
+                                    .map(|(name, value)| json!({ "name": name, "value": value }))
+                                    .collect::<Vec<Value>>(),
+                            }],
+                        },
+                    },
+                },
+            });
+            let file_name = format!("{}.yaml", service.name);
+            write_file_with_backup(&overlay_dir.join(&file_name), &render_yaml(&patch))?;
+            patches.push(json!({ "path": file_name }));
+        }
+
+        // Lists are replaced as a whole, so the patch repeats the rules with the new host
+        if let Some(ingress_host) = &overlay.ingress_host {
+            let mut patch = ingress.clone().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "kubernetes.environments.{}.ingress_host needs kubernetes.ingress",
+                        environment.name
+                    ),
+                )
+            })?;
+            patch["spec"]["rules"][0]["host"] = json!(ingress_host);
+            if patch["spec"]["tls"].is_array() {
+                patch["spec"]["tls"][0]["hosts"] = json!([ingress_host]);
+            }
+            schema.validate(&patch)?;
+            write_file_with_backup(&overlay_dir.join("ingress.yaml"), &render_yaml(&patch))?;
+            patches.push(json!({ "path": "ingress.yaml" }));
+        }
+
+        write_file_with_backup(
+            &overlay_dir.join("kustomization.yaml"),
+            &render_kustomization(vec![
+                ("resources", vec![json!("../../base")]),
+                ("images", images),
+                ("replicas", replicas),
+                ("patches", patches),
+            ]),
+        )?;
+    }
+
+    println!(
+        "🎉 Generated the Kustomize base and {} overlay(s) in {:?}",
+        environments.len(),
+        output_dir
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use std::{io, thread};
+
+use crate::constants::SLEEP_DURATION;
+use crate::kubernetes_generator::generate_kubernetes_manifests::generate_kubernetes_manifests;
+
+pub fn handle_kubernetes_generation() -> io::Result<()> {
+    println!("\n🚀 Starting Kubernetes Generator...");
+
+    println!("\n🔧 Generating Kubernetes manifests...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_kubernetes_manifests() {
+        eprintln!(
+            "❌ Error: Failed to generate Kubernetes manifests. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+    println!("✅ Kubernetes manifests generated successfully!");
+
+    println!(
+        "\n🎉 Kubernetes Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
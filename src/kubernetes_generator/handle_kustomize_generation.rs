@@ -0,0 +1,25 @@
+use std::{io, thread};
+
+use crate::constants::SLEEP_DURATION;
+use crate::kubernetes_generator::generate_kustomize_files::generate_kustomize_files;
+
+pub fn handle_kustomize_generation() -> io::Result<()> {
+    println!("\n🚀 Starting Kustomize Generator...");
+
+    println!("\n🔧 Generating Kustomize base and overlays...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_kustomize_files() {
+        eprintln!(
+            "❌ Error: Failed to generate Kustomize base and overlays. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+    println!("✅ Kustomize base and overlays generated successfully!");
+
+    println!(
+        "\n🎉 Kustomize Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,512 @@
+use std::{env, io, thread};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    ci_generator::deploy_environment::{get_deploy_environments, DeployEnvironment},
+    constants::SLEEP_DURATION,
+    kubernetes_generator::generate_kubernetes_manifests::{
+        to_map, KubernetesService, KubernetesSettings,
+    },
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, make_executable, write_file_with_backup},
+        yaml_helper::render_yaml,
+    },
+};
+
+/// The directory the charts are written to.
+pub const CHART_DIRECTORY: &str = "chart";
+
+/// The script the helm jobs run to install or upgrade the releases of an environment.
+pub const HELM_UPGRADE_SCRIPT: &str = "deploy/helm-upgrade.sh";
+
+/// The variable the helm jobs pass the kubeconfig of the environment's cluster in.
+pub const KUBECONFIG_CONTENT_VARIABLE: &str = "KUBECONFIG_CONTENT";
+
+/// A chart of the project, installed as the release of the same name.
+pub struct HelmChart {
+    /// The name of the chart and its release, the service name.
+    pub name: String,
+    /// The directory of the chart, e.g. `chart` or `chart/shop-api` in a monorepo.
+    pub directory: String,
+}
+
+/// The Helm deploy, read from the optional `helm` section of the configuration.
+///
+/// With Helm, the CI pipeline builds and pushes the images, then runs `helm upgrade --install`
+/// against the cluster of each environment instead of deploying the compose files with Ansible.
+pub struct HelmSettings {
+    /// How long `helm upgrade --atomic` waits for the release to become ready, e.g. `5m`.
+    pub timeout: String,
+    /// The namespace the releases are installed in, from `kubernetes.namespace`.
+    pub namespace: Option<String>,
+    pub charts: Vec<HelmChart>,
+}
+
+impl HelmSettings {
+    /// Reads the Helm settings, if the `helm` section is present.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<Option<HelmSettings>>`: The settings, `None` to deploy with Ansible, or an
+    ///   error if a setting is invalid, no image registry is configured or the deploy is combined
+    ///   with a role, releases or blue/green deploys.
+    pub fn from_config(config: &Value) -> io::Result<Option<HelmSettings>> {
+        let helm = &config["helm"];
+        if helm.is_null() || helm.as_bool() == Some(false) {
+            return Ok(None);
+        }
+
+        if config["image_registry"].is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "helm needs an image_registry the cluster pulls the images from",
+            ));
+        }
+        let ansible = &config["ansible"];
+        if ansible["role"].as_bool().unwrap_or(false)
+            || !ansible["keep_releases"].is_null()
+            || !config["blue_green"].is_null()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "helm cannot be combined with ansible.role, ansible.keep_releases or blue_green",
+            ));
+        }
+
+        let timeout = helm["timeout"].as_str().unwrap_or("5m");
+        let valid_timeout = timeout
+            .strip_suffix(['s', 'm', 'h'])
+            .is_some_and(|amount| !amount.is_empty() && amount.chars().all(|c| c.is_ascii_digit()));
+        if !valid_timeout {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid helm.timeout: {} (use a duration like 90s or 5m)",
+                    timeout
+                ),
+            ));
+        }
+
+        let kubernetes = KubernetesSettings::from_config(config)?;
+        let single_service = kubernetes.services.len() == 1;
+        let charts = kubernetes
+            .services
+            .iter()
+            .map(|service| HelmChart {
+                name: service.name.clone(),
+                directory: if single_service {
+                    CHART_DIRECTORY.to_string()
+                } else {
+                    format!("{}/{}", CHART_DIRECTORY, service.name)
+                },
+            })
+            .collect();
+
+        Ok(Some(HelmSettings {
+            timeout: timeout.to_string(),
+            namespace: kubernetes.namespace,
+            charts,
+        }))
+    }
+
+    /// Returns the command linting every chart, for the validation jobs.
+    pub fn lint_command(&self) -> String {
+        format!(
+            "helm lint {}",
+            self.charts
+                .iter()
+                .map(|chart| chart.directory.as_str())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        )
+    }
+}
+
+/// Returns the command a helm job runs to deploy an environment.
+pub fn helm_upgrade_command(environment: &DeployEnvironment) -> String {
+    format!("sh {} {}", HELM_UPGRADE_SCRIPT, environment.name)
+}
+
+const HELPERS_TEMPLATE: &str = r#"{{/*
+The labels of every resource of the chart.
+*/}}
+{{- define "[[ chart ]].labels" -}}
+helm.sh/chart: {{ .Chart.Name }}-{{ .Chart.Version }}
+{{ include "[[ chart ]].selectorLabels" . }}
+app.kubernetes.io/version: {{ .Chart.AppVersion | quote }}
+app.kubernetes.io/managed-by: {{ .Release.Service }}
+{{- end }}
+
+{{/*
+The labels selecting the pods of the release.
+*/}}
+{{- define "[[ chart ]].selectorLabels" -}}
+app.kubernetes.io/name: {{ .Chart.Name }}
+app.kubernetes.io/instance: {{ .Release.Name }}
+{{- end }}
+"#;
+
+const DEPLOYMENT_TEMPLATE: &str = r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: {{ .Release.Name }}
+  labels:
+    {{- include "[[ chart ]].labels" . | nindent 4 }}
+spec:
+  {{- if not .Values.autoscaling.enabled }}
+  replicas: {{ .Values.replicaCount }}
+  {{- end }}
+  selector:
+    matchLabels:
+      {{- include "[[ chart ]].selectorLabels" . | nindent 6 }}
+  template:
+    metadata:
+      annotations:
+        # Restarts the pods when the environment variables change
+        checksum/config: {{ include (print $.Template.BasePath "/configmap.yaml") . | sha256sum }}
+      labels:
+        {{- include "[[ chart ]].selectorLabels" . | nindent 8 }}
+    spec:
+      {{- with .Values.imagePullSecrets }}
+      imagePullSecrets:
+        {{- toYaml . | nindent 8 }}
+      {{- end }}
+      containers:
+        - name: {{ .Chart.Name }}
+          image: "{{ .Values.image.repository }}:{{ .Values.image.tag }}"
+          imagePullPolicy: {{ .Values.image.pullPolicy }}
+          {{- with .Values.containerPort }}
+          ports:
+            - name: http
+              containerPort: {{ . }}
+              protocol: TCP
+          {{- end }}
+          {{- if or .Values.env .Values.secrets }}
+          envFrom:
+            {{- if .Values.env }}
+            - configMapRef:
+                name: {{ .Release.Name }}-config
+            {{- end }}
+            {{- range .Values.secrets }}
+            - secretRef:
+                name: {{ . }}
+            {{- end }}
+          {{- end }}
+          {{- with .Values.livenessProbe }}
+          livenessProbe:
+            {{- toYaml . | nindent 12 }}
+          {{- end }}
+          {{- with .Values.readinessProbe }}
+          readinessProbe:
+            {{- toYaml . | nindent 12 }}
+          {{- end }}
+          {{- with .Values.resources }}
+          resources:
+            {{- toYaml . | nindent 12 }}
+          {{- end }}
+"#;
+
+const CONFIG_MAP_TEMPLATE: &str = r#"{{- if .Values.env }}
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: {{ .Release.Name }}-config
+  labels:
+    {{- include "[[ chart ]].labels" . | nindent 4 }}
+data:
+  {{- range $key, $value := .Values.env }}
+  {{ $key }}: {{ $value | quote }}
+  {{- end }}
+{{- end }}
+"#;
+
+const SERVICE_TEMPLATE: &str = r#"{{- if .Values.containerPort }}
+apiVersion: v1
+kind: Service
+metadata:
+  name: {{ .Release.Name }}
+  labels:
+    {{- include "[[ chart ]].labels" . | nindent 4 }}
+spec:
+  type: {{ .Values.service.type }}
+  selector:
+    {{- include "[[ chart ]].selectorLabels" . | nindent 4 }}
+  ports:
+    - name: http
+      port: {{ .Values.service.port }}
+      targetPort: http
+      protocol: TCP
+{{- end }}
+"#;
+
+const INGRESS_TEMPLATE: &str = r#"{{- if .Values.ingress.enabled }}
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: {{ .Release.Name }}
+  labels:
+    {{- include "[[ chart ]].labels" . | nindent 4 }}
+spec:
+  {{- with .Values.ingress.className }}
+  ingressClassName: {{ . }}
+  {{- end }}
+  {{- with .Values.ingress.tlsSecret }}
+  tls:
+    - hosts:
+        - {{ $.Values.ingress.host | quote }}
+      secretName: {{ . }}
+  {{- end }}
+  rules:
+    - host: {{ .Values.ingress.host | quote }}
+      http:
+        paths:
+          - path: {{ .Values.ingress.path }}
+            pathType: Prefix
+            backend:
+              service:
+                name: {{ .Release.Name }}
+                port:
+                  name: http
+{{- end }}
+"#;
+
+const HPA_TEMPLATE: &str = r#"{{- if .Values.autoscaling.enabled }}
+apiVersion: autoscaling/v2
+kind: HorizontalPodAutoscaler
+metadata:
+  name: {{ .Release.Name }}
+  labels:
+    {{- include "[[ chart ]].labels" . | nindent 4 }}
+spec:
+  scaleTargetRef:
+    apiVersion: apps/v1
+    kind: Deployment
+    name: {{ .Release.Name }}
+  minReplicas: {{ .Values.autoscaling.minReplicas }}
+  maxReplicas: {{ .Values.autoscaling.maxReplicas }}
+  metrics:
+    - type: Resource
+      resource:
+        name: cpu
+        target:
+          type: Utilization
+          averageUtilization: {{ .Values.autoscaling.targetCPUUtilizationPercentage }}
+{{- end }}
+"#;
+
+const HELM_UPGRADE: &str = r#"#!/bin/sh
+# Installs or upgrades the Helm releases of an environment, generated by fileforge.
+#
+# Usage: sh deploy/helm-upgrade.sh <environment>
+#
+# The kubeconfig of the cluster is read from KUBECONFIG_CONTENT when it is set. The releases run
+# the images tagged with IMAGE_TAG, the commit SHA by default, and are rolled back when they do
+# not become ready within [[ timeout ]].
+set -eu
+
+environment="${1:?Usage: sh deploy/helm-upgrade.sh <environment>}"
+IMAGE_TAG="${IMAGE_TAG:-$(git rev-parse HEAD)}"
+
+if ! command -v helm > /dev/null 2>&1; then
+  echo "Installing Helm..."
+  curl -fsSL https://raw.githubusercontent.com/helm/helm/main/scripts/get-helm-3 | sh
+fi
+
+if [ -n "${KUBECONFIG_CONTENT:-}" ]; then
+  KUBECONFIG="$(mktemp)"
+  export KUBECONFIG
+  trap 'rm -f "$KUBECONFIG"' EXIT
+  printf '%s\n' "$KUBECONFIG_CONTENT" > "$KUBECONFIG"
+fi
+
+upgrade() {
+  release="$1"
+  chart="$2"
+  set -- "$release" "$chart" --install --atomic --timeout [[ timeout ]][[ namespace ]] --set-string image.tag="$IMAGE_TAG"
+  if [ -f "$chart/values-$environment.yaml" ]; then
+    set -- "$@" -f "$chart/values-$environment.yaml"
+  fi
+  echo "Upgrading $release in $environment..."
+  helm upgrade "$@"
+}
+
+[[ upgrades ]]"#;
+
+/// Renders the default values of a chart from the Kubernetes settings of its service.
+fn render_values(settings: &KubernetesSettings, service: &KubernetesService) -> Value {
+    let mut env = Map::new();
+    for (key, value) in service.env.iter().chain(&service.config_env) {
+        env.insert(key.clone(), json!(value));
+    }
+
+    let ingress = match (&settings.ingress, &service.ingress_path) {
+        (Some(ingress), Some(path)) => json!({
+            "enabled": true,
+            "className": ingress.class_name,
+            "host": ingress.host,
+            "path": path,
+            "tlsSecret": ingress.tls_secret,
+        }),
+        _ => json!({ "enabled": false }),
+    };
+
+    let autoscaling = match &service.autoscaling {
+        Some(autoscaling) => json!({
+            "enabled": true,
+            "minReplicas": autoscaling.min_replicas,
+            "maxReplicas": autoscaling.max_replicas,
+            "targetCPUUtilizationPercentage": autoscaling.target_cpu_utilization,
+        }),
+        None => json!({ "enabled": false }),
+    };
+
+    json!({
+        "replicaCount": service.replicas,
+        "image": {
+            "repository": service.image_repository,
+            "tag": service.image_tag,
+            "pullPolicy": "IfNotPresent",
+        },
+        "imagePullSecrets": settings
+            .image_pull_secret
+            .iter()
+            .map(|secret| json!({ "name": secret }))
+            .collect::<Vec<Value>>(),
+        "containerPort": service.container_port,
+        "service": { "type": "ClusterIP", "port": service.port },
+        "env": env,
+        "secrets": service.secrets,
+        "livenessProbe": service.liveness_probe,
+        "readinessProbe": service.readiness_probe,
+        "resources": service.resources.clone().unwrap_or_else(|| json!({})),
+        "ingress": ingress,
+        "autoscaling": autoscaling,
+    })
+}
+
+/// Renders the values an environment overrides for a chart, like the compose override files.
+///
+/// The environment wide port and replica count only apply to projects with a single service.
+fn render_environment_values(
+    environment: &DeployEnvironment,
+    service: &KubernetesService,
+    single_service: bool,
+) -> Value {
+    let mut values = Map::new();
+    if let Some(replicas) = environment.replicas_override(&service.name, single_service) {
+        values.insert("replicaCount".to_string(), json!(replicas));
+    }
+    if let Some(port) = environment
+        .service_overrides
+        .iter()
+        .find(|(name, _)| *name == service.name)
+        .and_then(|(_, overrides)| overrides.port)
+        .or(environment.overrides.port.filter(|_| single_service))
+    {
+        values.insert("service".to_string(), json!({ "port": port }));
+    }
+
+    let env = environment.env_overrides(&service.name);
+    if !env.is_empty() {
+        values.insert("env".to_string(), to_map(&env));
+    }
+
+    Value::Object(values)
+}
+
+/// Generates the Helm charts when the `helm` section is configured: a chart per service with
+/// its default values, a values file per environment, and the `deploy/helm-upgrade.sh` script
+/// the CI jobs run.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn generate_helm_charts() -> io::Result<()> {
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+
+    let config = get_current_config(current_dir.clone());
+    let helm = match HelmSettings::from_config(&config)? {
+        Some(helm) => helm,
+        None => return Ok(()),
+    };
+    let settings = KubernetesSettings::from_config(&config)?;
+    let environments = get_deploy_environments(&config)?;
+    let output_dir = get_output_directory(&current_dir);
+    let single_service = settings.services.len() == 1;
+
+    for (chart, service) in helm.charts.iter().zip(&settings.services) {
+        println!("⎈ Writing the Helm chart of {}...", chart.name);
+        thread::sleep(SLEEP_DURATION);
+
+        let chart_dir = output_dir.join(&chart.directory);
+        write_file_with_backup(
+            &chart_dir.join("Chart.yaml"),
+            &render_yaml(&json!({
+                "apiVersion": "v2",
+                "name": chart.name,
+                "description": format!("The {} service, generated by fileforge", chart.name),
+                "type": "application",
+                "version": "0.1.0",
+                "appVersion": service.image_tag,
+            })),
+        )?;
+        write_file_with_backup(
+            &chart_dir.join("values.yaml"),
+            &render_yaml(&render_values(&settings, service)),
+        )?;
+        for environment in environments
+            .iter()
+            .filter(|environment| environment.configured)
+        {
+            write_file_with_backup(
+                &chart_dir.join(format!("values-{}.yaml", environment.name)),
+                &render_yaml(&render_environment_values(
+                    environment,
+                    service,
+                    single_service,
+                )),
+            )?;
+        }
+
+        for (file_name, template) in [
+            ("_helpers.tpl", HELPERS_TEMPLATE),
+            ("deployment.yaml", DEPLOYMENT_TEMPLATE),
+            ("configmap.yaml", CONFIG_MAP_TEMPLATE),
+            ("service.yaml", SERVICE_TEMPLATE),
+            ("ingress.yaml", INGRESS_TEMPLATE),
+            ("hpa.yaml", HPA_TEMPLATE),
+        ] {
+            write_file_with_backup(
+                &chart_dir.join("templates").join(file_name),
+                &template.replace("[[ chart ]]", &chart.name),
+            )?;
+        }
+    }
+
+    let namespace = helm
+        .namespace
+        .as_ref()
+        .map(|namespace| format!(" --namespace {} --create-namespace", namespace))
+        .unwrap_or_default();
+    let upgrades = helm
+        .charts
+        .iter()
+        .map(|chart| format!("upgrade {} {}\n", chart.name, chart.directory))
+        .collect::<String>();
+
+    let script_path = output_dir.join(HELM_UPGRADE_SCRIPT);
+    write_file_with_backup(
+        &script_path,
+        &HELM_UPGRADE
+            .replace("[[ namespace ]]", &namespace)
+            .replace("[[ upgrades ]]", &upgrades)
+            .replace("[[ timeout ]]", &helm.timeout),
+    )?;
+    make_executable(&script_path)?;
+
+    Ok(())
+}
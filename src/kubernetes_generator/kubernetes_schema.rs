@@ -0,0 +1,290 @@
+use std::io;
+
+use serde_json::Value;
+
+/// The definitions of the Kubernetes OpenAPI schema the generated manifests are validated
+/// against, bundled so the validation works offline.
+const KUBERNETES_OPENAPI_SCHEMA: &str = include_str!("schemas/kubernetes-openapi.json");
+
+/// Validates generated manifests against the bundled Kubernetes OpenAPI schema.
+///
+/// The validation is strict: besides wrong types, missing required fields and values outside an
+/// `enum`, it rejects fields the schema does not define, which the API server would silently drop.
+pub struct KubernetesSchema {
+    definitions: Value,
+}
+
+impl KubernetesSchema {
+    /// Loads the bundled schema.
+    ///
+    /// # Returns
+    /// - `io::Result<KubernetesSchema>`: The schema, or an error if the bundled file is invalid.
+    pub fn load() -> io::Result<KubernetesSchema> {
+        let schema: Value = serde_json::from_str(KUBERNETES_OPENAPI_SCHEMA)?;
+        Ok(KubernetesSchema {
+            definitions: schema["definitions"].clone(),
+        })
+    }
+
+    /// Validates a manifest against the definition of its `apiVersion` and `kind`.
+    ///
+    /// # Parameters
+    /// - `manifest`: The manifest to validate.
+    ///
+    /// # Returns
+    /// - `io::Result<()>`: An error listing every problem found in the manifest.
+    pub fn validate(&self, manifest: &Value) -> io::Result<()> {
+        let api_version = manifest["apiVersion"].as_str().unwrap_or_default();
+        let kind = manifest["kind"].as_str().unwrap_or_default();
+        let name = manifest["metadata"]["name"].as_str().unwrap_or_default();
+
+        let definition = self.find_definition(api_version, kind).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "No bundled Kubernetes schema for {} {} (apiVersion {})",
+                    kind, name, api_version
+                ),
+            )
+        })?;
+
+        let mut errors = Vec::new();
+        self.validate_value(manifest, definition, "", &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} {} does not match the Kubernetes schema:\n  {}",
+                    kind,
+                    name,
+                    errors.join("\n  ")
+                ),
+            ))
+        }
+    }
+
+    /// Returns the definition whose `x-kubernetes-group-version-kind` matches the manifest.
+    fn find_definition(&self, api_version: &str, kind: &str) -> Option<&Value> {
+        // The core group has no prefix in apiVersion, e.g. `v1`
+        let (group, version) = api_version.rsplit_once('/').unwrap_or(("", api_version));
+
+        self.definitions.as_object()?.values().find(|definition| {
+            definition["x-kubernetes-group-version-kind"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|gvk| {
+                    gvk["group"] == group && gvk["version"] == version && gvk["kind"] == kind
+                })
+        })
+    }
+
+    /// Validates a value against a schema, collecting the problems with the path of the field.
+    fn validate_value(&self, value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            match reference
+                .strip_prefix("#/definitions/")
+                .and_then(|name| self.definitions.get(name))
+            {
+                Some(definition) => self.validate_value(value, definition, path, errors),
+                None => errors.push(format!("{}: unknown schema reference {}", path, reference)),
+            }
+            return;
+        }
+
+        let field = if path.is_empty() { "(root)" } else { path };
+
+        if schema["format"] == "int-or-string" {
+            if !value.is_string() && !value.is_i64() && !value.is_u64() {
+                errors.push(format!("{}: expected an integer or a string", field));
+            }
+            return;
+        }
+
+        match schema["type"].as_str() {
+            Some("object") => {
+                let Some(object) = value.as_object() else {
+                    errors.push(format!("{}: expected an object", field));
+                    return;
+                };
+
+                for required in schema["required"].as_array().into_iter().flatten() {
+                    let required = required.as_str().unwrap_or_default();
+                    if !object.contains_key(required) {
+                        errors.push(format!("{}: missing required field {}", field, required));
+                    }
+                }
+
+                let properties = schema["properties"].as_object();
+                let additional_properties = schema.get("additionalProperties");
+                for (key, item) in object {
+                    let item_path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    match (
+                        properties.and_then(|properties| properties.get(key)),
+                        additional_properties,
+                    ) {
+                        (Some(item_schema), _) | (None, Some(item_schema)) => {
+                            self.validate_value(item, item_schema, &item_path, errors)
+                        }
+                        // Objects without properties, like the status, accept anything
+                        (None, None) if properties.is_none() => {}
+                        (None, None) => errors.push(format!("{}: unknown field", item_path)),
+                    }
+                }
+            }
+            Some("array") => {
+                let Some(items) = value.as_array() else {
+                    errors.push(format!("{}: expected an array", field));
+                    return;
+                };
+                for (index, item) in items.iter().enumerate() {
+                    self.validate_value(
+                        item,
+                        &schema["items"],
+                        &format!("{}[{}]", path, index),
+                        errors,
+                    );
+                }
+            }
+            Some("string") => {
+                if !value.is_string() {
+                    errors.push(format!("{}: expected a string", field));
+                } else if let Some(allowed) = schema["enum"].as_array() {
+                    if !allowed.contains(value) {
+                        errors.push(format!(
+                            "{}: {} is not one of {}",
+                            field,
+                            value,
+                            allowed
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .collect::<Vec<&str>>()
+                                .join(", ")
+                        ));
+                    }
+                }
+            }
+            Some("integer") => {
+                let in_range = match schema["format"].as_str() {
+                    Some("int32") => value
+                        .as_i64()
+                        .is_some_and(|number| i32::try_from(number).is_ok()),
+                    _ => value.is_i64() || value.is_u64(),
+                };
+                if !in_range {
+                    errors.push(format!("{}: expected an integer", field));
+                }
+            }
+            Some("boolean") if !value.is_boolean() => {
+                errors.push(format!("{}: expected a boolean", field));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn deployment() -> Value {
+        json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "shop-api" },
+            "spec": {
+                "replicas": 2,
+                "selector": { "matchLabels": { "app": "shop-api" } },
+                "template": {
+                    "metadata": { "labels": { "app": "shop-api" } },
+                    "spec": {
+                        "containers": [{
+                            "name": "shop-api",
+                            "image": "shop-api:latest",
+                            "imagePullPolicy": "IfNotPresent",
+                            "ports": [{ "containerPort": 5000 }],
+                            "readinessProbe": { "httpGet": { "path": "/health", "port": 5000 } }
+                        }]
+                    }
+                }
+            }
+        })
+    }
+
+    fn errors(manifest: &Value) -> String {
+        KubernetesSchema::load()
+            .unwrap()
+            .validate(manifest)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn accepts_a_valid_manifest() {
+        KubernetesSchema::load()
+            .unwrap()
+            .validate(&deployment())
+            .unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let mut manifest = deployment();
+        manifest["spec"]["template"]["spec"]["containers"][0]["imagePullPolicyy"] = json!("Always");
+
+        assert!(errors(&manifest)
+            .contains("spec.template.spec.containers[0].imagePullPolicyy: unknown field"));
+    }
+
+    #[test]
+    fn rejects_wrong_types() {
+        let mut manifest = deployment();
+        manifest["spec"]["replicas"] = json!("2");
+        manifest["spec"]["template"]["spec"]["containers"][0]["ports"] =
+            json!({ "containerPort": 5000 });
+
+        let errors = errors(&manifest);
+        assert!(errors.contains("spec.replicas: expected an integer"));
+        assert!(errors.contains("spec.template.spec.containers[0].ports: expected an array"));
+    }
+
+    #[test]
+    fn rejects_missing_required_fields() {
+        let mut manifest = deployment();
+        manifest["spec"].as_object_mut().unwrap().remove("selector");
+        manifest["spec"]["template"]["spec"]["containers"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("name");
+
+        let errors = errors(&manifest);
+        assert!(errors.contains("spec: missing required field selector"));
+        assert!(errors.contains("spec.template.spec.containers[0]: missing required field name"));
+    }
+
+    #[test]
+    fn rejects_values_outside_an_enum() {
+        let mut manifest = deployment();
+        manifest["spec"]["template"]["spec"]["containers"][0]["imagePullPolicy"] =
+            json!("Sometimes");
+
+        assert!(errors(&manifest).contains("imagePullPolicy: \"Sometimes\" is not one of"));
+    }
+
+    #[test]
+    fn rejects_unknown_kinds() {
+        let mut manifest = deployment();
+        manifest["apiVersion"] = json!("apps/v2");
+
+        assert!(errors(&manifest).contains("No bundled Kubernetes schema for Deployment shop-api"));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod generate_kubernetes_manifests;
+pub mod generate_kustomize_files;
+pub mod handle_kubernetes_generation;
+pub mod handle_kustomize_generation;
+pub mod helm_chart;
+pub mod kubernetes_schema;
@@ -1,15 +1,22 @@
 mod angular_generator;
+mod ci_generator;
 mod config;
 mod constants;
 mod dotnet_generator;
+mod kubernetes_generator;
+mod monorepo_generator;
+mod nomad_generator;
 mod others;
+mod podman_generator;
 mod shared;
+mod systemd_generator;
 
 use std::env;
 use std::process::exit;
 
 use config::handle_config_generation::handle_config_generation;
 use others::generate_everything::generate_everything;
+use others::print_ci_variables::print_ci_variables;
 use others::print_usage::print_usage;
 use others::show_config::show_config;
 
@@ -63,6 +70,14 @@ fn main() {
             }
         },
 
+        // Handle the "ci-vars" command
+        "ci-vars" => {
+            if let Err(e) = print_ci_variables() {
+                eprintln!("❌ Error listing CI variables: {}", e); // Error message
+                exit(1); // Exit with an error code
+            }
+        }
+
         // Handle unknown commands
         _ => {
             eprintln!("❌ Unknown command: {}", args[1]); // Error message for unknown command
@@ -0,0 +1,137 @@
+use std::{env, io, thread};
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::{write_ansible_inventory, InventorySettings},
+        ansible_releases::{
+            apply_release_placeholders, render_registry_environment, write_rollback_playbook,
+            ReleaseSettings,
+        },
+        ansible_role::{write_ansible_role, AnsibleRoleSettings},
+        deployment_pipeline::{indent_lines, DeploymentPipeline},
+        image_registry::IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT,
+    },
+    constants::SLEEP_DURATION,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+pub fn generate_ansible_files_for_monorepo() -> io::Result<()> {
+    println!("🌟 Starting Ansible File Copy Process...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+
+    let config = get_current_config(current_dir.clone());
+
+    let project_location = config["project_location"].as_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing project_location in config",
+        )
+    })?;
+    let project_directory = config["project_directory"].as_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing project_directory in config",
+        )
+    })?;
+
+    println!("📁 Project location: {}", project_location);
+    println!("📂 Project directory: {}", project_directory);
+
+    let ansible_dir = get_output_directory(&current_dir).join("ansible");
+
+    write_ansible_inventory(&config, &ansible_dir)?;
+    let inventory_settings = InventorySettings::from_config(&config)?;
+    let release_settings = ReleaseSettings::from_config(&config)?;
+
+    // With `ansible.role`, the playbook only applies a generated role
+    if let Some(role_settings) = AnsibleRoleSettings::from_config(&config)? {
+        write_ansible_role(&config, &ansible_dir, &role_settings)?;
+        println!("🎉 Ansible File Copy Process Completed Successfully!");
+        return Ok(());
+    }
+
+    // The registry login and base image pulls are only needed when a .NET service is built on
+    // the servers
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    let registry_login_task = pipeline
+        .server_base_image_registry()
+        .map(|registry| registry.render_playbook_task(pipeline.server_base_images()))
+        .unwrap_or_default();
+    let registry_url_variable = pipeline.server_registry_url_variable();
+
+    println!("📋 Creating `ansible-deploy.yml` file...");
+    thread::sleep(SLEEP_DURATION);
+    let ansible_deploy_content = r#"---
+- name: Deploy files and start docker compose
+  hosts: target_servers
+[[ play_options ]]  become: yes
+  vars:
+    project_directory: "[[ project_directory ]]"
+    project_location: "[[ project_location ]]" # This is for both local and remote server
+  tasks:
+[[ copy_tasks ]][[ registry_login_task ]]
+    - name: Run docker compose to start the services
+      ansible.builtin.shell:
+        cmd: |
+          echo "Current Directory:"
+          pwd
+[[ compose_up ]]
+        chdir: "[[ compose_directory ]]"
+      register: docker_compose_result
+      environment:
+[[ registry_environment ]]        COMPOSE_FILE: "{{ compose_file | default('compose.yaml') }}"
+[[ image_registry_environment ]][[ compose_project_name ]]      ignore_errors: [[ ignore_compose_errors ]]
+[[ compose_when ]]
+    - name: Display docker compose result
+      debug:
+        var: docker_compose_result.stdout
+[[ release_tasks ]]"#;
+
+    let updated_ansible_deploy_content = apply_release_placeholders(
+        ansible_deploy_content,
+        release_settings.as_ref(),
+        registry_url_variable,
+    )
+    .replace(
+        "[[ registry_environment ]]",
+        &render_registry_environment(registry_url_variable),
+    )
+    .replace(
+        "[[ image_registry_environment ]]",
+        match pipeline.image_registry {
+            Some(_) => IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT,
+            None => "",
+        },
+    )
+    .replace("[[ compose_when ]]", pipeline.compose_up_condition())
+    .replace(
+        "[[ compose_up ]]",
+        &indent_lines(pipeline.compose_up_script(), 10),
+    )
+    .replace("[[ registry_login_task ]]", &registry_login_task)
+    .replace(
+        "[[ play_options ]]",
+        &inventory_settings.render_play_options(),
+    )
+    .replace("[[ project_location ]]", project_location)
+    .replace("[[ project_directory ]]", project_directory);
+    write_file_with_backup(
+        &ansible_dir.join("ansible-deploy.yml"),
+        &updated_ansible_deploy_content,
+    )?;
+
+    // With `ansible.keep_releases`, a rollback playbook switches back to the previous release
+    if release_settings.is_some() {
+        write_rollback_playbook(&config, &ansible_dir, registry_url_variable)?;
+    }
+
+    println!("🎉 Ansible File Copy Process Completed Successfully!");
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{env, io, thread};
+
+use crate::{
+    angular_generator::generate_compose_file_for_angular::render_compose_service_for_angular,
+    ci_generator::{
+        base_image_registry::BaseImageRegistry,
+        image_registry::ImageRegistrySettings,
+        reverse_proxy::{write_reverse_proxy_files, ReverseProxySettings},
+        swarm_stack::write_swarm_stack_files,
+    },
+    constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::{
+        render_compose_service_for_dotnet, DOTNET_COMPOSE_NETWORKS,
+    },
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+pub fn generate_compose_file_for_monorepo() -> io::Result<()> {
+    println!("🚀 Starting combined compose file generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let services = get_monorepo_services(&config)?;
+    let image_registry = ImageRegistrySettings::from_config(&config)?;
+    let registry = BaseImageRegistry::from_config(&config)?;
+    let reverse_proxy = ReverseProxySettings::from_config(&config)?;
+
+    // Every service is built from its own directory, relative to the root compose file
+    let service_blocks = services
+        .iter()
+        .map(|service| {
+            let build_context = format!("./{}", service["path"].as_str().unwrap_or("."));
+            match service["project_type"].as_str() {
+                Some("angular") => Ok(render_compose_service_for_angular(
+                    service,
+                    &build_context,
+                    image_registry.as_ref(),
+                    reverse_proxy.as_ref(),
+                )),
+                _ => render_compose_service_for_dotnet(
+                    service,
+                    &build_context,
+                    image_registry.as_ref(),
+                    &registry,
+                    reverse_proxy.as_ref(),
+                ),
+            }
+        })
+        .collect::<io::Result<Vec<String>>>()?;
+
+    let mut template = String::from("\nservices:\n");
+    template.push_str(&service_blocks.join("\n"));
+
+    // Angular apps only join the API network when the proxy routes them
+    let joins_api_network = services.iter().any(|service| {
+        service["project_type"].as_str() == Some("dotnet")
+            || reverse_proxy.as_ref().is_some_and(|proxy| {
+                proxy.is_routed(
+                    service["service_name"]
+                        .as_str()
+                        .unwrap_or("default_service"),
+                )
+            })
+    });
+
+    if joins_api_network {
+        template.push_str(DOTNET_COMPOSE_NETWORKS);
+        println!("✅ Network configuration added.");
+        thread::sleep(SLEEP_DURATION);
+    }
+
+    let output_dir = get_output_directory(&current_dir);
+    let output_path = output_dir.join("compose.yaml");
+    write_file_with_backup(&output_path, &template)?;
+
+    // Swarm hosts deploy the same services as a stack
+    write_swarm_stack_files(&config, &output_dir)?;
+    write_reverse_proxy_files(&config, &output_dir)?;
+
+    println!(
+        "🎉 Compose file generated successfully at {:?}",
+        output_path
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::{collections::HashSet, env, io, thread};
+
+use crate::{
+    angular_generator::{
+        generate_dockerfile_for_angular::render_dockerfile_for_angular,
+        generate_nginx_file_for_angular::render_nginx_file_for_angular,
+    },
+    ci_generator::base_image_registry::BaseImageRegistry,
+    constants::SLEEP_DURATION,
+    dotnet_generator::{
+        generate_dockerfile_for_dotnet::render_dockerfile_for_dotnet,
+        generate_nginx_file_for_dotnet::render_nginx_file_for_dotnet,
+    },
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+pub fn generate_dockerfiles_for_monorepo() -> io::Result<()> {
+    println!("🚀 Starting Dockerfile generation for all services...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let services = get_monorepo_services(&config)?;
+    let registry = BaseImageRegistry::from_config(&config)?;
+    let output_dir = get_output_directory(&current_dir);
+
+    // Services sharing a directory share its nginx.conf, so it is only written once
+    let mut nginx_paths = HashSet::new();
+
+    for service in services {
+        let path = service["path"].as_str().unwrap_or(".");
+        let service_dir = output_dir.join(path);
+        println!("\n📦 Generating files for service at: {}", path);
+        thread::sleep(SLEEP_DURATION);
+
+        // Each service gets its own Dockerfile, plus an nginx.conf when nginx serves its build
+        let (dockerfile, nginx_file) = match service["project_type"].as_str() {
+            Some("angular") => (
+                render_dockerfile_for_angular(service)?,
+                Some(render_nginx_file_for_angular()),
+            ),
+            _ => {
+                let nginx_file = if service["app_type"].as_str() == Some("blazor_wasm") {
+                    Some(render_nginx_file_for_dotnet())
+                } else {
+                    None
+                };
+                (
+                    render_dockerfile_for_dotnet(service, &registry)?,
+                    nginx_file,
+                )
+            }
+        };
+
+        // Services sharing a directory, like the applications of one workspace, name their own
+        let dockerfile_name = service["dockerfile"].as_str().unwrap_or("Dockerfile");
+        write_file_with_backup(&service_dir.join(dockerfile_name), &dockerfile)?;
+        if let Some(nginx_file) = nginx_file {
+            let nginx_path = service_dir.join("nginx.conf");
+            if nginx_paths.insert(nginx_path.clone()) {
+                write_file_with_backup(&nginx_path, &nginx_file)?;
+            }
+        }
+    }
+
+    println!("🎉 Dockerfile generation for all services completed successfully!");
+    thread::sleep(SLEEP_DURATION);
+    Ok(())
+}
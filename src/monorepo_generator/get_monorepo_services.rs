@@ -0,0 +1,74 @@
+use std::{collections::BTreeSet, io};
+
+use serde_json::Value;
+
+use crate::dotnet_generator::generate_dockerfile_for_dotnet::get_dotnet_base_images;
+
+/// Returns the services defined in a monorepo configuration.
+///
+/// Every service must have a `path` relative to the repository root and a `project_type` of either
+/// `dotnet` or `angular`. The remaining keys are the same as in a single-project configuration.
+///
+/// # Parameters
+/// - `config`: The monorepo configuration.
+///
+/// # Returns
+/// - `io::Result<&Vec<Value>>`: The services, or an error if the list is missing or invalid.
+pub fn get_monorepo_services(config: &Value) -> io::Result<&Vec<Value>> {
+    let services = config["services"]
+        .as_array()
+        .filter(|services| !services.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing or empty services in config",
+            )
+        })?;
+
+    for service in services {
+        let path = service["path"].as_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Missing path in service config")
+        })?;
+
+        match service["project_type"].as_str() {
+            Some("dotnet") | Some("angular") => {}
+            Some(project_type) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown project_type {} for service {}", project_type, path),
+                ))
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Missing project_type for service {}", path),
+                ))
+            }
+        }
+    }
+
+    Ok(services)
+}
+
+/// Returns the `dotnet/<image>:<version>` base images needed by the .NET services of a monorepo.
+///
+/// # Parameters
+/// - `services`: The services of the monorepo.
+///
+/// # Returns
+/// - `Vec<String>`: The unique images, sorted, relative to the registry root.
+pub fn get_monorepo_dotnet_base_images(services: &[Value]) -> Vec<String> {
+    services
+        .iter()
+        .filter(|service| service["project_type"].as_str() == Some("dotnet"))
+        .flat_map(|service| {
+            let dotnet_version = service["dotnet_version"].as_str().unwrap_or("9.0");
+            let app_type = service["app_type"].as_str().unwrap_or("web");
+            get_dotnet_base_images(app_type)
+                .iter()
+                .map(move |image| format!("dotnet/{}:{}", image, dotnet_version))
+        })
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
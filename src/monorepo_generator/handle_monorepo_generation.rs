@@ -0,0 +1,98 @@
+use std::{io, thread};
+
+use crate::{
+    ci_generator::{
+        blue_green::generate_blue_green_files,
+        generate_compose_overrides::generate_compose_overrides,
+        handle_ci_generation::handle_ci_generation, image_registry::generate_image_registry_files,
+    },
+    constants::SLEEP_DURATION,
+    kubernetes_generator::helm_chart::generate_helm_charts,
+    monorepo_generator::{
+        generate_ansible_files_for_monorepo::generate_ansible_files_for_monorepo,
+        generate_compose_file_for_monorepo::generate_compose_file_for_monorepo,
+        generate_dockerfiles_for_monorepo::generate_dockerfiles_for_monorepo,
+    },
+};
+
+pub fn handle_monorepo_generation() -> io::Result<()> {
+    println!("\n🚀 Starting Monorepo Generator...");
+
+    println!("\n🔧 Generating Dockerfiles...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_dockerfiles_for_monorepo() {
+        eprintln!("❌ Error: Failed to generate Dockerfiles. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ Dockerfiles generated successfully!");
+
+    println!("\n🔧 Generating Docker Compose file...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_compose_file_for_monorepo() {
+        eprintln!(
+            "❌ Error: Failed to generate Docker Compose file. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+    println!("✅ Docker Compose file generated successfully!");
+
+    println!("\n🔧 Generating environment overrides...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_compose_overrides() {
+        eprintln!(
+            "❌ Error: Failed to generate environment overrides. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating blue/green deploy files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_blue_green_files() {
+        eprintln!(
+            "❌ Error: Failed to generate blue/green deploy files. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating image build files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_image_registry_files() {
+        eprintln!(
+            "❌ Error: Failed to generate image build files. Details: {}",
+            e
+        );
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating Helm charts...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_helm_charts() {
+        eprintln!("❌ Error: Failed to generate Helm charts. Details: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("\n🔧 Generating CI file...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = handle_ci_generation() {
+        eprintln!("❌ Error: Failed to generate CI file. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ CI file generated successfully!");
+
+    println!("\n🔧 Copying Ansible files...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_ansible_files_for_monorepo() {
+        eprintln!("❌ Error: Failed to copy Ansible files. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ Ansible files copied successfully!");
+
+    println!(
+        "\n🎉 Monorepo Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
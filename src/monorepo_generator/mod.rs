@@ -0,0 +1,5 @@
+pub mod generate_ansible_files_for_monorepo;
+pub mod generate_compose_file_for_monorepo;
+pub mod generate_dockerfiles_for_monorepo;
+pub mod get_monorepo_services;
+pub mod handle_monorepo_generation;
@@ -0,0 +1,524 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        blue_green::compose_project_name, deploy_environment::ComposeOverrides,
+        image_registry::ImageRegistrySettings,
+    },
+    constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::get_dotnet_container_port,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// How a new version of the job is rolled out, read from `nomad.update`.
+pub struct NomadUpdateSettings {
+    /// The allocations of the new version started next to the old ones before it is promoted.
+    pub canary: u64,
+    /// Whether healthy canaries are promoted without `nomad deployment promote`.
+    pub auto_promote: bool,
+    pub max_parallel: u64,
+    pub healthy_deadline: String,
+}
+
+/// A service of the project as it runs on Nomad, as a group with a single docker task.
+pub struct NomadService {
+    /// The name of the group, task and Nomad service, from `service_name`.
+    pub name: String,
+    /// The image without its tag, pulled from the image registry when one is configured.
+    pub image_repository: String,
+    /// The port the container listens on, or `None` for workers, which get no network or service.
+    pub container_port: Option<u16>,
+    /// The port published on the client, from `port`.
+    pub port: u64,
+    pub count: u64,
+    pub env: Vec<(String, String)>,
+    /// The path of the HTTP check, if the healthcheck is enabled.
+    pub check_path: Option<&'static str>,
+    /// The MHz of CPU and MB of memory reserved for the task.
+    pub cpu: u64,
+    pub memory: u64,
+}
+
+/// The Nomad settings of the project, read from the optional `nomad` section of the
+/// configuration.
+///
+/// `replicas`, `env`, `cpu` and `memory` apply to every service. In monorepos, set them per
+/// service under `"services": { "<service_name>": { ... } }`.
+pub struct NomadSettings {
+    /// The name of the job, the project directory by default.
+    pub job_name: String,
+    pub datacenters: Vec<String>,
+    pub namespace: Option<String>,
+    pub region: Option<String>,
+    /// The default of the `image_tag` variable of the job.
+    pub image_tag: String,
+    /// Whether the services publish their `port` on the client, instead of a dynamic port. Only
+    /// the default without canaries, since a canary can't bind the port of the allocation it
+    /// replaces on the same client.
+    pub static_ports: bool,
+    /// The catalog the services are registered in, `nomad` or `consul`.
+    pub service_provider: String,
+    pub update: NomadUpdateSettings,
+    pub services: Vec<NomadService>,
+}
+
+/// Checks that a name is valid for a Nomad service: letters, digits and dashes.
+fn check_name(setting: &str, name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} {:?} is not a valid Nomad name (use letters, digits and dashes)",
+                setting, name
+            ),
+        ))
+    }
+}
+
+impl NomadSettings {
+    /// Reads the Nomad settings and the services of the project.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<NomadSettings>`: The settings, or an error if a name or setting is invalid.
+    pub fn from_config(config: &Value) -> io::Result<NomadSettings> {
+        let nomad = &config["nomad"];
+        let image_registry = ImageRegistrySettings::from_config(config)?;
+        let defaults = ComposeOverrides::from_value(nomad);
+
+        let job_name = nomad["job_name"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| compose_project_name(config).replace('_', "-"));
+        check_name("nomad.job_name", &job_name)?;
+
+        let datacenters = match nomad["datacenters"].as_array() {
+            Some(datacenters) => datacenters
+                .iter()
+                .map(|datacenter| {
+                    datacenter.as_str().map(str::to_string).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid nomad.datacenters entry: {}", datacenter),
+                        )
+                    })
+                })
+                .collect::<io::Result<Vec<String>>>()?,
+            None => vec!["dc1".to_string()],
+        };
+
+        let service_provider = nomad["service_provider"].as_str().unwrap_or("nomad");
+        if service_provider != "nomad" && service_provider != "consul" {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Unknown nomad.service_provider: {} (use nomad or consul)",
+                    service_provider
+                ),
+            ));
+        }
+
+        let update = &nomad["update"];
+        let update = NomadUpdateSettings {
+            canary: update["canary"].as_u64().unwrap_or(1),
+            auto_promote: update["auto_promote"].as_bool().unwrap_or(true),
+            max_parallel: update["max_parallel"].as_u64().unwrap_or(1),
+            healthy_deadline: update["healthy_deadline"]
+                .as_str()
+                .unwrap_or("5m")
+                .to_string(),
+        };
+
+        let static_ports = nomad["static_ports"]
+            .as_bool()
+            .unwrap_or(update.canary == 0);
+        if static_ports && update.canary > 0 {
+            println!(
+                "⚠️  nomad.static_ports with canaries: each canary needs a client without the allocation it replaces."
+            );
+        }
+
+        let service_configs = if config["project_type"].as_str() == Some("monorepo") {
+            get_monorepo_services(config)?.iter().collect()
+        } else {
+            vec![config]
+        };
+
+        let mut services = Vec::new();
+        for service in service_configs {
+            let name = service["service_name"]
+                .as_str()
+                .unwrap_or("default_service")
+                .to_string();
+            check_name("service_name", &name)?;
+
+            let service_settings = &nomad["services"][&name];
+            let overrides = ComposeOverrides::from_value(service_settings);
+
+            let image_name = service["image_name"].as_str().unwrap_or("default_image");
+            let image_repository = match &image_registry {
+                Some(image_registry) => format!("{}/{}", image_registry.url, image_name),
+                None => image_name.to_string(),
+            };
+
+            let app_type = service["app_type"].as_str().unwrap_or("web");
+            let (container_port, mut env) = match service["project_type"].as_str() {
+                Some("angular") => (
+                    Some(80),
+                    vec![("NODE_ENV".to_string(), "production".to_string())],
+                ),
+                _ => (get_dotnet_container_port(app_type), Vec::new()),
+            };
+
+            // The service settings replace the shared ones with the same name
+            env.extend(
+                defaults
+                    .env
+                    .iter()
+                    .filter(|(key, _)| !overrides.env.iter().any(|(other, _)| other == key))
+                    .cloned(),
+            );
+            env.extend(overrides.env);
+            // The variables are attributes of the `env` block, so they must be HCL identifiers
+            if let Some((key, _)) = env.iter().find(|(key, _)| {
+                key.starts_with(|c: char| c.is_ascii_digit())
+                    || !key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            }) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "The variable {:?} of {} is not a valid Nomad env name (use letters, digits, underscores and dashes)",
+                        key, name
+                    ),
+                ));
+            }
+
+            // Workers listen on no port, so their healthcheck stays in the image
+            let check_path = match (
+                service["enable_healthcheck"].as_bool().unwrap_or(false),
+                container_port,
+            ) {
+                (false, _) | (_, None) => None,
+                _ if service["project_type"].as_str() == Some("angular")
+                    || app_type == "blazor_wasm" =>
+                {
+                    Some("/")
+                }
+                _ => Some("/health"),
+            };
+
+            let resource = |field: &str, default: u64| {
+                service_settings[field]
+                    .as_u64()
+                    .or(nomad[field].as_u64())
+                    .unwrap_or(default)
+            };
+
+            services.push(NomadService {
+                name,
+                image_repository,
+                container_port,
+                port: service["port"].as_u64().unwrap_or(5000),
+                count: overrides.replicas.or(defaults.replicas).unwrap_or(1),
+                env,
+                check_path,
+                cpu: resource("cpu", 500),
+                memory: resource("memory", 256),
+            });
+        }
+
+        Ok(NomadSettings {
+            job_name,
+            datacenters,
+            namespace: nomad["namespace"].as_str().map(str::to_string),
+            region: nomad["region"].as_str().map(str::to_string),
+            image_tag: nomad["image_tag"].as_str().unwrap_or("latest").to_string(),
+            static_ports,
+            service_provider: service_provider.to_string(),
+            update,
+            services,
+        })
+    }
+
+    /// Renders the `group` block of a service.
+    fn render_group(&self, service: &NomadService) -> String {
+        let mut group = format!(
+            "  group \"{name}\" {{\n    count = {count}\n",
+            name = service.name,
+            count = service.count
+        );
+
+        if let Some(container_port) = service.container_port {
+            // `nomad fmt` aligns the `=` of the two attributes
+            let port_attributes = if self.static_ports {
+                format!(
+                    "static = {}\n        to     = {}",
+                    service.port, container_port
+                )
+            } else {
+                format!("to = {}", container_port)
+            };
+            group.push_str(&format!(
+                r#"
+    network {{
+      port "http" {{
+        {port_attributes}
+      }}
+    }}
+
+    service {{
+      name     = "{name}"
+      port     = "http"
+      provider = "{provider}"
+"#,
+                port_attributes = port_attributes,
+                name = service.name,
+                provider = self.service_provider
+            ));
+
+            // An allocation failing its check 3 times is restarted, as the compose healthcheck
+            // marks the container unhealthy
+            if let Some(check_path) = service.check_path {
+                group.push_str(&format!(
+                    r#"
+      check {{
+        type     = "http"
+        path     = "{check_path}"
+        interval = "40s"
+        timeout  = "30s"
+
+        check_restart {{
+          limit = 3
+          grace = "60s"
+        }}
+      }}
+"#,
+                    check_path = check_path
+                ));
+            }
+            group.push_str("    }\n");
+        }
+
+        group.push_str(&format!(
+            r#"
+    task "{name}" {{
+      driver = "docker"
+
+      config {{
+        image = "{image}:${{var.image_tag}}"{ports}
+      }}
+"#,
+            name = service.name,
+            image = service.image_repository,
+            ports = if service.container_port.is_some() {
+                "\n        ports = [\"http\"]"
+            } else {
+                ""
+            }
+        ));
+
+        if !service.env.is_empty() {
+            let width = service
+                .env
+                .iter()
+                .map(|(key, _)| key.len())
+                .max()
+                .unwrap_or_default();
+            group.push_str("\n      env {\n");
+            for (key, value) in &service.env {
+                group.push_str(&format!(
+                    "        {:width$} = {}\n",
+                    key,
+                    render_hcl_string(value),
+                    width = width
+                ));
+            }
+            group.push_str("      }\n");
+        }
+
+        group.push_str(&format!(
+            r#"
+      resources {{
+        cpu    = {cpu}
+        memory = {memory}
+      }}
+    }}
+  }}
+"#,
+            cpu = service.cpu,
+            memory = service.memory
+        ));
+
+        group
+    }
+
+    /// Renders the job file.
+    pub fn render_job(&self) -> String {
+        let mut header = format!(
+            "  datacenters = [{}]\n  type        = \"service\"\n",
+            self.datacenters
+                .iter()
+                .map(|datacenter| render_hcl_string(datacenter))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        if let Some(namespace) = &self.namespace {
+            header.push_str(&format!(
+                "  namespace   = {}\n",
+                render_hcl_string(namespace)
+            ));
+        }
+        if let Some(region) = &self.region {
+            header.push_str(&format!("  region      = {}\n", render_hcl_string(region)));
+        }
+
+        // Canaries run next to the old allocations until they are healthy, and a failed
+        // deployment reverts to the last healthy version
+        let update = format!(
+            r#"
+  update {{
+    max_parallel      = {max_parallel}
+    canary            = {canary}
+    auto_promote      = {auto_promote}
+    auto_revert       = true
+    health_check      = "checks"
+    min_healthy_time  = "10s"
+    healthy_deadline  = "{healthy_deadline}"
+    progress_deadline = "10m"
+  }}
+"#,
+            max_parallel = self.update.max_parallel,
+            canary = self.update.canary,
+            auto_promote = self.update.auto_promote && self.update.canary > 0,
+            healthy_deadline = self.update.healthy_deadline
+        );
+
+        let groups = self
+            .services
+            .iter()
+            .map(|service| self.render_group(service))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!(
+            r#"# The {job} job, generated by fileforge.
+#
+# Deploy a release with: nomad job run -var image_tag=<tag> {job}.nomad.hcl
+
+variable "image_tag" {{
+  type    = string
+  default = {image_tag}
+}}
+
+job "{job}" {{
+{header}{update}
+{groups}}}
+"#,
+            job = self.job_name,
+            image_tag = render_hcl_string(&self.image_tag),
+            header = header,
+            update = update,
+            groups = groups
+        )
+    }
+}
+
+/// Renders a quoted HCL string, escaping the template sequences `${` and `%{`.
+fn render_hcl_string(value: &str) -> String {
+    Value::String(value.to_string())
+        .to_string()
+        .replace("${", "$${")
+        .replace("%{", "%%{")
+}
+
+/// Generates the Nomad job of the project as `<job_name>.nomad.hcl`, with a group running a
+/// docker task per service.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or the file cannot be written.
+pub fn generate_nomad_job() -> io::Result<()> {
+    println!("🚀 Starting Nomad job generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let settings = NomadSettings::from_config(&config)?;
+
+    let output_path =
+        get_output_directory(&current_dir).join(format!("{}.nomad.hcl", settings.job_name));
+    write_file_with_backup(&output_path, &settings.render_job())?;
+
+    println!(
+        "🎉 Generated the Nomad job {} with {} group(s) at {:?}",
+        settings.job_name,
+        settings.services.len(),
+        output_path
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn nomad_settings(nomad: Value) -> NomadSettings {
+        NomadSettings::from_config(&json!({
+            "project_type": "dotnet",
+            "dotnet_version": "9.0",
+            "app_type": "web",
+            "service_name": "shop-api",
+            "image_name": "shop-api",
+            "port": 8080,
+            "project_directory": "shop",
+            "project_location": "/srv",
+            "nomad": nomad
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn canaries_use_dynamic_ports_by_default() {
+        let settings = nomad_settings(json!({}));
+        assert_eq!(settings.update.canary, 1);
+        assert!(!settings.static_ports);
+        assert!(settings
+            .render_group(&settings.services[0])
+            .contains("      port \"http\" {\n        to = 5000\n      }\n"));
+    }
+
+    #[test]
+    fn static_ports_are_the_default_without_canaries() {
+        let settings = nomad_settings(json!({ "update": { "canary": 0 } }));
+        assert!(settings.static_ports);
+        assert!(settings
+            .render_group(&settings.services[0])
+            .contains("        static = 8080\n        to     = 5000\n"));
+
+        assert!(nomad_settings(json!({ "static_ports": true })).static_ports);
+    }
+}
@@ -0,0 +1,22 @@
+use std::{io, thread};
+
+use crate::constants::SLEEP_DURATION;
+use crate::nomad_generator::generate_nomad_job::generate_nomad_job;
+
+pub fn handle_nomad_generation() -> io::Result<()> {
+    println!("\n🚀 Starting Nomad Generator...");
+
+    println!("\n🔧 Generating Nomad job...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_nomad_job() {
+        eprintln!("❌ Error: Failed to generate Nomad job. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ Nomad job generated successfully!");
+
+    println!(
+        "\n🎉 Nomad Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
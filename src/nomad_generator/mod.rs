@@ -0,0 +1,2 @@
+pub mod generate_nomad_job;
+pub mod handle_nomad_generation;
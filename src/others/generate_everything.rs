@@ -4,7 +4,15 @@ use crate::{
     angular_generator::handle_angular_generation::handle_angular_generation,
     constants::SLEEP_DURATION,
     dotnet_generator::handle_dotnet_generation::handle_dotnet_generation,
+    kubernetes_generator::{
+        handle_kubernetes_generation::handle_kubernetes_generation,
+        handle_kustomize_generation::handle_kustomize_generation,
+    },
+    monorepo_generator::handle_monorepo_generation::handle_monorepo_generation,
+    nomad_generator::handle_nomad_generation::handle_nomad_generation,
+    podman_generator::handle_podman_generation::handle_podman_generation,
     shared::{self, check_git_status::check_git_status},
+    systemd_generator::handle_systemd_generation::handle_systemd_generation,
 };
 
 pub fn generate_everything(args: Vec<String>) -> io::Result<()> {
@@ -58,6 +66,85 @@ pub fn generate_everything(args: Vec<String>) -> io::Result<()> {
     let project_type = config["project_type"].as_str().unwrap_or("dotnet");
     println!("🔧 Project type: {}", project_type);
 
+    // The `--target` flag selects what the project is deployed with (default to "compose")
+    let target = args
+        .iter()
+        .position(|arg| arg == "--target")
+        .map(|index| args.get(index + 1).map(String::as_str).unwrap_or_default())
+        .unwrap_or("compose");
+    println!("🎯 Target: {}", target);
+
+    match target {
+        "compose" => {}
+        "kubernetes" => {
+            match handle_kubernetes_generation() {
+                Ok(_) => {
+                    println!("🎉 Kubernetes manifests generated successfully!");
+                }
+                Err(_) => {
+                    eprintln!("❌ Error generating Kubernetes manifests.");
+                    exit(1); // Exit with an error code if generation fails
+                }
+            }
+            return Ok(());
+        }
+        "kustomize" => {
+            match handle_kustomize_generation() {
+                Ok(_) => {
+                    println!("🎉 Kustomize base and overlays generated successfully!");
+                }
+                Err(_) => {
+                    eprintln!("❌ Error generating Kustomize base and overlays.");
+                    exit(1); // Exit with an error code if generation fails
+                }
+            }
+            return Ok(());
+        }
+        "podman" => {
+            match handle_podman_generation() {
+                Ok(_) => {
+                    println!("🎉 Podman units generated successfully!");
+                }
+                Err(_) => {
+                    eprintln!("❌ Error generating Podman units.");
+                    exit(1); // Exit with an error code if generation fails
+                }
+            }
+            return Ok(());
+        }
+        "nomad" => {
+            match handle_nomad_generation() {
+                Ok(_) => {
+                    println!("🎉 Nomad job generated successfully!");
+                }
+                Err(_) => {
+                    eprintln!("❌ Error generating Nomad job.");
+                    exit(1); // Exit with an error code if generation fails
+                }
+            }
+            return Ok(());
+        }
+        "systemd" => {
+            match handle_systemd_generation() {
+                Ok(_) => {
+                    println!("🎉 systemd units generated successfully!");
+                }
+                Err(_) => {
+                    eprintln!("❌ Error generating systemd units.");
+                    exit(1); // Exit with an error code if generation fails
+                }
+            }
+            return Ok(());
+        }
+        _ => {
+            eprintln!(
+                "❌ Unknown target: {} (use compose, kubernetes, kustomize, podman, nomad or systemd)",
+                target
+            );
+            exit(1); // Exit with an error code for unsupported targets
+        }
+    }
+
     // Handle generation based on the project type
     match project_type {
         "dotnet" => match handle_dotnet_generation() {
@@ -80,6 +167,15 @@ pub fn generate_everything(args: Vec<String>) -> io::Result<()> {
                 exit(1); // Exit with an error code if generation fails
             }
         },
+        "monorepo" => match handle_monorepo_generation() {
+            Ok(_) => {
+                println!("🎉 Dockerfiles and other files for all services generated successfully!");
+            }
+            Err(_) => {
+                eprintln!("❌ Error generating files for monorepo services.");
+                exit(1); // Exit with an error code if generation fails
+            }
+        },
         _ => {
             eprintln!("❌ Unknown project type: {}", project_type);
             exit(1); // Exit with an error code for unsupported project types
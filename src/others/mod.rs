@@ -1,3 +1,4 @@
 pub mod generate_everything;
+pub mod print_ci_variables;
 pub mod print_usage;
 pub mod show_config;
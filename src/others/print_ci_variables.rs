@@ -0,0 +1,111 @@
+use std::{env, io, process::exit};
+
+use crate::{
+    ci_generator::{
+        deployment_pipeline::DeploymentPipeline,
+        generate_azure_pipelines_file::AzurePipelinesSettings,
+        generate_jenkinsfile::{jenkins_credentials_id, JenkinsSettings},
+    },
+    shared::get_current_config::get_current_config,
+};
+
+/// Prints the variables the CI provider must define for the generated pipeline, named the way
+/// the configured `ci_provider` expects them.
+pub fn print_ci_variables() -> io::Result<()> {
+    let current_dir = env::current_dir()?;
+
+    if !current_dir.join("fileforge.config.json").exists() {
+        eprintln!(
+            "❌ Error: `fileforge.config.json` not found. Run 'fileforge init' to generate a config."
+        );
+        exit(1);
+    }
+
+    let config = get_current_config(current_dir);
+    let pipeline = DeploymentPipeline::from_config(&config)?;
+    let ci_provider = config["ci_provider"].as_str().unwrap_or("gitlab");
+    let ci_variables = pipeline.ci_variables();
+
+    match ci_provider {
+        "gitlab" => {
+            println!("🔐 Define these CI/CD variables in Settings > CI/CD > Variables:\n");
+            for variable in &ci_variables {
+                println!("  {:<36} {}", variable.name, variable.description);
+            }
+        }
+        "github" | "gitea" | "forgejo" => {
+            println!("🔐 Define these repository secrets:\n");
+            for variable in &ci_variables {
+                println!("  {:<36} {}", variable.name, variable.description);
+            }
+        }
+        "woodpecker" => {
+            println!("🔐 Define these repository secrets:\n");
+            for variable in &ci_variables {
+                println!(
+                    "  {:<36} {}",
+                    variable.name.to_lowercase(),
+                    variable.description
+                );
+            }
+        }
+        "jenkins" => {
+            // The registry login is a single username with password credential
+            let settings = JenkinsSettings::from_config(&config);
+            println!("🔐 Define these Jenkins credentials:\n");
+            let registry = &pipeline.base_image_registry;
+            if !pipeline.base_images.is_empty() && registry.url_variable().is_some() {
+                println!(
+                    "  {:<36} Secret text: URL of the Docker registry",
+                    settings.registry_url_credentials_id
+                );
+            }
+            if !pipeline.base_images.is_empty() && registry.login {
+                println!(
+                    "  {:<36} Username with password: Docker registry login",
+                    settings.registry_credentials_id
+                );
+            }
+            if pipeline.image_registry.is_some() {
+                println!(
+                    "  {:<36} Username with password: Image registry login",
+                    settings.image_registry_credentials_id
+                );
+            }
+            for variable in pipeline
+                .environments
+                .iter()
+                .flat_map(|environment| pipeline.environment_variables(environment))
+                .chain(
+                    pipeline
+                        .vault_variable()
+                        .filter(|_| pipeline.helm.is_none()),
+                )
+            {
+                println!(
+                    "  {:<36} Secret text: {}",
+                    jenkins_credentials_id(&variable.name),
+                    variable.description
+                );
+            }
+        }
+        "azure" => {
+            let settings = AzurePipelinesSettings::from_config(&config);
+            println!(
+                "🔐 Define these variables in the `{}` variable group:\n",
+                settings.variable_group
+            );
+            for variable in &ci_variables {
+                println!("  {:<36} {}", variable.name, variable.description);
+            }
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown ci_provider: {}", ci_provider),
+            ))
+        }
+    }
+
+    Ok(())
+}
@@ -1,9 +1,18 @@
 /// Prints the usage instructions for the program.
 pub fn print_usage() {
-    eprintln!("Usage: fileforge <command> [--ignore-git]");
+    eprintln!(
+        "Usage: fileforge <command> [--ignore-git] [--target compose|kubernetes|kustomize|podman|nomad|systemd]"
+    );
     eprintln!("Commands:");
     eprintln!("  init      Generate configuration");
-    eprintln!("  generate  Generate the Dockerfile");
+    eprintln!(
+        "  generate  Generate the Dockerfile, or the Kubernetes manifests with --target kubernetes"
+    );
+    eprintln!("            or a Kustomize base and overlays with --target kustomize");
+    eprintln!("            or Podman units and their playbook with --target podman");
+    eprintln!("            or a Nomad job with --target nomad");
+    eprintln!("            or systemd units and their playbook with --target systemd");
     eprintln!("  config    Print the current configuration");
+    eprintln!("  ci-vars   List the CI variables the generated pipeline needs");
     eprintln!("  version   Print the version of fileforge");
 }
@@ -0,0 +1,534 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::{write_ansible_inventory, InventorySettings},
+        deploy_environment::{get_compose_services, get_deploy_environments, DeployEnvironment},
+        image_registry::{ImageRegistrySettings, IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT},
+    },
+    constants::SLEEP_DURATION,
+    dotnet_generator::generate_compose_file_for_dotnet::get_dotnet_healthcheck_test,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// The directory the units are written to.
+pub const PODMAN_DIRECTORY: &str = "podman";
+
+/// The playbook installing the units on the servers.
+pub const PODMAN_PLAYBOOK: &str = "ansible/podman-deploy.yml";
+
+/// The network the .NET services share, like the external `api-network` of the compose file.
+const API_NETWORK: &str = "api-network";
+
+/// The kind of units the services run as.
+pub enum PodmanUnits {
+    /// Quadlet `.container` and `.network` files, turned into services by Podman 4.4 and later.
+    Quadlet,
+    /// Plain systemd `.service` units running `podman run`, for older Podman versions.
+    Systemd,
+}
+
+impl PodmanUnits {
+    /// Returns the directory the units are installed in on the servers.
+    fn install_directory(&self) -> &'static str {
+        match self {
+            PodmanUnits::Quadlet => "/etc/containers/systemd",
+            PodmanUnits::Systemd => "/etc/systemd/system",
+        }
+    }
+}
+
+/// The Podman settings of the project, read from the optional `podman` section of the
+/// configuration.
+pub struct PodmanSettings {
+    pub units: PodmanUnits,
+    /// The tag of the images. Auto-updates need a tag the CI pipeline pushes every release to.
+    pub image_tag: String,
+    /// Whether `podman auto-update` replaces the containers when their image tag moves.
+    pub auto_update: bool,
+}
+
+impl PodmanSettings {
+    /// Reads the Podman settings.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<PodmanSettings>`: The settings, or an error if `podman.units` is unknown.
+    pub fn from_config(config: &Value) -> io::Result<PodmanSettings> {
+        let podman = &config["podman"];
+        let units = match podman["units"].as_str().unwrap_or("quadlet") {
+            "quadlet" => PodmanUnits::Quadlet,
+            "systemd" => PodmanUnits::Systemd,
+            units => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown podman.units: {} (use quadlet or systemd)", units),
+                ))
+            }
+        };
+
+        Ok(PodmanSettings {
+            units,
+            image_tag: podman["image_tag"].as_str().unwrap_or("latest").to_string(),
+            auto_update: podman["auto_update"].as_bool().unwrap_or(true),
+        })
+    }
+}
+
+/// A service of the project as it runs under Podman.
+struct PodmanService {
+    name: String,
+    image: String,
+    container_port: Option<u16>,
+    port: u64,
+    /// The environment variables set by the project type, e.g. `NODE_ENV`.
+    env: Vec<(String, String)>,
+    /// The shell command of the healthcheck, if enabled.
+    healthcheck: Option<String>,
+    /// Whether the service joins the API network.
+    api_network: bool,
+}
+
+/// Turns a compose healthcheck test into the shell command Podman runs, unwrapping the
+/// `["CMD-SHELL", ...]` exec form.
+fn to_shell_command(test: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(test) {
+        Ok(parts) if parts.first().map(String::as_str) == Some("CMD-SHELL") => parts[1..].join(" "),
+        Ok(parts) if parts.first().map(String::as_str) == Some("CMD") => parts[1..].join(" "),
+        _ => test.to_string(),
+    }
+}
+
+/// Escapes the `%` specifiers of systemd in a unit value.
+fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Quotes a value for a unit setting that systemd splits on whitespace.
+fn quote_unit_value(value: &str) -> String {
+    let value = escape_specifiers(value);
+    if value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\' || c == ';') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value
+    }
+}
+
+/// Reads the services of the project.
+fn get_podman_services(
+    config: &Value,
+    settings: &PodmanSettings,
+) -> io::Result<Vec<PodmanService>> {
+    // Podman hosts have no Docker to build the images with, they pull the pushed ones
+    let image_registry = ImageRegistrySettings::from_config(config)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The podman target needs an image_registry the hosts pull the images from",
+        )
+    })?;
+    let service_configs = if config["project_type"].as_str() == Some("monorepo") {
+        get_monorepo_services(config)?.iter().collect()
+    } else {
+        vec![config]
+    };
+
+    let compose_services = get_compose_services(config)?;
+    let mut services = Vec::new();
+    for (service, compose_service) in service_configs.into_iter().zip(compose_services) {
+        // The name becomes the name of the unit file
+        if compose_service.name.is_empty()
+            || !compose_service
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "service_name {:?} is not a valid unit name (use letters, digits, dashes, underscores and dots)",
+                    compose_service.name
+                ),
+            ));
+        }
+
+        let image_name = service["image_name"].as_str().unwrap_or("default_image");
+        let image = format!(
+            "{}/{}:{}",
+            image_registry.url, image_name, settings.image_tag
+        );
+
+        let angular = service["project_type"].as_str() == Some("angular");
+        let healthcheck = if !angular && service["enable_healthcheck"].as_bool().unwrap_or(false) {
+            Some(to_shell_command(&get_dotnet_healthcheck_test(service)?))
+        } else {
+            None
+        };
+
+        services.push(PodmanService {
+            name: compose_service.name,
+            image,
+            container_port: compose_service.container_port,
+            port: compose_service.port,
+            env: if angular {
+                vec![("NODE_ENV".to_string(), "production".to_string())]
+            } else {
+                Vec::new()
+            },
+            healthcheck,
+            api_network: !angular,
+        });
+    }
+
+    Ok(services)
+}
+
+/// Renders the Quadlet `.container` file of a service.
+fn render_container_unit(
+    service: &PodmanService,
+    settings: &PodmanSettings,
+    port: u64,
+    env: &[(String, String)],
+) -> String {
+    let mut container = format!(
+        "ContainerName={name}\nImage={image}\n",
+        name = service.name,
+        image = service.image
+    );
+    if settings.auto_update {
+        container.push_str("AutoUpdate=registry\n");
+    }
+    if service.api_network {
+        container.push_str(&format!("Network={}.network\n", API_NETWORK));
+    }
+    if let Some(container_port) = service.container_port {
+        container.push_str(&format!("PublishPort={}:{}\n", port, container_port));
+    }
+    for (key, value) in env {
+        container.push_str(&format!(
+            "Environment={}\n",
+            quote_unit_value(&format!("{}={}", key, value))
+        ));
+    }
+    // An unhealthy container is killed, so systemd restarts it
+    if let Some(healthcheck) = &service.healthcheck {
+        container.push_str(&format!(
+            "HealthCmd={}\nHealthInterval=40s\nHealthTimeout=30s\nHealthRetries=3\nHealthStartPeriod=60s\nHealthOnFailure=kill\n",
+            escape_specifiers(healthcheck)
+        ));
+    }
+
+    format!(
+        r#"# The {name} service, generated by fileforge.
+[Unit]
+Description={name}
+Wants=network-online.target
+After=network-online.target
+
+[Container]
+{container}
+[Service]
+Restart=always
+# Pulling the image can take longer than the default start timeout
+TimeoutStartSec=900
+
+[Install]
+WantedBy=multi-user.target
+"#,
+        name = service.name,
+        container = container
+    )
+}
+
+/// Renders the systemd unit of a service running `podman run`, like `podman generate systemd
+/// --new` does.
+fn render_systemd_unit(
+    service: &PodmanService,
+    settings: &PodmanSettings,
+    port: u64,
+    env: &[(String, String)],
+) -> String {
+    let mut arguments = vec![
+        "--cidfile=%t/%n.ctr-id".to_string(),
+        "--cgroups=no-conmon".to_string(),
+        "--rm".to_string(),
+        "--sdnotify=conmon".to_string(),
+        "--replace".to_string(),
+        "-d".to_string(),
+        format!("--name {}", service.name),
+    ];
+    if settings.auto_update {
+        arguments.push("--label io.containers.autoupdate=registry".to_string());
+    }
+    if service.api_network {
+        arguments.push(format!("--network {}", API_NETWORK));
+    }
+    if let Some(container_port) = service.container_port {
+        arguments.push(format!("-p {}:{}", port, container_port));
+    }
+    // systemd expands `$` in the command line, so it is doubled
+    for (key, value) in env {
+        arguments.push(format!(
+            "-e {}",
+            quote_unit_value(&format!("{}={}", key, value)).replace('$', "$$")
+        ));
+    }
+    if let Some(healthcheck) = &service.healthcheck {
+        arguments.push(format!(
+            "--health-cmd {}",
+            quote_unit_value(healthcheck).replace('$', "$$")
+        ));
+        arguments.push("--health-interval 40s".to_string());
+        arguments.push("--health-timeout 30s".to_string());
+        arguments.push("--health-retries 3".to_string());
+        arguments.push("--health-start-period 60s".to_string());
+        arguments.push("--health-on-failure kill".to_string());
+    }
+    arguments.push(service.image.clone());
+
+    let network = if service.api_network {
+        format!(
+            "ExecStartPre=-/usr/bin/podman network create --ignore {}\n",
+            API_NETWORK
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"# The {name} service, generated by fileforge.
+[Unit]
+Description={name}
+Wants=network-online.target
+After=network-online.target
+RequiresMountsFor=%t/containers
+
+[Service]
+Environment=PODMAN_SYSTEMD_UNIT=%n
+Restart=always
+# Pulling the image can take longer than the default start timeout
+TimeoutStartSec=900
+TimeoutStopSec=70
+{network}ExecStart=/usr/bin/podman run \
+    {arguments}
+ExecStop=/usr/bin/podman stop --ignore -t 10 --cidfile=%t/%n.ctr-id
+ExecStopPost=/usr/bin/podman rm -f --ignore -t 10 --cidfile=%t/%n.ctr-id
+Type=notify
+NotifyAccess=all
+
+[Install]
+WantedBy=multi-user.target
+"#,
+        name = service.name,
+        network = network,
+        arguments = arguments.join(" \\\n    ")
+    )
+}
+
+const NETWORK_UNIT: &str = r#"# The network the .NET services share, generated by fileforge.
+[Network]
+NetworkName=[[ network ]]
+"#;
+
+const PODMAN_PLAYBOOK_CONTENT: &str = r#"---
+- name: Install the Podman units and start the services
+  hosts: target_servers
+[[ play_options ]]  become: yes
+  tasks:
+[[ login_task ]][[ install_tasks ]]
+    - name: Reload systemd to pick up the units
+      ansible.builtin.systemd:
+        daemon_reload: true
+
+    - name: Restart the services
+      ansible.builtin.systemd:
+        name: "{{ item }}"
+        state: restarted
+[[ enabled ]]      loop:
+[[ services ]][[ auto_update_tasks ]]"#;
+
+const REGISTRY_LOGIN_TASK: &str = r#"    - name: Log in to the image registry
+      ansible.builtin.shell:
+        cmd: echo "$IMAGE_REGISTRY_PASSWORD" | podman login "[[ host ]]" -u "$IMAGE_REGISTRY_USERNAME" --password-stdin
+      environment:
+[[ environment ]]      when: image_registry_password | default('') | length > 0
+      no_log: true
+
+"#;
+
+const AUTO_UPDATE_TASKS: &str = r#"
+    - name: Pull the newer images and restart their services
+      ansible.builtin.command: podman auto-update
+
+    - name: Check for newer images every day
+      ansible.builtin.systemd:
+        name: podman-auto-update.timer
+        enabled: true
+        state: started
+"#;
+
+/// Returns the directory of the units of an environment, e.g. `podman/staging`, or `podman` for
+/// the implicit environment.
+fn units_directory(environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!("{}/{}", PODMAN_DIRECTORY, environment.name)
+    } else {
+        PODMAN_DIRECTORY.to_string()
+    }
+}
+
+/// Renders the task copying the units of an environment to the servers of its host group.
+fn render_install_task(environment: &DeployEnvironment, settings: &PodmanSettings) -> String {
+    let mut task = format!(
+        r#"    - name: Install the units{of}
+      ansible.builtin.copy:
+        src: "{{{{ playbook_dir }}}}/../{directory}/"
+        dest: {install_directory}/
+        mode: '0644'
+"#,
+        of = if environment.configured {
+            format!(" of {}", environment.name)
+        } else {
+            String::new()
+        },
+        directory = units_directory(environment),
+        install_directory = settings.units.install_directory()
+    );
+    if environment.configured {
+        task.push_str(&format!(
+            "      when: \"'{}' in group_names\"\n",
+            environment.host_group
+        ));
+    }
+    task
+}
+
+/// Generates the Podman units of every service into the `podman` directory, one directory per
+/// configured environment, and the `ansible/podman-deploy.yml` playbook installing them.
+///
+/// The units publish the ports and set the `env` overrides of their environment. The replica
+/// counts only apply to docker compose.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid or a file cannot be written.
+pub fn generate_podman_units() -> io::Result<()> {
+    println!("🚀 Starting Podman units generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let settings = PodmanSettings::from_config(&config)?;
+    let services = get_podman_services(&config, &settings)?;
+    let compose_services = get_compose_services(&config)?;
+    let environments = get_deploy_environments(&config)?;
+    let output_dir = get_output_directory(&current_dir);
+
+    for environment in &environments {
+        println!(
+            "🦭 Writing the units of {}...",
+            units_directory(environment)
+        );
+        thread::sleep(SLEEP_DURATION);
+
+        let directory = output_dir.join(units_directory(environment));
+        for (service, compose_service) in services.iter().zip(&compose_services) {
+            let port = environment
+                .port_override(compose_service, &compose_services)
+                .unwrap_or(service.port);
+            let mut env = service.env.clone();
+            env.extend(environment.env_overrides(&service.name));
+
+            let (file_name, unit) = match settings.units {
+                PodmanUnits::Quadlet => (
+                    format!("{}.container", service.name),
+                    render_container_unit(service, &settings, port, &env),
+                ),
+                PodmanUnits::Systemd => (
+                    format!("{}.service", service.name),
+                    render_systemd_unit(service, &settings, port, &env),
+                ),
+            };
+            write_file_with_backup(&directory.join(file_name), &unit)?;
+        }
+
+        if matches!(settings.units, PodmanUnits::Quadlet)
+            && services.iter().any(|service| service.api_network)
+        {
+            write_file_with_backup(
+                &directory.join(format!("{}.network", API_NETWORK)),
+                &NETWORK_UNIT.replace("[[ network ]]", API_NETWORK),
+            )?;
+        }
+    }
+
+    println!("📋 Writing the Podman playbook...");
+    thread::sleep(SLEEP_DURATION);
+
+    let ansible_dir = output_dir.join("ansible");
+    write_ansible_inventory(&config, &ansible_dir)?;
+
+    let login_task = match ImageRegistrySettings::from_config(&config)? {
+        Some(image_registry) => REGISTRY_LOGIN_TASK
+            .replace("[[ host ]]", image_registry.host())
+            .replace("[[ environment ]]", IMAGE_REGISTRY_PLAYBOOK_ENVIRONMENT),
+        None => String::new(),
+    };
+    let playbook = PODMAN_PLAYBOOK_CONTENT
+        .replace(
+            "[[ play_options ]]",
+            &InventorySettings::from_config(&config)?.render_play_options(),
+        )
+        .replace("[[ login_task ]]", &login_task)
+        .replace(
+            "[[ install_tasks ]]",
+            &environments
+                .iter()
+                .map(|environment| render_install_task(environment, &settings))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )
+        // Units generated by Quadlet start at boot through their `[Install]` section instead
+        .replace(
+            "[[ enabled ]]",
+            match settings.units {
+                PodmanUnits::Quadlet => "",
+                PodmanUnits::Systemd => "        enabled: true\n",
+            },
+        )
+        .replace(
+            "[[ services ]]",
+            &services
+                .iter()
+                .map(|service| format!("        - {}.service\n", service.name))
+                .collect::<String>(),
+        )
+        .replace(
+            "[[ auto_update_tasks ]]",
+            if settings.auto_update {
+                AUTO_UPDATE_TASKS
+            } else {
+                ""
+            },
+        );
+    write_file_with_backup(&output_dir.join(PODMAN_PLAYBOOK), &playbook)?;
+
+    println!(
+        "🎉 Generated the Podman units of {} service(s) in {:?}",
+        services.len(),
+        output_dir.join(PODMAN_DIRECTORY)
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
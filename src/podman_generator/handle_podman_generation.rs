@@ -0,0 +1,22 @@
+use std::{io, thread};
+
+use crate::constants::SLEEP_DURATION;
+use crate::podman_generator::generate_podman_units::generate_podman_units;
+
+pub fn handle_podman_generation() -> io::Result<()> {
+    println!("\n🚀 Starting Podman Generator...");
+
+    println!("\n🔧 Generating Podman units...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_podman_units() {
+        eprintln!("❌ Error: Failed to generate Podman units. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ Podman units generated successfully!");
+
+    println!(
+        "\n🎉 Podman Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,2 @@
+pub mod generate_podman_units;
+pub mod handle_podman_generation;
@@ -8,7 +8,7 @@ pub fn check_git_status() -> io::Result<()> {
         .output()
         .map_err(|e| {
             eprintln!("❌ Failed to execute `git status`: {}", e);
-            io::Error::new(io::ErrorKind::Other, "Failed to execute git status")
+            io::Error::other("Failed to execute git status")
         })?;
 
     // Convert the command output to a string
@@ -1,4 +1,6 @@
 pub mod shared_input_helper;
 pub mod directory_helper;
 pub mod check_git_status;
-pub mod get_current_config;
\ No newline at end of file
+pub mod get_current_config;
+pub mod output_helper;
+pub mod yaml_helper;
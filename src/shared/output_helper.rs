@@ -0,0 +1,76 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Determines the directory generated files are written to.
+///
+/// Debug builds write into `testing_directory/dotnet-testing` so that running fileforge from the
+/// repository never overwrites real project files.
+///
+/// # Parameters
+/// - `current_dir`: The path to the current directory.
+///
+/// # Returns
+/// - `PathBuf`: The output directory.
+pub fn get_output_directory(current_dir: &Path) -> PathBuf {
+    if cfg!(debug_assertions) {
+        current_dir.join("testing_directory").join("dotnet-testing")
+    } else {
+        current_dir.to_path_buf()
+    }
+}
+
+/// Writes a generated file, backing up any existing file to `<file name>.backup` first.
+///
+/// # Parameters
+/// - `output_path`: The path of the file to write. Missing parent directories are created.
+/// - `content`: The content to write.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the backup or the write fails.
+pub fn write_file_with_backup(output_path: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Check if the file exists and create a backup
+    if output_path.exists() {
+        let mut backup_name = output_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        backup_name.push(".backup");
+        let backup_path = output_path.with_file_name(backup_name);
+
+        println!("📂 Backing up {:?} to {:?}...", output_path, backup_path);
+        fs::rename(output_path, &backup_path)?;
+        println!("✅ Backup created successfully.");
+    }
+
+    let mut output_file = File::create(output_path)?;
+    output_file.write_all(content.as_bytes())?;
+    println!("✅ File written to: {:?}", output_path);
+
+    Ok(())
+}
+
+/// Marks a generated script as executable. Other platforms have no executable bit to set.
+///
+/// # Parameters
+/// - `path`: The path of the script.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the permissions cannot be changed.
+pub fn make_executable(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
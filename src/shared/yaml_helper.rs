@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+/// Renders a JSON value as a block-style YAML document.
+///
+/// Strings that YAML would read as another type, or that contain special characters, are written
+/// as double-quoted JSON strings, which are valid YAML.
+///
+/// # Parameters
+/// - `value`: The value to render.
+///
+/// # Returns
+/// - `String`: The YAML document, ending with a newline.
+pub fn render_yaml(value: &Value) -> String {
+    let mut yaml = String::new();
+    match value {
+        Value::Object(object) if !object.is_empty() => render_object(value, 0, &mut yaml),
+        Value::Array(items) if !items.is_empty() => render_array(value, 0, &mut yaml),
+        _ => {
+            yaml.push_str(&render_scalar(value));
+            yaml.push('\n');
+        }
+    }
+    yaml
+}
+
+/// Renders the entries of a non-empty object, each on its own line at `indent`.
+fn render_object(value: &Value, indent: usize, yaml: &mut String) {
+    for (key, item) in value.as_object().into_iter().flatten() {
+        yaml.push_str(&" ".repeat(indent));
+        yaml.push_str(&render_string(key));
+        yaml.push(':');
+        render_nested(item, indent + 2, yaml);
+    }
+}
+
+/// Renders the items of a non-empty array, each starting with `- ` at `indent`.
+fn render_array(value: &Value, indent: usize, yaml: &mut String) {
+    for item in value.as_array().into_iter().flatten() {
+        yaml.push_str(&" ".repeat(indent));
+        yaml.push('-');
+        match item {
+            // The first entry of an object shares the line of the dash
+            Value::Object(object) if !object.is_empty() => {
+                let mut nested = String::new();
+                render_object(item, indent + 2, &mut nested);
+                yaml.push(' ');
+                yaml.push_str(&nested[indent + 2..]);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                yaml.push('\n');
+                render_array(item, indent + 2, yaml);
+            }
+            _ => render_nested(item, indent + 2, yaml),
+        }
+    }
+}
+
+/// Renders the value of an entry after its key or dash: scalars on the same line, non-empty
+/// objects and arrays on the following lines.
+fn render_nested(value: &Value, indent: usize, yaml: &mut String) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            yaml.push('\n');
+            render_object(value, indent, yaml);
+        }
+        // Arrays of an object entry sit at the indentation of the key, as in kubectl output
+        Value::Array(items) if !items.is_empty() => {
+            yaml.push('\n');
+            render_array(value, indent.saturating_sub(2), yaml);
+        }
+        _ => {
+            yaml.push(' ');
+            yaml.push_str(&render_scalar(value));
+            yaml.push('\n');
+        }
+    }
+}
+
+/// Renders a scalar, or an empty object or array in flow style.
+fn render_scalar(value: &Value) -> String {
+    match value {
+        Value::String(string) => render_string(string),
+        Value::Object(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Renders a string plain when YAML reads it back unchanged, and double-quoted otherwise.
+fn render_string(string: &str) -> String {
+    let reserved = [
+        "true", "false", "yes", "no", "on", "off", "y", "n", "null", "~",
+    ];
+    let plain = string
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '/' || first == '_')
+        && string
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c))
+        && !reserved.contains(&string.to_lowercase().as_str());
+
+    if plain {
+        string.to_string()
+    } else {
+        serde_json::to_string(string).unwrap_or_default()
+    }
+}
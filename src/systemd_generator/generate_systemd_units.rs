@@ -0,0 +1,470 @@
+use std::{env, io, thread};
+
+use serde_json::Value;
+
+use crate::{
+    ci_generator::{
+        ansible_inventory::{write_ansible_inventory, InventorySettings},
+        deploy_environment::{get_compose_services, get_deploy_environments, DeployEnvironment},
+    },
+    constants::SLEEP_DURATION,
+    monorepo_generator::get_monorepo_services::get_monorepo_services,
+    shared::{
+        get_current_config::get_current_config,
+        output_helper::{get_output_directory, write_file_with_backup},
+    },
+};
+
+/// The directory the units and environment files are written to.
+pub const SYSTEMD_DIRECTORY: &str = "systemd";
+
+/// The playbook publishing the services and installing their units.
+pub const SYSTEMD_PLAYBOOK: &str = "ansible/systemd-deploy.yml";
+
+/// The bare-metal settings of the project, read from the optional `systemd` section of the
+/// configuration.
+pub struct SystemdSettings {
+    /// The user the services run as, the service name by default.
+    pub user: Option<String>,
+    /// Whether the services are published with the .NET runtime, so the servers need none.
+    pub self_contained: bool,
+    /// The runtime identifier of self-contained releases, e.g. `linux-x64`.
+    pub runtime_identifier: String,
+    /// The `dotnet` host running framework-dependent releases.
+    pub dotnet_path: String,
+    /// The `GOARCH` Go binaries are built for, e.g. `amd64`.
+    pub go_arch: String,
+    /// The main package of Go projects, relative to the repository root.
+    pub go_package: String,
+}
+
+impl SystemdSettings {
+    /// Reads the bare-metal settings.
+    ///
+    /// # Parameters
+    /// - `config`: The project configuration.
+    ///
+    /// # Returns
+    /// - `io::Result<SystemdSettings>`: The settings, or an error if the user is invalid.
+    pub fn from_config(config: &Value) -> io::Result<SystemdSettings> {
+        let systemd = &config["systemd"];
+        let user = systemd["user"].as_str().map(str::to_string);
+        if let Some(user) = &user {
+            check_unit_name("systemd.user", user)?;
+        }
+
+        Ok(SystemdSettings {
+            user,
+            self_contained: systemd["self_contained"].as_bool().unwrap_or(false),
+            runtime_identifier: systemd["runtime_identifier"]
+                .as_str()
+                .unwrap_or("linux-x64")
+                .to_string(),
+            dotnet_path: systemd["dotnet_path"]
+                .as_str()
+                .unwrap_or("/usr/bin/dotnet")
+                .to_string(),
+            go_arch: systemd["go_arch"].as_str().unwrap_or("amd64").to_string(),
+            go_package: systemd["go_package"].as_str().unwrap_or(".").to_string(),
+        })
+    }
+}
+
+/// How the release of a service is built and started.
+enum SystemdRuntime {
+    /// A .NET project published with `dotnet publish`.
+    Dotnet {
+        /// The project file, relative to the repository root.
+        csproj: String,
+        /// The assembly name, the name of the project file without `.csproj`.
+        assembly: String,
+    },
+    /// A Go program built with `go build` into a binary named after the service.
+    Go,
+}
+
+/// A .NET or Go service run directly by systemd.
+struct SystemdService {
+    name: String,
+    /// The user and group the service runs as.
+    user: String,
+    runtime: SystemdRuntime,
+    /// The directory the release is copied to on the servers.
+    working_directory: String,
+    /// Whether the service listens on the port of `ASPNETCORE_URLS`, or `PORT` for Go.
+    listens: bool,
+}
+
+/// Checks that a name can be used for a unit file and a system user.
+fn check_unit_name(setting: &str, name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !name.starts_with('-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} {:?} is not a valid unit or user name (use letters, digits, dashes and underscores)",
+                setting, name
+            ),
+        ))
+    }
+}
+
+const SERVICE_UNIT: &str = r#"# The [[ name ]] service, generated by fileforge.
+[Unit]
+Description=[[ name ]]
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=simple
+User=[[ user ]]
+Group=[[ user ]]
+WorkingDirectory=[[ working_directory ]]
+ExecStart=[[ exec_start ]]
+EnvironmentFile=-[[ working_directory ]]/[[ name ]].env
+Restart=on-failure
+RestartSec=5
+TimeoutStopSec=30
+SyslogIdentifier=[[ name ]]
+
+# The release is read-only for the service, which only writes its logs
+NoNewPrivileges=true
+ProtectSystem=strict
+ProtectHome=true
+PrivateTmp=true
+PrivateDevices=true
+ProtectKernelTunables=true
+ProtectKernelModules=true
+ProtectControlGroups=true
+RestrictSUIDSGID=true
+LockPersonality=true
+ReadWritePaths=[[ working_directory ]]/Logs
+
+[Install]
+WantedBy=multi-user.target
+"#;
+
+const SYSTEMD_PLAYBOOK_CONTENT: &str = r#"---
+- name: Publish the services and install their systemd units
+  hosts: target_servers
+[[ play_options ]]  become: yes
+  tasks:
+[[ service_tasks ]]"#;
+
+const DOTNET_PUBLISH_TASK: &str = r#"    - name: Publish [[ name ]]
+      ansible.builtin.command:
+        cmd: dotnet publish "{{ playbook_dir }}/../[[ csproj ]]" -c Release -o "{{ playbook_dir }}/../publish/[[ name ]]" [[ publish_options ]]
+      delegate_to: localhost
+      run_once: true
+      become: false
+"#;
+
+const GO_BUILD_TASK: &str = r#"    - name: Build [[ name ]]
+      ansible.builtin.command:
+        cmd: go build -trimpath -o "{{ playbook_dir }}/../publish/[[ name ]]/[[ name ]]" [[ go_package ]]
+        chdir: "{{ playbook_dir }}/.."
+      environment:
+        GOOS: linux
+        GOARCH: "[[ go_arch ]]"
+        CGO_ENABLED: "0"
+      delegate_to: localhost
+      run_once: true
+      become: false
+"#;
+
+const SERVICE_TASKS: &str = r#"[[ build_task ]]
+    - name: Create the [[ user ]] user
+      ansible.builtin.user:
+        name: "[[ user ]]"
+        system: true
+        shell: /usr/sbin/nologin
+        create_home: false
+
+    - name: Copy the release of [[ name ]]
+      ansible.builtin.copy:
+        src: "{{ playbook_dir }}/../publish/[[ name ]]/"
+        dest: "[[ working_directory ]]/"
+        owner: root
+        group: root
+        mode: '0755'
+
+    - name: Ensure the log directory of [[ name ]] exists
+      ansible.builtin.file:
+        path: "[[ working_directory ]]/Logs"
+        state: directory
+        owner: "[[ user ]]"
+        group: "[[ user ]]"
+        mode: '0755'
+[[ environment_tasks ]]
+    - name: Install the [[ name ]] unit
+      ansible.builtin.copy:
+        src: "{{ playbook_dir }}/../systemd/[[ name ]].service"
+        dest: "/etc/systemd/system/[[ name ]].service"
+        mode: '0644'
+
+    - name: Restart [[ name ]]
+      ansible.builtin.systemd:
+        name: "[[ name ]].service"
+        state: restarted
+        enabled: true
+        daemon_reload: true
+"#;
+
+/// Returns the environment file of a service for an environment, relative to the `systemd`
+/// directory, e.g. `shop-api.staging.env`.
+fn environment_file(service: &SystemdService, environment: &DeployEnvironment) -> String {
+    if environment.configured {
+        format!("{}.{}.env", service.name, environment.name)
+    } else {
+        format!("{}.env", service.name)
+    }
+}
+
+/// Renders the task copying the environment file of a service to the servers of an environment.
+fn render_environment_task(service: &SystemdService, environment: &DeployEnvironment) -> String {
+    let mut task = format!(
+        r#"
+    - name: Write the environment of [[ name ]]{of}
+      ansible.builtin.copy:
+        src: "{{{{ playbook_dir }}}}/../systemd/{file}"
+        dest: "[[ working_directory ]]/[[ name ]].env"
+        owner: root
+        group: "[[ user ]]"
+        mode: '0640'
+"#,
+        of = if environment.configured {
+            format!(" for {}", environment.name)
+        } else {
+            String::new()
+        },
+        file = environment_file(service, environment)
+    );
+    if environment.configured {
+        task.push_str(&format!(
+            "      when: \"'{}' in group_names\"\n",
+            environment.host_group
+        ));
+    }
+    task
+}
+
+/// Generates a systemd unit per .NET or Go service into the `systemd` directory, with an environment
+/// file per environment, and the `ansible/systemd-deploy.yml` playbook publishing the services
+/// and installing their units.
+///
+/// Go projects have the `go` project type and are built into a static binary for the servers.
+/// The services run without containers from `project_location/project_directory`, or a
+/// directory per service below it in monorepos. Angular and Blazor WebAssembly apps are served
+/// by nginx in their container, so they are left out.
+///
+/// # Returns
+/// - `io::Result<()>`: An error if the configuration is invalid, the project has no .NET or Go
+///   service to run or a file cannot be written.
+pub fn generate_systemd_units() -> io::Result<()> {
+    println!("🚀 Starting systemd units generation...");
+    thread::sleep(SLEEP_DURATION);
+
+    // Get the current directory
+    let current_dir = env::current_dir()?;
+    println!("📂 Current directory: {:?}", current_dir);
+    thread::sleep(SLEEP_DURATION);
+
+    let config = get_current_config(current_dir.clone());
+    let settings = SystemdSettings::from_config(&config)?;
+    let project_location = config["project_location"].as_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing project_location in config",
+        )
+    })?;
+    let project_directory = config["project_directory"].as_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing project_directory in config",
+        )
+    })?;
+    let project_path = format!("{}/{}", project_location, project_directory);
+
+    let monorepo = config["project_type"].as_str() == Some("monorepo");
+    let service_configs = if monorepo {
+        get_monorepo_services(&config)?.iter().collect()
+    } else {
+        vec![&config]
+    };
+    let compose_services = get_compose_services(&config)?;
+
+    let mut services = Vec::new();
+    for (service, compose_service) in service_configs.into_iter().zip(&compose_services) {
+        let app_type = service["app_type"].as_str().unwrap_or("web");
+        if service["project_type"].as_str() == Some("angular") || app_type == "blazor_wasm" {
+            println!(
+                "⚠️  Skipping {}: it is served by nginx in its container.",
+                compose_service.name
+            );
+            continue;
+        }
+        check_unit_name("service_name", &compose_service.name)?;
+
+        let runtime = if service["project_type"].as_str() == Some("go") {
+            SystemdRuntime::Go
+        } else {
+            let assembly = service["project_directory"]
+                .as_str()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Missing project_directory of {}", compose_service.name),
+                    )
+                })?
+                .to_string();
+            let csproj = match service["path"].as_str() {
+                Some(path) if monorepo => {
+                    format!("{}/{}.csproj", path.trim_end_matches('/'), assembly)
+                }
+                _ => format!("{}.csproj", assembly),
+            };
+            SystemdRuntime::Dotnet { csproj, assembly }
+        };
+
+        services.push((
+            SystemdService {
+                name: compose_service.name.clone(),
+                user: settings
+                    .user
+                    .clone()
+                    .unwrap_or_else(|| compose_service.name.clone()),
+                runtime,
+                working_directory: if monorepo {
+                    format!("{}/{}", project_path, compose_service.name)
+                } else {
+                    project_path.clone()
+                },
+                listens: compose_service.container_port.is_some(),
+            },
+            compose_service,
+        ));
+    }
+    if services.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The systemd target needs a .NET web app or worker service, or a Go project",
+        ));
+    }
+
+    let environments = get_deploy_environments(&config)?;
+    let output_dir = get_output_directory(&current_dir);
+    let systemd_dir = output_dir.join(SYSTEMD_DIRECTORY);
+
+    for (service, compose_service) in &services {
+        println!("⚙️  Writing the unit of {}...", service.name);
+        thread::sleep(SLEEP_DURATION);
+
+        let exec_start = match &service.runtime {
+            SystemdRuntime::Dotnet { assembly, .. } if settings.self_contained => {
+                format!("{}/{}", service.working_directory, assembly)
+            }
+            SystemdRuntime::Dotnet { assembly, .. } => format!(
+                "{} {}/{}.dll",
+                settings.dotnet_path, service.working_directory, assembly
+            ),
+            SystemdRuntime::Go => format!("{}/{}", service.working_directory, service.name),
+        };
+        write_file_with_backup(
+            &systemd_dir.join(format!("{}.service", service.name)),
+            &SERVICE_UNIT
+                .replace("[[ name ]]", &service.name)
+                .replace("[[ user ]]", &service.user)
+                .replace("[[ working_directory ]]", &service.working_directory)
+                .replace("[[ exec_start ]]", &exec_start),
+        )?;
+
+        // Kestrel, or the Go program through `PORT`, listens on the published port, which
+        // environments may override
+        for environment in &environments {
+            let mut env = Vec::new();
+            if service.listens {
+                let port = environment
+                    .port_override(compose_service, &compose_services)
+                    .unwrap_or(compose_service.port);
+                env.push(match service.runtime {
+                    SystemdRuntime::Dotnet { .. } => (
+                        "ASPNETCORE_URLS".to_string(),
+                        format!("http://0.0.0.0:{}", port),
+                    ),
+                    SystemdRuntime::Go => ("PORT".to_string(), port.to_string()),
+                });
+            }
+            env.extend(environment.env_overrides(&service.name));
+
+            let content = env
+                .iter()
+                .map(|(key, value)| format!("{}={}\n", key, Value::String(value.clone())))
+                .collect::<String>();
+            write_file_with_backup(
+                &systemd_dir.join(environment_file(service, environment)),
+                &content,
+            )?;
+        }
+    }
+
+    println!("📋 Writing the systemd playbook...");
+    thread::sleep(SLEEP_DURATION);
+
+    let ansible_dir = output_dir.join("ansible");
+    write_ansible_inventory(&config, &ansible_dir)?;
+
+    let publish_options = if settings.self_contained {
+        format!("-r {} --self-contained", settings.runtime_identifier)
+    } else {
+        "/p:UseAppHost=false".to_string()
+    };
+    let service_tasks = services
+        .iter()
+        .map(|(service, _)| {
+            let build_task = match &service.runtime {
+                SystemdRuntime::Dotnet { csproj, .. } => DOTNET_PUBLISH_TASK
+                    .replace("[[ csproj ]]", csproj)
+                    .replace("[[ publish_options ]]", &publish_options),
+                SystemdRuntime::Go => GO_BUILD_TASK
+                    .replace("[[ go_package ]]", &settings.go_package)
+                    .replace("[[ go_arch ]]", &settings.go_arch),
+            };
+            SERVICE_TASKS
+                .replace("[[ build_task ]]", &build_task)
+                .replace(
+                    "[[ environment_tasks ]]",
+                    &environments
+                        .iter()
+                        .map(|environment| render_environment_task(service, environment))
+                        .collect::<String>(),
+                )
+                .replace("[[ name ]]", &service.name)
+                .replace("[[ user ]]", &service.user)
+                .replace("[[ working_directory ]]", &service.working_directory)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let playbook = SYSTEMD_PLAYBOOK_CONTENT
+        .replace(
+            "[[ play_options ]]",
+            &InventorySettings::from_config(&config)?.render_play_options(),
+        )
+        .replace("[[ service_tasks ]]", &service_tasks);
+    write_file_with_backup(&output_dir.join(SYSTEMD_PLAYBOOK), &playbook)?;
+
+    println!(
+        "🎉 Generated the systemd units of {} service(s) in {:?}",
+        services.len(),
+        systemd_dir
+    );
+    thread::sleep(SLEEP_DURATION);
+
+    Ok(())
+}
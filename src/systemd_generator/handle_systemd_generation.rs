@@ -0,0 +1,22 @@
+use std::{io, thread};
+
+use crate::constants::SLEEP_DURATION;
+use crate::systemd_generator::generate_systemd_units::generate_systemd_units;
+
+pub fn handle_systemd_generation() -> io::Result<()> {
+    println!("\n🚀 Starting systemd Generator...");
+
+    println!("\n🔧 Generating systemd units...");
+    thread::sleep(SLEEP_DURATION); // Simulate progress
+    if let Err(e) = generate_systemd_units() {
+        eprintln!("❌ Error: Failed to generate systemd units. Details: {}", e);
+        std::process::exit(1);
+    }
+    println!("✅ systemd units generated successfully!");
+
+    println!(
+        "\n🎉 systemd Generator completed successfully! All required files have been generated.\n"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,2 @@
+pub mod generate_systemd_units;
+pub mod handle_systemd_generation;